@@ -1,210 +1,5251 @@
 use cosmwasm_std::{
-    entry_point, to_binary, Binary, Deps, DepsMut, Env, MessageInfo, Response, StdResult,
+    entry_point, from_slice, to_binary, Addr, Binary, ContractResult, Deps, DepsMut, Env, MessageInfo, Order, Reply, ReplyOn, Response, StdError, StdResult, Storage, Uint128, WasmMsg,
 };
+use cosmwasm_storage::to_length_prefixed;
+use cw_storage_plus::Bound;
 
 use crate::error::ContractError;
-use crate::msg::{ExecuteMsg, InstantiateMsg, QueryMsg, ConfigResponse, RefDataResponse, ReferenceData};
-use crate::state::{RefData, State, config, config_read};
-use std::collections::HashMap;
-use num::BigUint;
+use crate::msg::{ExecuteMsg, InstantiateMsg, MigrateMsg, OwnershipCallbackMsg, QueryMsg, BasketValue, PartialBulkResponse, RelayEntry, RelayPayload, RelayResponse, SignedRelayPayload, ConfigResponse, LabeledReferenceData, MedianReferenceData, RefDataResponse, ReferenceData, ReferenceDataFull, ReferenceDataResult, ReferenceDataSplit, ReferenceDataVerbose, ReferenceDataWithAge, SignedReferenceData, ReferenceDataStatus, RefStatus, RawReferenceData, RefsPageResponse, SymbolsPageResponse, RelayersResponse, OwnershipResponse, LastRelayResponse, HealthResponse, FullConfigResponse, TwapResponse};
+use crate::pricing::{compute_reference_data, mul_div, ratio, scale, usd_ref_data};
+use crate::state::{CONFIG_KEY, LegacyState, RateSample, RefData, RoundingMode, State, UsdTimestampMode, ALIASES, DEFAULT_MAX_BATCH_SIZE, DENOMS, DEFAULT_TWAP_SAMPLES, LAST_WRITES, MAX_TWAP_SAMPLES, NANOS_PER_SECOND, PENDING_CONTRACT_OWNER, REFS, RESOLVE_TIMES, SAMPLES, SIGNER_NONCES, config, config_read, initial_config_read, legacy_config, schema_version, schema_version_read};
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use sha2::{Digest, Sha256};
+use cw2::{get_contract_version, set_contract_version, CONTRACT};
+
+const CONTRACT_NAME: &str = "crates.io:std-reference";
+const CONTRACT_VERSION: &str = env!("CARGO_PKG_VERSION");
+
+type MigrationStep = fn(DepsMut, &MigrateMsg) -> Result<(), ContractError>;
+
+/// Storage layout steps in order; step `n` upgrades schema version `n + 1` to `n + 2`.
+/// Schema 1 is the original refs-only blob, 2 the 0.2.x blob with an owner and config, 3 moved the
+/// refs to `REFS`, 4 stored `std_dev`, 5 `updated_by`, 6 `decimals` and 7 indexed `RESOLVE_TIMES`.
+/// Later fields read as their serde defaults and need no step.
+const MIGRATIONS: [MigrationStep; 6] = [
+    migrate_introduce_owner,
+    migrate_refs_to_map,
+    migrate_refs_std_dev,
+    migrate_refs_updated_by,
+    migrate_refs_decimals,
+    migrate_resolve_time_index,
+];
+const SCHEMA_VERSION: u32 = MIGRATIONS.len() as u32 + 1;
+
+/// How `update_refs` treats an incoming resolve time older than the stored one.
+pub enum StalePolicy {
+    Skip,
+    Reject,
+    /// Always write; only the owner may relay with this policy.
+    Overwrite,
+}
+
+pub const DEFAULT_BASE_DECIMALS: u32 = 18;
+pub const DEFAULT_USD_DECIMALS: u32 = 9;
+
+const MAX_SYMBOL_LENGTH: usize = 32;
+
+const MAX_REMOVE_BY_PREFIX: usize = 100;
+
+const TRANSFER_OWNERSHIP_REPLY_ID: u64 = 1;
+
+const DEFAULT_LIMIT: u32 = 30;
+const MAX_LIMIT: u32 = 100;
 
 #[entry_point]
 pub fn instantiate(
     deps: DepsMut,
-    _env: Env,
-    _info: MessageInfo,
-    _msg: InstantiateMsg,
+    env: Env,
+    info: MessageInfo,
+    msg: InstantiateMsg,
 ) -> Result<Response, ContractError> {
+    // code reused by a factory must never reset a live contract's refs
+    if deps.storage.get(&to_length_prefixed(CONFIG_KEY)).is_some() {
+        return Err(ContractError::AlreadyInitialized {});
+    }
+    let base_decimals = msg.base_decimals.unwrap_or(DEFAULT_BASE_DECIMALS);
+    let usd_decimals = msg.usd_decimals.unwrap_or(DEFAULT_USD_DECIMALS);
+    validate_decimals(base_decimals, usd_decimals)?;
+    if msg.usd_rate == Some(0) {
+        return Err(ContractError::InvalidUsdRate {});
+    }
+    if let Some(separator) = &msg.symbol_separator {
+        if separator.len() != 1 || !separator.chars().all(|c| c.is_ascii_punctuation()) {
+            return Err(ContractError::InvalidSeparator {});
+        }
+    }
+    let owner = match &msg.owner {
+        Some(owner) => deps.api.addr_validate(owner)?,
+        None => info.sender.clone(),
+    };
     let state = State {
-        refs: HashMap::new(),
+        owner: Some(owner),
+        pending_owner: None,
+        relayers: vec![],
+        paused: false,
+        symbol_relayers: HashMap::new(),
+        symbol_count: 0,
+        max_staleness_secs: None,
+        base_decimals,
+        usd_decimals,
+        usd_rate: msg.usd_rate,
+        symbol_separator: msg.symbol_separator,
+        event_namespace: msg.event_namespace.unwrap_or_default(),
+        usd_timestamp_mode: UsdTimestampMode::BlockTime,
+        twap_samples: DEFAULT_TWAP_SAMPLES,
+        max_future_skew_secs: None,
+        relayer_active_after: HashMap::new(),
+        max_rate_change_bps: None,
+        max_batch_size: DEFAULT_MAX_BATCH_SIZE,
+        last_relay_time: 0,
+        last_relay_block: 0,
+        relayer_pubkeys: vec![],
+        min_relay_interval_secs: None,
+        allow_signed_rates: false,
+        relay_seq: 0,
+        rounding: RoundingMode::Trunc,
+        frozen: BTreeSet::new(),
+        strict_request_id: false,
     };
     config(deps.storage).save(&state)?;
+    schema_version(deps.storage).save(&SCHEMA_VERSION)?;
+    set_contract_version(deps.storage, CONTRACT_NAME, CONTRACT_VERSION)?;
+    // the instantiator may seed refs even when it is not the owner
+    if msg.initial_symbols.is_some() || msg.initial_rates.is_some() || msg.initial_resolve_times.is_some() || msg.initial_request_ids.is_some() {
+        apply_refs(
+            deps,
+            env,
+            state,
+            info.sender,
+            &msg.initial_symbols.unwrap_or_default(),
+            &msg.initial_rates.unwrap_or_default(),
+            &msg.initial_resolve_times.unwrap_or_default(),
+            &msg.initial_request_ids.unwrap_or_default(),
+            RelayExtras::default(),
+            StalePolicy::Reject,
+        )?;
+    }
     Ok(Response::default())
 }
 
+#[entry_point]
+pub fn migrate(mut deps: DepsMut, _env: Env, msg: MigrateMsg) -> Result<Response, ContractError> {
+    // the original release predates cw2, so a missing version means schema 1
+    let found = match CONTRACT.may_load(deps.storage)? {
+        None => 1,
+        Some(stored) => {
+            if stored.contract != CONTRACT_NAME || version_parts(&stored.version) > version_parts(CONTRACT_VERSION) {
+                return Err(ContractError::CannotMigrate {});
+            }
+            if version_parts(&stored.version) < version_parts("0.9.0") {
+                legacy_schema_version(&stored.version)
+            } else {
+                schema_version_read(deps.storage).load()?
+            }
+        }
+    };
+    if found == 0 || found > SCHEMA_VERSION {
+        return Err(ContractError::UnknownSchemaVersion { found });
+    }
+    for step in MIGRATIONS[(found - 1) as usize..].iter() {
+        step(deps.branch(), &msg)?;
+    }
+    schema_version(deps.storage).save(&SCHEMA_VERSION)?;
+    set_contract_version(deps.storage, CONTRACT_NAME, CONTRACT_VERSION)?;
+    Ok(Response::default())
+}
+
+/// Gives the original refs-only blob the 0.2.x shape, owned by `MigrateMsg::owner` and otherwise
+/// configured like a fresh 0.2.x instantiation.
+fn migrate_introduce_owner(deps: DepsMut, msg: &MigrateMsg) -> Result<(), ContractError> {
+    let owner = msg.owner.as_ref().ok_or(ContractError::MissingOwner {})?;
+    let initial = initial_config_read(deps.storage).load()?;
+    let legacy = LegacyState {
+        owner: deps.api.addr_validate(owner)?,
+        pending_owner: None,
+        relayers: vec![],
+        paused: false,
+        symbol_relayers: HashMap::new(),
+        symbol_count: initial.refs.len() as u64,
+        max_staleness_secs: None,
+        base_decimals: DEFAULT_BASE_DECIMALS,
+        usd_decimals: DEFAULT_USD_DECIMALS,
+        refs: initial.refs,
+    };
+    legacy_config(deps.storage).save(&legacy)?;
+    Ok(())
+}
+
+/// Moves every ref out of the config blob into its own `REFS` entry.
+fn migrate_refs_to_map(deps: DepsMut, _msg: &MigrateMsg) -> Result<(), ContractError> {
+    let storage = deps.storage;
+    let legacy = legacy_config(storage).load()?;
+    for (symbol, ref_data) in legacy.refs.iter() {
+        REFS.save(storage, symbol, ref_data)?;
+    }
+    let state = State {
+        owner: Some(legacy.owner),
+        pending_owner: legacy.pending_owner,
+        relayers: legacy.relayers,
+        paused: legacy.paused,
+        symbol_relayers: legacy.symbol_relayers,
+        symbol_count: legacy.refs.len() as u64,
+        max_staleness_secs: legacy.max_staleness_secs,
+        base_decimals: legacy.base_decimals,
+        usd_decimals: legacy.usd_decimals,
+        usd_rate: None,
+        symbol_separator: None,
+        max_future_skew_secs: None,
+        relayer_active_after: HashMap::new(),
+        max_rate_change_bps: None,
+        max_batch_size: DEFAULT_MAX_BATCH_SIZE,
+        last_relay_time: 0,
+        last_relay_block: 0,
+        relayer_pubkeys: vec![],
+        min_relay_interval_secs: None,
+        allow_signed_rates: false,
+        relay_seq: 0,
+        rounding: RoundingMode::Trunc,
+        frozen: BTreeSet::new(),
+        strict_request_id: false,
+        event_namespace: String::new(),
+        usd_timestamp_mode: UsdTimestampMode::BlockTime,
+        twap_samples: DEFAULT_TWAP_SAMPLES,
+    };
+    config(storage).save(&state)?;
+    Ok(())
+}
+
+/// Rewrites every ref so it is stored with an explicit `std_dev: None`.
+fn migrate_refs_std_dev(deps: DepsMut, _msg: &MigrateMsg) -> Result<(), ContractError> {
+    let storage = deps.storage;
+    let refs = REFS
+        .range(storage, None, None, Order::Ascending)
+        .map(|item| {
+            let (key, ref_data) = item?;
+            Ok((String::from_utf8(key)?, ref_data))
+        })
+        .collect::<StdResult<Vec<(String, RefData)>>>()?;
+    for (symbol, ref_data) in refs {
+        REFS.save(storage, &symbol, &ref_data)?;
+    }
+    Ok(())
+}
+
+/// Attributes every ref stored before 0.5.0 to the current owner.
+fn migrate_refs_updated_by(deps: DepsMut, _msg: &MigrateMsg) -> Result<(), ContractError> {
+    let storage = deps.storage;
+    // entries from before 0.5.0 cannot be attributed once ownership is renounced
+    let owner = match config_read(storage).load()?.owner {
+        Some(owner) => owner,
+        None => return Ok(()),
+    };
+    let refs = REFS
+        .range(storage, None, None, Order::Ascending)
+        .map(|item| {
+            let (key, ref_data) = item?;
+            Ok((String::from_utf8(key)?, ref_data))
+        })
+        .collect::<StdResult<Vec<(String, RefData)>>>()?;
+    for (symbol, mut ref_data) in refs {
+        if ref_data.updated_by.as_str().is_empty() {
+            ref_data.updated_by = owner.clone();
+            REFS.save(storage, &symbol, &ref_data)?;
+        }
+    }
+    Ok(())
+}
+
+/// Every ref stored before 0.6.0 was relayed at the configured `usd_decimals`.
+fn migrate_refs_decimals(deps: DepsMut, _msg: &MigrateMsg) -> Result<(), ContractError> {
+    let storage = deps.storage;
+    let usd_decimals = config_read(storage).load()?.usd_decimals;
+    let refs = REFS
+        .range(storage, None, None, Order::Ascending)
+        .map(|item| {
+            let (key, ref_data) = item?;
+            Ok((String::from_utf8(key)?, ref_data))
+        })
+        .collect::<StdResult<Vec<(String, RefData)>>>()?;
+    for (symbol, mut ref_data) in refs {
+        ref_data.decimals = usd_decimals;
+        REFS.save(storage, &symbol, &ref_data)?;
+    }
+    Ok(())
+}
+
+/// Indexes every stored ref by resolve time for `GetOldestResolveTime`.
+fn migrate_resolve_time_index(deps: DepsMut, _msg: &MigrateMsg) -> Result<(), ContractError> {
+    let storage = deps.storage;
+    let refs = REFS
+        .range(storage, None, None, Order::Ascending)
+        .map(|item| {
+            let (key, ref_data) = item?;
+            Ok((String::from_utf8(key)?, ref_data.resolve_time))
+        })
+        .collect::<StdResult<Vec<(String, u64)>>>()?;
+    for (symbol, resolve_time) in refs {
+        RESOLVE_TIMES.save(storage, (resolve_time.into(), &symbol), &symbol)?;
+    }
+    Ok(())
+}
+
+/// Schema version of a deployment from before 0.9.0, which only recorded its cw2 version.
+fn legacy_schema_version(version: &str) -> u32 {
+    let version = version_parts(version);
+    ["0.3.0", "0.4.0", "0.5.0", "0.6.0"].iter().filter(|&&release| version >= version_parts(release)).count() as u32 + 2
+}
+
+fn version_parts(version: &str) -> Vec<u64> {
+    version.split('.').map(|part| part.parse().unwrap_or(0)).collect()
+}
+
 #[entry_point]
 pub fn execute(
     deps: DepsMut,
-    _env: Env,
-    _info: MessageInfo,
+    env: Env,
+    info: MessageInfo,
     msg: ExecuteMsg,
 ) -> Result<Response, ContractError> {
-    match msg {
-        ExecuteMsg::Relay { symbols, rates, resolve_times, request_ids } => update_refs(deps, &symbols, &rates, &resolve_times, &request_ids),
+    // fixed at instantiation, so it can be read before `deps` is handed over
+    let namespace = config_read(deps.storage).load()?.event_namespace;
+    let res = match msg {
+        ExecuteMsg::Relay { symbols, rates, resolve_times, request_ids } => update_refs(deps, env, info, &symbols, &rates, &resolve_times, &request_ids, RelayExtras::default(), StalePolicy::Skip),
+        ExecuteMsg::RelayV2 { entries } => relay_entries(deps, env, info, entries),
+        ExecuteMsg::RelayStrict { symbols, rates, resolve_times, request_ids } => update_refs(deps, env, info, &symbols, &rates, &resolve_times, &request_ids, RelayExtras::default(), StalePolicy::Reject),
+        ExecuteMsg::RelayWithStdDev { symbols, rates, resolve_times, request_ids, std_devs } => update_refs(deps, env, info, &symbols, &rates, &resolve_times, &request_ids, RelayExtras { std_devs: Some(&std_devs.into_iter().map(Some).collect::<Vec<_>>()), ..RelayExtras::default() }, StalePolicy::Skip),
+        ExecuteMsg::RelayWithDecimals { symbols, rates, resolve_times, request_ids, decimals } => update_refs(deps, env, info, &symbols, &rates, &resolve_times, &request_ids, RelayExtras { decimals: Some(&decimals), ..RelayExtras::default() }, StalePolicy::Skip),
+        ExecuteMsg::RelaySignedRates { symbols, rates, resolve_times, request_ids } => relay_signed_rates(deps, env, info, &symbols, &rates, &resolve_times, &request_ids),
+        ExecuteMsg::Heartbeat { symbols, resolve_times, request_ids } => heartbeat(deps, env, info, &symbols, &resolve_times, &request_ids),
+        ExecuteMsg::ForceRelay { symbols, rates, resolve_times, request_ids } => update_refs(deps, env, info, &symbols, &rates, &resolve_times, &request_ids, RelayExtras::default(), StalePolicy::Overwrite),
+        ExecuteMsg::RelaySigned { payload, signature, pubkey } => relay_signed(deps, env, payload, signature, pubkey),
+        ExecuteMsg::RemoveSymbol { symbols } => remove_symbols(deps, env, info, &symbols),
+        ExecuteMsg::RemoveByPrefix { prefix } => remove_by_prefix(deps, info, prefix),
+        ExecuteMsg::Clear {} => clear(deps, info),
+        ExecuteMsg::BatchUpdate { relays, remove } => batch_update(deps, env, info, relays, remove),
+        ExecuteMsg::AddRelayer { address } => add_relayer(deps, info, address),
+        ExecuteMsg::RemoveRelayer { address } => remove_relayer(deps, info, address),
+        ExecuteMsg::AddRelayerDelayed { address, delay_secs } => add_relayer_delayed(deps, env, info, address, delay_secs),
+        ExecuteMsg::AddRelayerPubkey { pubkey } => add_relayer_pubkey(deps, info, pubkey),
+        ExecuteMsg::RemoveRelayerPubkey { pubkey } => remove_relayer_pubkey(deps, info, pubkey),
+        ExecuteMsg::TransferOwnership { new_owner } => transfer_ownership(deps, info, new_owner),
+        ExecuteMsg::AcceptOwnership {} => accept_ownership(deps, info),
+        ExecuteMsg::TransferOwnershipToContract { contract_addr } => transfer_ownership_to_contract(deps, info, contract_addr),
+        ExecuteMsg::RenounceOwnership {} => renounce_ownership(deps, info),
+        ExecuteMsg::Pause {} => set_paused(deps, info, true),
+        ExecuteMsg::Unpause {} => set_paused(deps, info, false),
+        ExecuteMsg::FreezeSymbol { symbol } => set_frozen(deps, info, symbol, true),
+        ExecuteMsg::UnfreezeSymbol { symbol } => set_frozen(deps, info, symbol, false),
+        ExecuteMsg::SetSymbolRelayers { symbol, relayers } => set_symbol_relayers(deps, info, symbol, relayers),
+        ExecuteMsg::SetAlias { alias, symbol } => set_alias(deps, info, alias, symbol),
+        ExecuteMsg::RemoveAlias { alias } => remove_alias(deps, info, alias),
+        ExecuteMsg::SetDenom { denom, symbol } => set_denom(deps, info, denom, symbol),
+        ExecuteMsg::UpdateConfig { owner, max_staleness_secs, max_future_skew_secs, max_rate_change_bps, base_decimals, usd_decimals, max_batch_size, min_relay_interval_secs, allow_signed_rates, rounding, strict_request_id, usd_timestamp_mode, twap_samples } => update_config(deps, info, owner, max_staleness_secs, max_future_skew_secs, max_rate_change_bps, base_decimals, usd_decimals, max_batch_size, min_relay_interval_secs, allow_signed_rates, rounding, strict_request_id, usd_timestamp_mode, twap_samples),
+    }?;
+    Ok(namespaced(&namespace, res))
+}
+
+/// Applies `State::event_namespace` to the event type, i.e. the `action` value, and to every other attribute key;
+/// the `action` key itself stays unprefixed so indexers can still filter on it.
+fn namespaced(namespace: &str, mut res: Response) -> Response {
+    if namespace.is_empty() {
+        return res;
+    }
+    for attribute in res.attributes.iter_mut() {
+        if attribute.key == "action" {
+            attribute.value = format!("{}.{}", namespace, attribute.value);
+        } else {
+            attribute.key = format!("{}.{}", namespace, attribute.key);
+        }
+    }
+    res
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn update_config(deps: DepsMut, info: MessageInfo, owner: Option<String>, max_staleness_secs: Option<Option<u64>>, max_future_skew_secs: Option<Option<u64>>, max_rate_change_bps: Option<Option<u16>>, base_decimals: Option<u32>, usd_decimals: Option<u32>, max_batch_size: Option<u32>, min_relay_interval_secs: Option<Option<u64>>, allow_signed_rates: Option<bool>, rounding: Option<RoundingMode>, strict_request_id: Option<bool>, usd_timestamp_mode: Option<UsdTimestampMode>, twap_samples: Option<u32>) -> Result<Response, ContractError> {
+    let mut state = config(deps.storage).load()?;
+    assert_owner(&state, &info)?;
+    // a direct handover also drops any pending two-step transfer
+    if let Some(owner) = owner {
+        state.owner = Some(deps.api.addr_validate(&owner)?);
+        state.pending_owner = None;
+    }
+    if let Some(max_staleness_secs) = max_staleness_secs {
+        state.max_staleness_secs = max_staleness_secs;
+    }
+    if let Some(max_future_skew_secs) = max_future_skew_secs {
+        state.max_future_skew_secs = max_future_skew_secs;
+    }
+    if let Some(max_rate_change_bps) = max_rate_change_bps {
+        state.max_rate_change_bps = max_rate_change_bps;
+    }
+    if let Some(base_decimals) = base_decimals {
+        state.base_decimals = base_decimals;
+    }
+    if let Some(usd_decimals) = usd_decimals {
+        // usd_rate is stored at the current precision, so another one would rescale the USD leg
+        if state.usd_rate.is_some() && usd_decimals != state.usd_decimals {
+            return Err(ContractError::UsdRatePinned {});
+        }
+        state.usd_decimals = usd_decimals;
+    }
+    if let Some(max_batch_size) = max_batch_size {
+        state.max_batch_size = max_batch_size;
+    }
+    if let Some(min_relay_interval_secs) = min_relay_interval_secs {
+        state.min_relay_interval_secs = min_relay_interval_secs;
+    }
+    if let Some(allow_signed_rates) = allow_signed_rates {
+        state.allow_signed_rates = allow_signed_rates;
+    }
+    if let Some(rounding) = rounding {
+        state.rounding = rounding;
+    }
+    if let Some(strict_request_id) = strict_request_id {
+        state.strict_request_id = strict_request_id;
+    }
+    if let Some(usd_timestamp_mode) = usd_timestamp_mode {
+        state.usd_timestamp_mode = usd_timestamp_mode;
+    }
+    if let Some(twap_samples) = twap_samples {
+        if twap_samples == 0 || twap_samples > MAX_TWAP_SAMPLES {
+            return Err(ContractError::InvalidTwapSamples { max: MAX_TWAP_SAMPLES });
+        }
+        state.twap_samples = twap_samples;
+    }
+    validate_decimals(state.base_decimals, state.usd_decimals)?;
+    config(deps.storage).save(&state)?;
+    let mut res = Response::new();
+    res.add_attribute("action", "update_config");
+    res.add_attribute("owner", optional_attr(state.owner.as_ref()));
+    res.add_attribute("max_staleness_secs", optional_attr(state.max_staleness_secs));
+    res.add_attribute("max_future_skew_secs", optional_attr(state.max_future_skew_secs));
+    res.add_attribute("max_rate_change_bps", optional_attr(state.max_rate_change_bps));
+    res.add_attribute("base_decimals", state.base_decimals.to_string());
+    res.add_attribute("usd_decimals", state.usd_decimals.to_string());
+    res.add_attribute("max_batch_size", state.max_batch_size.to_string());
+    res.add_attribute("min_relay_interval_secs", optional_attr(state.min_relay_interval_secs));
+    res.add_attribute("allow_signed_rates", state.allow_signed_rates.to_string());
+    res.add_attribute("rounding", format!("{:?}", state.rounding).to_lowercase());
+    res.add_attribute("strict_request_id", state.strict_request_id.to_string());
+    res.add_attribute("usd_timestamp_mode", format!("{:?}", state.usd_timestamp_mode).to_lowercase());
+    res.add_attribute("twap_samples", state.twap_samples.to_string());
+    Ok(res)
+}
+
+fn optional_attr<T: ToString>(value: Option<T>) -> String {
+    match value {
+        Some(value) => value.to_string(),
+        None => String::from("none"),
+    }
+}
+
+pub fn set_symbol_relayers(deps: DepsMut, info: MessageInfo, symbol: String, relayers: Vec<String>) -> Result<Response, ContractError> {
+    let mut state = config(deps.storage).load()?;
+    assert_owner(&state, &info)?;
+    let mut addrs: Vec<Addr> = vec![];
+    for relayer in relayers.iter() {
+        let addr = deps.api.addr_validate(relayer)?;
+        if !addrs.contains(&addr) {
+            addrs.push(addr);
+        }
+    }
+    let mut res = Response::new();
+    res.add_attribute("action", "set_symbol_relayers");
+    res.add_attribute("symbol", &symbol);
+    res.add_attribute("num_relayers", addrs.len().to_string());
+    if addrs.is_empty() {
+        state.symbol_relayers.remove(&symbol);
+    } else {
+        state.symbol_relayers.insert(symbol, addrs);
+    }
+    config(deps.storage).save(&state)?;
+    Ok(res)
+}
+
+pub fn set_alias(deps: DepsMut, info: MessageInfo, alias: String, symbol: String) -> Result<Response, ContractError> {
+    let state = config(deps.storage).load()?;
+    assert_owner(&state, &info)?;
+    validate_symbol(&alias, &state.symbol_separator)?;
+    if alias == symbol || REFS.may_load(deps.storage, &alias)?.is_some() {
+        return Err(ContractError::InvalidAlias { alias });
+    }
+    if REFS.may_load(deps.storage, &symbol)?.is_none() {
+        return Err(ContractError::SymbolNotFound { symbol });
+    }
+    // aliases never chain, so there is no cycle to follow
+    let is_target = ALIASES
+        .range(deps.storage, None, None, Order::Ascending)
+        .any(|item| matches!(item, Ok((_, target)) if target == alias));
+    if ALIASES.may_load(deps.storage, &symbol)?.is_some() || is_target {
+        return Err(ContractError::InvalidAlias { alias });
+    }
+    ALIASES.save(deps.storage, &alias, &symbol)?;
+    let mut res = Response::new();
+    res.add_attribute("action", "set_alias");
+    res.add_attribute("alias", &alias);
+    res.add_attribute("symbol", &symbol);
+    Ok(res)
+}
+
+pub fn remove_alias(deps: DepsMut, info: MessageInfo, alias: String) -> Result<Response, ContractError> {
+    let state = config(deps.storage).load()?;
+    assert_owner(&state, &info)?;
+    ALIASES.remove(deps.storage, &alias);
+    let mut res = Response::new();
+    res.add_attribute("action", "remove_alias");
+    res.add_attribute("alias", &alias);
+    Ok(res)
+}
+
+/// Drops `symbol`'s ref along with its last write, samples and resolve time index; false when nothing was stored.
+fn remove_ref(storage: &mut dyn Storage, symbol: &str) -> StdResult<bool> {
+    let ref_data = match REFS.may_load(storage, symbol)? {
+        Some(ref_data) => ref_data,
+        None => return Ok(false),
+    };
+    REFS.remove(storage, symbol);
+    LAST_WRITES.remove(storage, symbol);
+    SAMPLES.remove(storage, symbol);
+    RESOLVE_TIMES.remove(storage, (ref_data.resolve_time.into(), symbol));
+    Ok(true)
+}
+
+/// Drops every alias whose target is being removed, so no alias outlives the feed it names.
+fn remove_aliases_to(storage: &mut dyn Storage, removed: impl Fn(&String) -> bool) -> StdResult<()> {
+    let mut dangling = vec![];
+    for item in ALIASES.range(storage, None, None, Order::Ascending) {
+        let (alias, symbol) = item?;
+        if removed(&symbol) {
+            dangling.push(String::from_utf8(alias)?);
+        }
+    }
+    for alias in dangling.iter() {
+        ALIASES.remove(storage, alias);
+    }
+    Ok(())
+}
+
+pub fn set_denom(deps: DepsMut, info: MessageInfo, denom: String, symbol: String) -> Result<Response, ContractError> {
+    let state = config(deps.storage).load()?;
+    assert_owner(&state, &info)?;
+    // the symbol need not be relayed yet, and stablecoin denoms usually map to the synthetic USD
+    if symbol != "USD" {
+        validate_symbol(&symbol, &state.symbol_separator)?;
+    }
+    DENOMS.save(deps.storage, &denom, &symbol)?;
+    let mut res = Response::new();
+    res.add_attribute("action", "set_denom");
+    res.add_attribute("denom", &denom);
+    res.add_attribute("symbol", &symbol);
+    Ok(res)
+}
+
+/// Symbols are 1 to 32 bytes of `A-Z`, `0-9` and the configured separator. `USD` is reserved for the synthetic rate.
+fn validate_symbol(symbol: &str, separator: &Option<String>) -> Result<(), ContractError> {
+    let separator = separator.as_ref().and_then(|separator| separator.chars().next());
+    let valid = !symbol.is_empty()
+        && symbol.len() <= MAX_SYMBOL_LENGTH
+        && symbol != "USD"
+        && symbol.chars().all(|c| c.is_ascii_uppercase() || c.is_ascii_digit() || Some(c) == separator);
+    if !valid {
+        return Err(ContractError::InvalidSymbol { symbol: symbol.to_string() });
+    }
+    Ok(())
+}
+
+fn validate_decimals(base_decimals: u32, usd_decimals: u32) -> Result<(), ContractError> {
+    // the scaling factor must fit in a u128 and the synthetic USD rate in a u64
+    if 10u128.checked_pow(base_decimals).is_none() || 10u64.checked_pow(usd_decimals).is_none() {
+        return Err(ContractError::InvalidDecimals {});
+    }
+    Ok(())
+}
+
+pub fn set_paused(deps: DepsMut, info: MessageInfo, paused: bool) -> Result<Response, ContractError> {
+    let mut state = config(deps.storage).load()?;
+    assert_owner(&state, &info)?;
+    state.paused = paused;
+    config(deps.storage).save(&state)?;
+    let mut res = Response::new();
+    res.add_attribute("action", if paused { "pause" } else { "unpause" });
+    Ok(res)
+}
+
+pub fn set_frozen(deps: DepsMut, info: MessageInfo, symbol: String, frozen: bool) -> Result<Response, ContractError> {
+    let mut state = config(deps.storage).load()?;
+    assert_owner(&state, &info)?;
+    if frozen {
+        state.frozen.insert(symbol.clone());
+    } else {
+        state.frozen.remove(&symbol);
+    }
+    config(deps.storage).save(&state)?;
+    let mut res = Response::new();
+    res.add_attribute("action", if frozen { "freeze_symbol" } else { "unfreeze_symbol" });
+    res.add_attribute("symbol", symbol);
+    Ok(res)
+}
+
+pub fn transfer_ownership(deps: DepsMut, info: MessageInfo, new_owner: String) -> Result<Response, ContractError> {
+    let mut state = config(deps.storage).load()?;
+    assert_owner(&state, &info)?;
+    // a second transfer simply replaces the previously proposed owner
+    let pending_owner = deps.api.addr_validate(&new_owner)?;
+    let mut res = Response::new();
+    res.add_attribute("action", "transfer_ownership");
+    res.add_attribute("pending_owner", &pending_owner);
+    state.pending_owner = Some(pending_owner);
+    config(deps.storage).save(&state)?;
+    Ok(res)
+}
+
+pub fn accept_ownership(deps: DepsMut, info: MessageInfo) -> Result<Response, ContractError> {
+    let mut state = config(deps.storage).load()?;
+    if state.pending_owner.as_ref() != Some(&info.sender) {
+        return Err(ContractError::Unauthorized {});
+    }
+    let mut res = Response::new();
+    res.add_attribute("action", "accept_ownership");
+    res.add_attribute("owner", &info.sender);
+    state.owner = Some(info.sender);
+    state.pending_owner = None;
+    config(deps.storage).save(&state)?;
+    Ok(res)
+}
+
+pub fn transfer_ownership_to_contract(deps: DepsMut, info: MessageInfo, contract_addr: String) -> Result<Response, ContractError> {
+    let state = config(deps.storage).load()?;
+    assert_owner(&state, &info)?;
+    let pending_owner = deps.api.addr_validate(&contract_addr)?;
+    PENDING_CONTRACT_OWNER.save(deps.storage, &pending_owner)?;
+    let msg = WasmMsg::Execute {
+        contract_addr: pending_owner.to_string(),
+        msg: to_binary(&OwnershipCallbackMsg::AcknowledgeOwnership {})?,
+        send: vec![],
+    };
+    let mut res = Response::new();
+    res.add_submessage(TRANSFER_OWNERSHIP_REPLY_ID, msg, None, ReplyOn::Always);
+    res.add_attribute("action", "transfer_ownership_to_contract");
+    res.add_attribute("pending_owner", &pending_owner);
+    Ok(res)
+}
+
+pub fn renounce_ownership(deps: DepsMut, info: MessageInfo) -> Result<Response, ContractError> {
+    let mut state = config(deps.storage).load()?;
+    assert_owner(&state, &info)?;
+    // a pending transfer must not be able to bring an owner back
+    state.owner = None;
+    state.pending_owner = None;
+    config(deps.storage).save(&state)?;
+    let mut res = Response::new();
+    res.add_attribute("action", "renounce_ownership");
+    Ok(res)
+}
+
+pub fn add_relayer(deps: DepsMut, info: MessageInfo, address: String) -> Result<Response, ContractError> {
+    let mut state = config(deps.storage).load()?;
+    assert_owner(&state, &info)?;
+    let relayer = deps.api.addr_validate(&address)?;
+    // re-adding a delayed relayer activates it immediately
+    let was_delayed = state.relayer_active_after.remove(&relayer).is_some();
+    let mut res = Response::new();
+    res.add_attribute("action", "add_relayer");
+    res.add_attribute("relayer", &relayer);
+    if !state.relayers.contains(&relayer) {
+        state.relayers.push(relayer);
+        config(deps.storage).save(&state)?;
+    } else if was_delayed {
+        config(deps.storage).save(&state)?;
+    }
+    Ok(res)
+}
+
+pub fn add_relayer_delayed(deps: DepsMut, env: Env, info: MessageInfo, address: String, delay_secs: u64) -> Result<Response, ContractError> {
+    let mut state = config(deps.storage).load()?;
+    assert_owner(&state, &info)?;
+    let relayer = deps.api.addr_validate(&address)?;
+    if !state.relayers.contains(&relayer) {
+        state.relayers.push(relayer.clone());
+    }
+    let active_after = env.block.time.nanos().saturating_add(delay_secs.saturating_mul(NANOS_PER_SECOND));
+    let mut res = Response::new();
+    res.add_attribute("action", "add_relayer_delayed");
+    res.add_attribute("relayer", &relayer);
+    res.add_attribute("active_after", active_after.to_string());
+    state.relayer_active_after.insert(relayer, active_after);
+    config(deps.storage).save(&state)?;
+    Ok(res)
+}
+
+pub fn remove_relayer(deps: DepsMut, info: MessageInfo, address: String) -> Result<Response, ContractError> {
+    let mut state = config(deps.storage).load()?;
+    assert_owner(&state, &info)?;
+    let relayer = deps.api.addr_validate(&address)?;
+    state.relayers.retain(|r| r != &relayer);
+    state.relayer_active_after.remove(&relayer);
+    config(deps.storage).save(&state)?;
+    let mut res = Response::new();
+    res.add_attribute("action", "remove_relayer");
+    res.add_attribute("relayer", relayer);
+    Ok(res)
+}
+
+pub fn add_relayer_pubkey(deps: DepsMut, info: MessageInfo, pubkey: Binary) -> Result<Response, ContractError> {
+    let mut state = config(deps.storage).load()?;
+    assert_owner(&state, &info)?;
+    if !state.relayer_pubkeys.contains(&pubkey) {
+        state.relayer_pubkeys.push(pubkey.clone());
+        config(deps.storage).save(&state)?;
+    }
+    let mut res = Response::new();
+    res.add_attribute("action", "add_relayer_pubkey");
+    res.add_attribute("pubkey", pubkey.to_base64());
+    Ok(res)
+}
+
+pub fn remove_relayer_pubkey(deps: DepsMut, info: MessageInfo, pubkey: Binary) -> Result<Response, ContractError> {
+    let mut state = config(deps.storage).load()?;
+    assert_owner(&state, &info)?;
+    state.relayer_pubkeys.retain(|key| key != &pubkey);
+    config(deps.storage).save(&state)?;
+    let mut res = Response::new();
+    res.add_attribute("action", "remove_relayer_pubkey");
+    res.add_attribute("pubkey", pubkey.to_base64());
+    Ok(res)
+}
+
+fn assert_owner(state: &State, info: &MessageInfo) -> Result<(), ContractError> {
+    if state.owner.as_ref() != Some(&info.sender) {
+        return Err(ContractError::Unauthorized {});
+    }
+    Ok(())
+}
+
+fn assert_relayer(state: &State, env: &Env, info: &MessageInfo, symbols: &[String]) -> Result<(), ContractError> {
+    if state.owner.as_ref() == Some(&info.sender) {
+        return Ok(());
+    }
+    if state.relayers.contains(&info.sender) {
+        return match state.relayer_active_after.get(&info.sender) {
+            Some(&active_after) if env.block.time.nanos() < active_after => Err(ContractError::Unauthorized {}),
+            _ => Ok(()),
+        };
+    }
+    // not a global relayer, so every symbol in the batch must be delegated to the sender
+    for symbol in symbols.iter() {
+        match state.symbol_relayers.get(symbol) {
+            Some(relayers) if relayers.contains(&info.sender) => {}
+            _ => return Err(ContractError::Unauthorized {}),
+        }
+    }
+    Ok(())
+}
+
+pub fn remove_symbols(deps: DepsMut, env: Env, info: MessageInfo, symbols: &[String]) -> Result<Response, ContractError> {
+    let mut state = config(deps.storage).load()?;
+    if state.paused {
+        return Err(ContractError::ContractPaused {});
+    }
+    // an empty list would let any sender through `assert_relayer`
+    if symbols.is_empty() {
+        return Err(ContractError::EmptyRelay {});
+    }
+    assert_relayer(&state, &env, &info, symbols)?;
+    let mut num_removed = 0u64;
+    for symbol in symbols.iter() {
+        if remove_ref(deps.storage, symbol)? {
+            num_removed += 1;
+        }
+    }
+    remove_aliases_to(deps.storage, |symbol| symbols.contains(symbol))?;
+    state.symbol_count -= num_removed;
+    config(deps.storage).save(&state)?;
+    let mut res = Response::new();
+    res.add_attribute("action", "remove_symbol");
+    res.add_attribute("num_removed", num_removed.to_string());
+    Ok(res)
+}
+
+pub fn remove_by_prefix(deps: DepsMut, info: MessageInfo, prefix: String) -> Result<Response, ContractError> {
+    let mut state = config(deps.storage).load()?;
+    assert_owner(&state, &info)?;
+    // an empty prefix would be `Clear` without its resets
+    if prefix.is_empty() {
+        return Err(ContractError::InvalidSymbol { symbol: prefix });
+    }
+    let mut symbols = REFS
+        .keys(deps.storage, Some(Bound::inclusive(prefix.as_bytes())), None, Order::Ascending)
+        .map(String::from_utf8)
+        .take_while(|symbol| symbol.as_ref().map_or(true, |symbol| symbol.starts_with(&prefix)))
+        .take(MAX_REMOVE_BY_PREFIX + 1)
+        .collect::<Result<Vec<String>, _>>()
+        .map_err(StdError::from)?;
+    let has_more = symbols.len() > MAX_REMOVE_BY_PREFIX;
+    symbols.truncate(MAX_REMOVE_BY_PREFIX);
+    for symbol in symbols.iter() {
+        remove_ref(deps.storage, symbol)?;
+    }
+    remove_aliases_to(deps.storage, |symbol| symbols.contains(symbol))?;
+    state.symbol_count -= symbols.len() as u64;
+    config(deps.storage).save(&state)?;
+    let mut res = Response::new();
+    res.add_attribute("action", "remove_by_prefix");
+    res.add_attribute("prefix", &prefix);
+    res.add_attribute("num_removed", symbols.len().to_string());
+    res.add_attribute("has_more", has_more.to_string());
+    Ok(res)
+}
+
+pub fn clear(deps: DepsMut, info: MessageInfo) -> Result<Response, ContractError> {
+    let mut state = config(deps.storage).load()?;
+    assert_owner(&state, &info)?;
+    let symbols = REFS
+        .keys(deps.storage, None, None, Order::Ascending)
+        .map(|key| Ok(String::from_utf8(key)?))
+        .collect::<StdResult<Vec<String>>>()?;
+    for symbol in symbols.iter() {
+        remove_ref(deps.storage, symbol)?;
+    }
+    remove_aliases_to(deps.storage, |_| true)?;
+    state.symbol_count = 0;
+    state.last_relay_time = 0;
+    state.last_relay_block = 0;
+    config(deps.storage).save(&state)?;
+    let mut res = Response::new();
+    res.add_attribute("action", "clear");
+    res.add_attribute("num_removed", symbols.len().to_string());
+    Ok(res)
+}
+
+pub fn batch_update(mut deps: DepsMut, env: Env, info: MessageInfo, relays: RelayPayload, remove: Vec<String>) -> Result<Response, ContractError> {
+    if relays.symbols.is_empty() && remove.is_empty() {
+        return Err(ContractError::EmptyRelay {});
+    }
+    // fail before any relay work when the removals would be rejected anyway
+    let state = config_read(deps.storage).load()?;
+    if state.paused {
+        return Err(ContractError::ContractPaused {});
+    }
+    assert_relayer(&state, &env, &info, &remove)?;
+    if let Some(symbol) = remove.iter().find(|symbol| relays.symbols.contains(symbol)) {
+        return Err(ContractError::DuplicateSymbol { symbol: symbol.clone() });
+    }
+    let mut res = if relays.symbols.is_empty() {
+        Response::new()
+    } else {
+        update_refs(deps.branch(), env.clone(), info.clone(), &relays.symbols, &relays.rates, &relays.resolve_times, &relays.request_ids, RelayExtras::default(), StalePolicy::Skip)?
+    };
+    if !remove.is_empty() {
+        let removed = remove_symbols(deps, env, info, &remove)?;
+        res.attributes.extend(removed.attributes.into_iter().filter(|attribute| attribute.key == "num_removed"));
+    }
+    Ok(res)
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn update_refs(deps: DepsMut, env: Env, info: MessageInfo, symbols: &[String], new_rates: &[u64], new_resolve_times: &[u64], new_request_ids: &[u64], extras: RelayExtras, stale_policy: StalePolicy) -> Result<Response, ContractError> {
+    let state = config(deps.storage).load()?;
+    if let StalePolicy::Overwrite = stale_policy {
+        assert_owner(&state, &info)?;
+    }
+    assert_relayer(&state, &env, &info, symbols)?;
+    apply_refs(deps, env, state, info.sender, symbols, new_rates, new_resolve_times, new_request_ids, extras, stale_policy)
+}
+
+pub fn relay_entries(deps: DepsMut, env: Env, info: MessageInfo, entries: Vec<RelayEntry>) -> Result<Response, ContractError> {
+    let mut relays = RelayPayload::default();
+    for entry in entries {
+        relays.symbols.push(entry.symbol);
+        relays.rates.push(entry.rate);
+        relays.resolve_times.push(entry.resolve_time);
+        relays.request_ids.push(entry.request_id);
+    }
+    update_refs(deps, env, info, &relays.symbols, &relays.rates, &relays.resolve_times, &relays.request_ids, RelayExtras::default(), StalePolicy::Skip)
+}
+
+pub fn heartbeat(deps: DepsMut, env: Env, info: MessageInfo, symbols: &[String], resolve_times: &[u64], request_ids: &[u64]) -> Result<Response, ContractError> {
+    let mut existing = vec![];
+    for symbol in symbols.iter() {
+        match REFS.may_load(deps.storage, symbol)? {
+            Some(ref_data) => existing.push(ref_data),
+            None => return Err(ContractError::SymbolNotFound { symbol: symbol.clone() }),
+        }
+    }
+    let rates: Vec<u64> = existing.iter().map(|ref_data| ref_data.rate).collect();
+    let std_devs: Vec<Option<u64>> = existing.iter().map(|ref_data| ref_data.std_dev).collect();
+    let decimals: Vec<u32> = existing.iter().map(|ref_data| ref_data.decimals).collect();
+    let negatives: Vec<bool> = existing.iter().map(|ref_data| ref_data.negative).collect();
+    update_refs(deps, env, info, symbols, &rates, resolve_times, request_ids, RelayExtras { std_devs: Some(&std_devs), decimals: Some(&decimals), negatives: Some(&negatives) }, StalePolicy::Skip)
+}
+
+pub fn relay_signed_rates(deps: DepsMut, env: Env, info: MessageInfo, symbols: &[String], rates: &[i64], resolve_times: &[u64], request_ids: &[u64]) -> Result<Response, ContractError> {
+    if !config(deps.storage).load()?.allow_signed_rates {
+        return Err(ContractError::SignedRatesDisabled {});
+    }
+    let magnitudes: Vec<u64> = rates.iter().map(|rate| rate.unsigned_abs()).collect();
+    let negatives: Vec<bool> = rates.iter().map(|rate| *rate < 0).collect();
+    update_refs(deps, env, info, symbols, &magnitudes, resolve_times, request_ids, RelayExtras { negatives: Some(&negatives), ..RelayExtras::default() }, StalePolicy::Skip)
+}
+
+/// The sender is not checked here; `payload` must be signed by one of `State::relayer_pubkeys`, which is
+/// recorded as `updated_by` in place of the sender.
+pub fn relay_signed(deps: DepsMut, env: Env, payload: Binary, signature: Binary, pubkey: Binary) -> Result<Response, ContractError> {
+    let state = config(deps.storage).load()?;
+    if !state.relayer_pubkeys.contains(&pubkey) {
+        return Err(ContractError::Unauthorized {});
+    }
+    let hash = Sha256::digest(payload.as_slice());
+    if !deps.api.secp256k1_verify(&hash, &signature, &pubkey).map_err(StdError::from)? {
+        return Err(ContractError::InvalidSignature {});
+    }
+    let signed: SignedRelayPayload = from_slice(&payload)?;
+    if signed.contract_addr != env.contract.address.as_str() || signed.chain_id != env.block.chain_id {
+        return Err(ContractError::WrongSignatureDomain {});
+    }
+    // nonces only move forward, so a payload cannot be replayed after a removal or a correction either
+    if let Some(last) = SIGNER_NONCES.may_load(deps.storage, pubkey.as_slice())? {
+        if signed.nonce <= last {
+            return Err(ContractError::NonceAlreadyUsed { nonce: signed.nonce, last });
+        }
+    }
+    SIGNER_NONCES.save(deps.storage, pubkey.as_slice(), &signed.nonce)?;
+    let relays = signed.relays;
+    let signer = Addr::unchecked(pubkey.to_base64());
+    apply_refs(deps, env, state, signer, &relays.symbols, &relays.rates, &relays.resolve_times, &relays.request_ids, RelayExtras::default(), StalePolicy::Skip)
+}
+
+/// Validates and writes an already authorized batch; `updater` is recorded as `updated_by`.
+#[allow(clippy::too_many_arguments)]
+fn apply_refs(deps: DepsMut, env: Env, mut state: State, updater: Addr, symbols: &[String], new_rates: &[u64], new_resolve_times: &[u64], new_request_ids: &[u64], extras: RelayExtras, stale_policy: StalePolicy) -> Result<Response, ContractError> {
+    if state.paused {
+        return Err(ContractError::ContractPaused {});
+    }
+    let len = symbols.len();
+    let std_devs_len = extras.std_devs.map(|std_devs| std_devs.len());
+    let decimals_len = extras.decimals.map(|decimals| decimals.len());
+    let negatives_len = extras.negatives.map(|negatives| negatives.len());
+    if new_rates.len() != len || new_request_ids.len() != len || new_resolve_times.len() != len || std_devs_len.unwrap_or(len) != len || decimals_len.unwrap_or(len) != len || negatives_len.unwrap_or(len) != len {
+        return Err(ContractError::DifferentArrayLength {
+            symbols_len: len,
+            rates_len: new_rates.len(),
+            resolve_times_len: new_resolve_times.len(),
+            request_ids_len: new_request_ids.len(),
+            std_devs_len,
+            decimals_len,
+        });
+    }
+    if len == 0 {
+        return Err(ContractError::EmptyRelay {});
+    }
+    if len > state.max_batch_size as usize {
+        return Err(ContractError::BatchTooLarge { size: len, max: state.max_batch_size });
+    }
+    let mut seen = HashSet::new();
+    for symbol in symbols.iter() {
+        validate_symbol(symbol, &state.symbol_separator)?;
+        if !seen.insert(symbol) {
+            return Err(ContractError::DuplicateSymbol { symbol: symbol.clone() });
+        }
+        if state.frozen.contains(symbol) {
+            return Err(ContractError::SymbolFrozen { symbol: symbol.clone() });
+        }
+        // a ref stored under an alias name would be shadowed by the alias on every read
+        if ALIASES.may_load(deps.storage, symbol)?.is_some() {
+            return Err(ContractError::SymbolIsAlias { symbol: symbol.clone() });
+        }
+    }
+    if extras.decimals.unwrap_or_default().iter().any(|&decimals| 10u64.checked_pow(decimals).is_none()) {
+        return Err(ContractError::InvalidDecimals {});
+    }
+    if let Some(max_future_skew_secs) = state.max_future_skew_secs {
+        let latest = env.block.time.nanos().saturating_add(max_future_skew_secs.saturating_mul(NANOS_PER_SECOND));
+        if let Some(idx) = (0..len).find(|&idx| new_resolve_times[idx] > latest) {
+            return Err(ContractError::ResolveTimeInFuture { symbol: symbols[idx].clone() });
+        }
+    }
+    let relay_seq = state.relay_seq + 1;
+    let mut res = Response::new();
+    res.add_attribute("action", "relay");
+    res.add_attribute("num_symbols", len.to_string());
+    // check every symbol before writing so a rejected batch leaves storage untouched
+    let mut writes = vec![];
+    for idx in 0..len {
+        let decimals = extras.decimals.map(|decimals| decimals[idx]).unwrap_or(state.usd_decimals);
+        let negative = extras.negatives.map(|negatives| negatives[idx]).unwrap_or(false) && new_rates[idx] != 0;
+        let existing = REFS.may_load(deps.storage, &symbols[idx])?;
+        if let Some(existing) = &existing {
+            if new_resolve_times[idx] < existing.resolve_time {
+                match stale_policy {
+                    StalePolicy::Skip => continue,
+                    StalePolicy::Reject => return Err(ContractError::StaleData { symbol: symbols[idx].clone() }),
+                    StalePolicy::Overwrite => {}
+                }
+            }
+            match (state.max_rate_change_bps, &stale_policy) {
+                (_, StalePolicy::Overwrite) | (None, _) => {}
+                (Some(max_bps), _) => {
+                    let new_rate = if negative { -(new_rates[idx] as i128) } else { new_rates[idx] as i128 };
+                    // compare at the finer of the two precisions so a decimals change cannot hide a jump
+                    let common = existing.decimals.max(decimals);
+                    let within = match (at_decimals(existing.signed_rate(), existing.decimals, common), at_decimals(new_rate, decimals, common)) {
+                        (Some(old_rate), Some(new_rate)) => within_bps(old_rate, new_rate, max_bps),
+                        _ => existing.rate == 0,
+                    };
+                    if !within {
+                        return Err(ContractError::RateJumpTooLarge { symbol: symbols[idx].clone() });
+                    }
+                }
+            }
+        }
+        let ref_data = RefData {
+            rate: new_rates[idx],
+            resolve_time: new_resolve_times[idx],
+            request_id: new_request_ids[idx],
+            std_dev: extras.std_devs.and_then(|std_devs| std_devs[idx]),
+            updated_by: updater.clone(),
+            decimals,
+            negative,
+            relay_seq,
+        };
+        // an identical resubmission costs no write
+        if existing.as_ref().map(|existing| RefData { relay_seq, ..existing.clone() }).as_ref() == Some(&ref_data) {
+            continue;
+        }
+        match (state.min_relay_interval_secs, &stale_policy) {
+            (_, StalePolicy::Overwrite) | (None, _) => {}
+            (Some(min_interval_secs), _) => {
+                if let Some(last_write) = LAST_WRITES.may_load(deps.storage, &symbols[idx])? {
+                    if env.block.time.nanos() < last_write.saturating_add(min_interval_secs.saturating_mul(NANOS_PER_SECOND)) {
+                        return Err(ContractError::RelayTooSoon { symbol: symbols[idx].clone() });
+                    }
+                }
+            }
+        }
+        let new_history = existing.as_ref().is_none_or(|existing| existing.decimals != decimals || existing.negative != negative);
+        writes.push((idx, ref_data, existing.map(|existing| existing.resolve_time), new_history));
+    }
+    res.add_attribute("num_updated", writes.len().to_string());
+    res.set_data(to_binary(&RelayResponse { num_updated: writes.len() as u32, relay_seq })?);
+    for (idx, ref_data, existing_resolve_time, new_history) in writes {
+        REFS.save(deps.storage, &symbols[idx], &ref_data)?;
+        if let Some(existing_resolve_time) = existing_resolve_time {
+            RESOLVE_TIMES.remove(deps.storage, (existing_resolve_time.into(), &symbols[idx]));
+        }
+        RESOLVE_TIMES.save(deps.storage, (ref_data.resolve_time.into(), &symbols[idx]), &symbols[idx])?;
+        LAST_WRITES.save(deps.storage, &symbols[idx], &env.block.time.nanos())?;
+        let mut samples = if new_history { vec![] } else { SAMPLES.may_load(deps.storage, &symbols[idx])?.unwrap_or_default() };
+        samples.push(RateSample { rate: ref_data.rate, resolve_time: ref_data.resolve_time });
+        let excess = samples.len().saturating_sub(state.twap_samples as usize);
+        samples.drain(..excess);
+        SAMPLES.save(deps.storage, &symbols[idx], &samples)?;
+        if existing_resolve_time.is_none() {
+            state.symbol_count += 1;
+        }
+        res.add_attribute(format!("symbol:{}", symbols[idx]), format!("{}@{}", ref_data.signed_rate(), new_resolve_times[idx]));
+    }
+    state.last_relay_time = env.block.time.nanos();
+    state.last_relay_block = env.block.height;
+    state.relay_seq = relay_seq;
+    config(deps.storage).save(&state)?;
+    Ok(res)
+}
+
+/// A stored rate of zero has nothing to compare against, so any new rate passes.
+fn within_bps(old_rate: i128, new_rate: i128, max_bps: u16) -> bool {
+    let diff = (old_rate - new_rate).unsigned_abs();
+    old_rate == 0 || diff * 10_000 <= old_rate.unsigned_abs() * max_bps as u128
+}
+
+fn at_decimals(rate: i128, decimals: u32, target: u32) -> Option<i128> {
+    rate.checked_mul(scale(target - decimals)? as i128)
+}
+
+/// Optional per-entry fields of a relay, each aligned with the symbols when set.
+#[derive(Default)]
+pub struct RelayExtras<'a> {
+    pub std_devs: Option<&'a [Option<u64>]>,
+    pub decimals: Option<&'a [u32]>,
+    pub negatives: Option<&'a [bool]>,
+}
+
+#[entry_point]
+pub fn reply(deps: DepsMut, _env: Env, msg: Reply) -> Result<Response, ContractError> {
+    if msg.id != TRANSFER_OWNERSHIP_REPLY_ID {
+        return Err(ContractError::UnknownReplyId { id: msg.id });
+    }
+    let pending_owner = PENDING_CONTRACT_OWNER.load(deps.storage)?;
+    PENDING_CONTRACT_OWNER.remove(deps.storage);
+    let mut state = config(deps.storage).load()?;
+    let mut res = Response::new();
+    res.add_attribute("action", "finalize_ownership_transfer");
+    // a failed acknowledgement is swallowed so the old owner keeps control
+    match msg.result {
+        ContractResult::Ok(_) => {
+            res.add_attribute("owner", &pending_owner);
+            state.owner = Some(pending_owner);
+            state.pending_owner = None;
+            config(deps.storage).save(&state)?;
+        }
+        ContractResult::Err(err) => {
+            res.add_attribute("rejected_owner", &pending_owner);
+            res.add_attribute("error", err);
+        }
+    }
+    Ok(namespaced(&state.event_namespace, res))
+}
+
+#[entry_point]
+pub fn query(deps: Deps, env: Env, msg: QueryMsg) -> Result<Binary, ContractError> {
+    let binary = match msg {
+        QueryMsg::GetAllRefs {} => to_binary(&query_refs(deps)?)?,
+        QueryMsg::GetRefs { symbols } => to_binary(&query_refs_by_symbols(deps, env, symbols)?)?,
+        QueryMsg::GetRefsPaginated { start_after, limit } => to_binary(&query_refs_paginated(deps, start_after, limit)?)?,
+        QueryMsg::GetSymbols { start_after, limit } => to_binary(&query_symbols(deps, start_after, limit)?)?,
+        QueryMsg::GetSymbolsByRequestId { request_id, start_after, limit } => to_binary(&query_symbols_page(deps, start_after, limit, |ref_data| ref_data.request_id == request_id)?)?,
+        QueryMsg::GetUpdatedSince { resolve_time, start_after, limit } => to_binary(&query_symbols_page(deps, start_after, limit, |ref_data| ref_data.resolve_time >= resolve_time)?)?,
+        QueryMsg::GetRelayers {} => to_binary(&query_relayers(deps)?)?,
+        QueryMsg::GetOwnership {} => to_binary(&query_ownership(deps)?)?,
+        QueryMsg::GetConfig {} => to_binary(&query_config(deps)?)?,
+        QueryMsg::GetLastRelay {} => to_binary(&query_last_relay(deps)?)?,
+        QueryMsg::Health {} => to_binary(&query_health(deps)?)?,
+        QueryMsg::IsPaused {} => to_binary(&load_state(deps)?.paused)?,
+        QueryMsg::GetSymbolRelayers { symbol } => to_binary(&query_symbol_relayers(deps, symbol)?)?,
+        QueryMsg::GetSignerNonce { pubkey } => to_binary(&SIGNER_NONCES.may_load(deps.storage, pubkey.as_slice())?)?,
+        QueryMsg::GetAlias { alias } => to_binary(&ALIASES.may_load(deps.storage, &alias)?)?,
+        QueryMsg::GetReferenceDataByDenom { base_denom, quote_denom } => to_binary(&query_reference_data_by_denom(deps, env, base_denom, quote_denom)?)?,
+        QueryMsg::GetSymbolCount {} => to_binary(&load_state(deps)?.symbol_count)?,
+        QueryMsg::GetOldestResolveTime {} => to_binary(&query_oldest_resolve_time(deps)?)?,
+        QueryMsg::HasSymbol { symbol } => to_binary(&(symbol == "USD" || REFS.may_load(deps.storage, &symbol)?.is_some()))?,
+        QueryMsg::GetContractVersion {} => to_binary(&get_contract_version(deps.storage)?)?,
+        QueryMsg::GetRef { symbol } => to_binary(&query_ref(deps, env, symbol)?)?,
+        QueryMsg::GetRefData { symbol } => to_binary(&get_ref_data(deps, env, symbol)?)?,
+        QueryMsg::GetTwap { symbol, window_secs } => to_binary(&query_twap(deps, env, symbol, window_secs)?)?,
+        QueryMsg::GetReferenceData { base, quote, decimals } => to_binary(&query_reference_data(deps, env, base, quote, decimals)?)?,
+        QueryMsg::GetReferenceDataFull { base, quote } => to_binary(&query_reference_data_full(deps, env, base, quote, None)?)?,
+        QueryMsg::GetReferenceDataWithAge { base, quote } => to_binary(&query_reference_data_with_age(deps, env, base, quote)?)?,
+        QueryMsg::GetLabeledReferenceData { base, quote } => to_binary(&query_labeled_reference_data(deps, env, base, quote)?)?,
+        QueryMsg::GetReferenceDataVerbose { base, quote } => to_binary(&query_reference_data_verbose(deps, env, base, quote)?)?,
+        QueryMsg::PreviewReferenceData { base_rate, quote_rate, base_resolve_time, quote_resolve_time } => to_binary(&query_preview_reference_data(deps, base_rate, quote_rate, base_resolve_time, quote_resolve_time)?)?,
+        QueryMsg::TryGetReferenceData { base, quote } => to_binary(&try_query_reference_data(deps, env, base, quote)?)?,
+        QueryMsg::GetReferenceDataScaled { base, quote, result_decimals } => to_binary(&query_reference_data_scaled(deps, env, base, quote, result_decimals)?)?,
+        QueryMsg::GetReferenceDataSplit { base, quote } => to_binary(&query_reference_data_split(deps, env, base, quote)?)?,
+        QueryMsg::GetInverseReferenceData { base, quote } => to_binary(&query_reference_data(deps, env, quote, base, None)?)?,
+        QueryMsg::GetReferenceDataSigned { base, quote } => to_binary(&query_reference_data_signed(deps, env, base, quote)?)?,
+        QueryMsg::GetReferenceDataStatus { base, quote } => to_binary(&query_reference_data_status(deps, env, base, quote)?)?,
+        QueryMsg::GetCrossRate { base, quote } => to_binary(&query_cross_rate(deps, env, base, quote)?)?,
+        QueryMsg::GetRawReferenceData { base, quote } => to_binary(&query_raw_reference_data(deps, env, base, quote)?)?,
+        QueryMsg::GetReferenceDataBulk { pairs } => to_binary(&query_reference_data_bulk(deps, env, pairs)?)?,
+        QueryMsg::GetReferenceDataTryBulk { pairs } => to_binary(&query_reference_data_try_bulk(deps, env, pairs)?)?,
+        QueryMsg::GetReferenceDataBulkPartial { pairs } => to_binary(&query_reference_data_bulk_partial(deps, env, pairs)?)?,
+        QueryMsg::GetMedianReferenceData { base, quotes, skip_missing } => to_binary(&query_median_reference_data(deps, env, base, quotes, skip_missing)?)?,
+        QueryMsg::GetBasketValue { base, holdings, skip_missing } => to_binary(&query_basket_value(deps, env, base, holdings, skip_missing)?)?,
+        QueryMsg::GetReferenceDataMulti { base, quotes } => to_binary(&query_reference_data_multi(deps, env, base, quotes)?)?,
+    };
+    Ok(binary)
+}
+
+/// Queries can reach a freshly deployed address before `instantiate` has saved any state.
+fn load_state(deps: Deps) -> Result<State, ContractError> {
+    config_read(deps.storage).may_load()?.ok_or(ContractError::NotInitialized {})
+}
+
+fn query_reference_data(deps: Deps, env: Env, base: String, quote: String, decimals: Option<u32>) -> Result<ReferenceData, ContractError> {
+    let data = query_reference_data_full(deps, env, base, quote, decimals)?;
+    Ok(ReferenceData {
+        rate: data.rate,
+        last_updated_base: data.last_updated_base,
+        last_updated_quote: data.last_updated_quote,
+    })
+}
+
+fn query_reference_data_with_age(deps: Deps, env: Env, base: String, quote: String) -> Result<ReferenceDataWithAge, ContractError> {
+    let now = env.block.time.nanos();
+    let data = query_reference_data(deps, env, base.clone(), quote.clone(), None)?;
+    // USD is fresh whatever timestamp `usd_timestamp_mode` reports for it
+    let age_secs = |symbol: &str, last_update: Uint128| if symbol == "USD" { 0 } else { (now as u128).saturating_sub(last_update.u128()) as u64 / NANOS_PER_SECOND };
+    Ok(ReferenceDataWithAge {
+        rate: data.rate,
+        last_updated_base: data.last_updated_base,
+        last_updated_quote: data.last_updated_quote,
+        base_age_secs: age_secs(&base, data.last_updated_base),
+        quote_age_secs: age_secs(&quote, data.last_updated_quote),
+    })
+}
+
+fn query_reference_data_by_denom(deps: Deps, env: Env, base_denom: String, quote_denom: String) -> Result<ReferenceData, ContractError> {
+    let base = DENOMS.may_load(deps.storage, &base_denom)?.ok_or(ContractError::UnknownDenom { denom: base_denom })?;
+    let quote = DENOMS.may_load(deps.storage, &quote_denom)?.ok_or(ContractError::UnknownDenom { denom: quote_denom })?;
+    query_reference_data(deps, env, base, quote, None)
+}
+
+fn query_labeled_reference_data(deps: Deps, env: Env, base: String, quote: String) -> Result<LabeledReferenceData, ContractError> {
+    let data = query_reference_data(deps, env, base.clone(), quote.clone(), None)?;
+    Ok(LabeledReferenceData {
+        base,
+        quote,
+        rate: data.rate,
+        last_updated_base: data.last_updated_base,
+        last_updated_quote: data.last_updated_quote,
+    })
+}
+
+fn query_reference_data_verbose(deps: Deps, env: Env, base: String, quote: String) -> Result<ReferenceDataVerbose, ContractError> {
+    let data = query_reference_data(deps, env.clone(), base.clone(), quote.clone(), None)?;
+    Ok(ReferenceDataVerbose {
+        data,
+        base: query_ref(deps, env.clone(), resolve_alias(deps, base)?)?,
+        quote: query_ref(deps, env, resolve_alias(deps, quote)?)?,
+    })
+}
+
+fn query_preview_reference_data(deps: Deps, base_rate: u64, quote_rate: u64, base_resolve_time: u64, quote_resolve_time: u64) -> Result<ReferenceData, ContractError> {
+    let state = load_state(deps)?;
+    let leg = |rate: u64, resolve_time: u64| RefData {
+        rate,
+        resolve_time,
+        request_id: 0,
+        std_dev: None,
+        updated_by: Addr::unchecked(""),
+        decimals: state.usd_decimals,
+        negative: false,
+        relay_seq: 0,
+    };
+    compute_reference_data(&leg(base_rate, base_resolve_time), &leg(quote_rate, quote_resolve_time), state.base_decimals, &state.rounding)
+}
+
+fn query_reference_data_scaled(deps: Deps, env: Env, base: String, quote: String, result_decimals: u32) -> Result<ReferenceData, ContractError> {
+    // 10^38 is the largest power of ten a u128 holds
+    if result_decimals > 38 {
+        return Err(ContractError::InvalidDecimals {});
+    }
+    query_reference_data(deps, env, base, quote, Some(result_decimals))
+}
+
+fn try_query_reference_data(deps: Deps, env: Env, base: String, quote: String) -> Result<Option<ReferenceData>, ContractError> {
+    match query_reference_data(deps, env, base, quote, None) {
+        Ok(data) => Ok(Some(data)),
+        Err(ContractError::SymbolNotFound { .. }) | Err(ContractError::RefDataNotAvailable {}) => Ok(None),
+        Err(err) => Err(err),
+    }
+}
+
+fn query_reference_data_split(deps: Deps, env: Env, base: String, quote: String) -> Result<ReferenceDataSplit, ContractError> {
+    let decimals = load_state(deps)?.base_decimals;
+    let rate = query_reference_data(deps, env, base, quote, None)?.rate.u128();
+    let unit = 10u128.pow(decimals);
+    Ok(ReferenceDataSplit {
+        integer: Uint128::from(rate / unit),
+        fraction: Uint128::from(rate % unit),
+        decimals,
+    })
+}
+
+fn query_reference_data_signed(deps: Deps, env: Env, base: String, quote: String) -> Result<SignedReferenceData, ContractError> {
+    let state = load_state(deps)?;
+    let scale = 10u128.pow(state.base_decimals);
+    let base_ref_data = signed_ref_data(deps, env.clone(), base.clone())?;
+    let quote_ref_data = signed_ref_data(deps, env, quote.clone())?;
+    let rate = if base == quote {
+        Uint128::from(scale)
+    } else if quote_ref_data.rate.is_zero() {
+        return Err(ContractError::DivideByZero {});
+    } else {
+        leg_ratio(&base_ref_data, &quote_ref_data, scale, &state.rounding)?
+    };
+    Ok(SignedReferenceData {
+        rate,
+        negative: base != quote && !rate.is_zero() && base_ref_data.negative != quote_ref_data.negative,
+        last_updated_base: base_ref_data.last_update,
+        last_updated_quote: quote_ref_data.last_update,
+    })
+}
+
+/// Staleness and overflow are still returned as errors; only the conditions in `RefStatus` are folded into the payload.
+fn query_reference_data_status(deps: Deps, env: Env, base: String, quote: String) -> Result<ReferenceDataStatus, ContractError> {
+    let missing = |status: RefStatus| Ok(ReferenceDataStatus { status, data: None });
+    match get_ref_data(deps, env.clone(), base.clone()) {
+        Err(ContractError::SymbolNotFound { .. }) | Err(ContractError::RefDataNotAvailable {}) => return missing(RefStatus::BaseMissing),
+        Err(err) => return Err(err),
+        Ok(_) => {}
+    }
+    match get_ref_data(deps, env.clone(), quote.clone()) {
+        Err(ContractError::SymbolNotFound { .. }) | Err(ContractError::RefDataNotAvailable {}) => return missing(RefStatus::QuoteMissing),
+        Err(err) => return Err(err),
+        Ok(_) => {}
+    }
+    match query_reference_data(deps, env, base, quote, None) {
+        Ok(data) => Ok(ReferenceDataStatus { status: RefStatus::Ok, data: Some(data) }),
+        Err(ContractError::DivideByZero {}) => missing(RefStatus::QuoteZero),
+        Err(err) => Err(err),
+    }
+}
+
+/// `decimals` overrides the configured `base_decimals`; the division rounds as configured, truncating by default.
+fn query_reference_data_full(deps: Deps, env: Env, base: String, quote: String, decimals: Option<u32>) -> Result<ReferenceDataFull, ContractError> {
+    let state = load_state(deps)?;
+    let scale = scale(decimals.unwrap_or(state.base_decimals)).ok_or(ContractError::InvalidDecimals {})?;
+    let base_ref_data = get_ref_data(deps, env.clone(), base.clone())?;
+    let quote_ref_data = if base == quote { base_ref_data.clone() } else { get_ref_data(deps, env, quote.clone())? };
+    pair_reference_data(&base, &base_ref_data, &quote, &quote_ref_data, scale, &state.rounding)
+}
+
+/// `base/quote` from legs that were already read, shared by the single-pair and multi-quote queries.
+fn pair_reference_data(base: &str, base_ref_data: &RefDataResponse, quote: &str, quote_ref_data: &RefDataResponse, scale: u128, rounding: &RoundingMode) -> Result<ReferenceDataFull, ContractError> {
+    // a symbol against itself is exactly one, even when its stored rate is zero
+    let rate = if base == quote {
+        Uint128::from(scale)
+    } else if quote_ref_data.rate.is_zero() {
+        return Err(ContractError::DivideByZero {});
+    } else {
+        leg_ratio(base_ref_data, quote_ref_data, scale, rounding)?
+    };
+    Ok(ReferenceDataFull {
+        rate,
+        last_updated_base: base_ref_data.last_update,
+        last_updated_quote: quote_ref_data.last_update,
+        base_request_id: base_ref_data.request_id,
+        quote_request_id: quote_ref_data.request_id,
+        base_std_dev: base_ref_data.std_dev,
+        quote_std_dev: quote_ref_data.std_dev,
+    })
+}
+
+fn leg_ratio(base: &RefDataResponse, quote: &RefDataResponse, scale: u128, rounding: &RoundingMode) -> Result<Uint128, ContractError> {
+    ratio(base.rate.u128(), base.decimals, quote.rate.u128(), quote.decimals, scale, rounding)
+}
+
+/// `rate = (base/USD * 10^base_decimals) * 10^base_decimals / (quote/USD * 10^base_decimals)`, where each
+/// USD leg is truncated on its own. The USD scale cancels, so this only differs from the direct division in
+/// `GetReferenceData` by that intermediate rounding.
+fn query_cross_rate(deps: Deps, env: Env, base: String, quote: String) -> Result<ReferenceData, ContractError> {
+    let state = load_state(deps)?;
+    let base_usd = query_reference_data(deps, env.clone(), base, String::from("USD"), None)?;
+    let quote_usd = query_reference_data(deps, env, quote, String::from("USD"), None)?;
+    if quote_usd.rate.is_zero() {
+        return Err(ContractError::DivideByZero {});
+    }
+    Ok(ReferenceData {
+        rate: mul_div(base_usd.rate, 10u128.pow(state.base_decimals), quote_usd.rate)?,
+        last_updated_base: base_usd.last_updated_base,
+        last_updated_quote: quote_usd.last_updated_base,
+    })
+}
+
+fn query_raw_reference_data(deps: Deps, env: Env, base: String, quote: String) -> Result<RawReferenceData, ContractError> {
+    let base_ref = query_ref(deps, env.clone(), base.clone())?;
+    let quote_ref = query_ref(deps, env, quote.clone())?;
+    if base_ref.negative {
+        return Err(ContractError::NegativeRate { symbol: base });
+    }
+    if quote_ref.negative {
+        return Err(ContractError::NegativeRate { symbol: quote });
+    }
+    Ok(RawReferenceData {
+        base_rate: base_ref.rate,
+        quote_rate: quote_ref.rate,
+        last_updated_base: base_ref.resolve_time,
+        last_updated_quote: quote_ref.resolve_time,
+        base_request_id: base_ref.request_id,
+        quote_request_id: quote_ref.request_id,
+    })
+}
+
+fn query_median_reference_data(deps: Deps, env: Env, base: String, quotes: Vec<String>, skip_missing: bool) -> Result<MedianReferenceData, ContractError> {
+    let mut rates = vec![];
+    let mut symbols = vec![];
+    for quote in quotes {
+        match query_reference_data(deps, env.clone(), base.clone(), quote.clone(), None) {
+            Ok(data) => {
+                rates.push(data.rate.u128());
+                symbols.push(quote);
+            }
+            Err(ContractError::SymbolNotFound { .. }) | Err(ContractError::RefDataNotAvailable {}) if skip_missing => {}
+            Err(err) => return Err(err),
+        }
+    }
+    if rates.is_empty() {
+        return Err(ContractError::NoReferenceData {});
+    }
+    rates.sort_unstable();
+    let mid = rates.len() / 2;
+    // halving each side first cannot overflow, adding back the carry of two odd values
+    let rate = if rates.len() % 2 == 1 {
+        rates[mid]
+    } else {
+        rates[mid - 1] / 2 + rates[mid] / 2 + (rates[mid - 1] % 2 + rates[mid] % 2) / 2
+    };
+    Ok(MedianReferenceData { rate: Uint128::from(rate), symbols })
+}
+
+fn query_basket_value(deps: Deps, env: Env, base: String, holdings: Vec<(String, Uint128)>, skip_missing: bool) -> Result<BasketValue, ContractError> {
+    let scale = Uint128::from(10u128.pow(load_state(deps)?.base_decimals));
+    let mut value = Uint128::zero();
+    let mut symbols = vec![];
+    for (symbol, amount) in holdings {
+        match query_reference_data(deps, env.clone(), symbol.clone(), base.clone(), None) {
+            Ok(data) => {
+                value = value.checked_add(mul_div(amount, data.rate.u128(), scale)?).map_err(|_| ContractError::Overflow {})?;
+                symbols.push(symbol);
+            }
+            Err(ContractError::SymbolNotFound { .. }) | Err(ContractError::RefDataNotAvailable {}) if skip_missing => {}
+            Err(err) => return Err(err),
+        }
+    }
+    Ok(BasketValue { value, symbols })
+}
+
+fn query_reference_data_bulk(deps: Deps, env: Env, pairs: Vec<(String, String)>) -> Result<Vec<ReferenceData>, ContractError> {
+    pairs
+        .into_iter()
+        .map(|(base, quote)| query_reference_data(deps, env.clone(), base, quote, None))
+        .collect()
+}
+
+fn query_reference_data_bulk_partial(deps: Deps, env: Env, pairs: Vec<(String, String)>) -> Result<PartialBulkResponse, ContractError> {
+    let results: Vec<Option<ReferenceData>> = query_reference_data_try_bulk(deps, env, pairs)?.into_iter().map(|result| result.data).collect();
+    let failed_indices = (0..results.len() as u32).filter(|&idx| results[idx as usize].is_none()).collect();
+    Ok(PartialBulkResponse { results, failed_indices })
+}
+
+fn query_reference_data_try_bulk(deps: Deps, env: Env, pairs: Vec<(String, String)>) -> Result<Vec<ReferenceDataResult>, ContractError> {
+    // without state every pair would fail the same way
+    load_state(deps)?;
+    Ok(pairs
+        .into_iter()
+        .map(|(base, quote)| match query_reference_data(deps, env.clone(), base, quote, None) {
+            Ok(data) => ReferenceDataResult { data: Some(data), error: None },
+            Err(err) => ReferenceDataResult { data: None, error: Some(err.to_string()) },
+        })
+        .collect())
+}
+
+fn query_reference_data_multi(deps: Deps, env: Env, base: String, quotes: Vec<String>) -> Result<Vec<ReferenceData>, ContractError> {
+    let state = load_state(deps)?;
+    let scale = scale(state.base_decimals).ok_or(ContractError::InvalidDecimals {})?;
+    let base_ref_data = get_ref_data(deps, env.clone(), base.clone())?;
+    quotes
+        .into_iter()
+        .map(|quote| {
+            let quote_ref_data = if quote == base { base_ref_data.clone() } else { get_ref_data(deps, env.clone(), quote.clone())? };
+            let data = pair_reference_data(&base, &base_ref_data, &quote, &quote_ref_data, scale, &state.rounding)?;
+            Ok(ReferenceData {
+                rate: data.rate,
+                last_updated_base: data.last_updated_base,
+                last_updated_quote: data.last_updated_quote,
+            })
+        })
+        .collect()
+}
+
+/// Each symbol is read like `GetRef`, so the synthetic USD ref is included and staleness still fails the query.
+fn query_refs_by_symbols(deps: Deps, env: Env, symbols: Vec<String>) -> Result<Vec<Option<RefData>>, ContractError> {
+    symbols
+        .into_iter()
+        .map(|symbol| match query_ref(deps, env.clone(), symbol) {
+            Ok(ref_data) => Ok(Some(ref_data)),
+            Err(ContractError::SymbolNotFound { .. }) => Ok(None),
+            Err(err) => Err(err),
+        })
+        .collect()
+}
+
+fn query_refs(deps: Deps) -> StdResult<ConfigResponse> {
+    let refs = REFS
+        .range(deps.storage, None, None, Order::Ascending)
+        .map(|item| {
+            let (key, ref_data) = item?;
+            Ok((String::from_utf8(key)?, ref_data))
+        })
+        .collect::<StdResult<BTreeMap<String, RefData>>>()?;
+    Ok(ConfigResponse { refs })
+}
+
+fn query_refs_paginated(deps: Deps, start_after: Option<String>, limit: Option<u32>) -> StdResult<RefsPageResponse> {
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+    let start = start_after.map(|symbol| Bound::exclusive(symbol.as_bytes()));
+    let refs = REFS
+        .range(deps.storage, start, None, Order::Ascending)
+        .take(limit)
+        .map(|item| {
+            let (key, ref_data) = item?;
+            Ok((String::from_utf8(key)?, ref_data))
+        })
+        .collect::<StdResult<Vec<(String, RefData)>>>()?;
+    let symbols = refs.iter().map(|(symbol, _)| symbol.clone()).collect();
+    Ok(RefsPageResponse { refs, symbols })
+}
+
+fn query_oldest_resolve_time(deps: Deps) -> StdResult<Option<(String, u64)>> {
+    match RESOLVE_TIMES.range(deps.storage, None, None, Order::Ascending).next().transpose()? {
+        Some((_, symbol)) => {
+            let ref_data = REFS.load(deps.storage, &symbol)?;
+            Ok(Some((symbol, ref_data.resolve_time)))
+        }
+        None => Ok(None),
+    }
+}
+
+fn query_health(deps: Deps) -> Result<HealthResponse, ContractError> {
+    let state = load_state(deps)?;
+    Ok(HealthResponse {
+        paused: state.paused,
+        symbol_count: state.symbol_count,
+        last_relay_time: state.last_relay_time,
+        oldest_resolve_time: query_oldest_resolve_time(deps)?.map(|(_, resolve_time)| resolve_time),
+    })
+}
+
+fn query_symbols(deps: Deps, start_after: Option<String>, limit: Option<u32>) -> StdResult<Vec<String>> {
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+    let start = start_after.map(|symbol| Bound::exclusive(symbol.as_bytes()));
+    REFS
+        .keys(deps.storage, start, None, Order::Ascending)
+        .take(limit)
+        .map(|key| Ok(String::from_utf8(key)?))
+        .collect()
+}
+
+fn query_symbols_page(deps: Deps, start_after: Option<String>, limit: Option<u32>, matches: impl Fn(&RefData) -> bool) -> StdResult<SymbolsPageResponse> {
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+    let start = start_after.map(|symbol| Bound::exclusive(symbol.as_bytes()));
+    let mut symbols = vec![];
+    let mut scanned = 0;
+    let mut last = None;
+    for item in REFS.range(deps.storage, start, None, Order::Ascending).take(limit) {
+        let (key, ref_data) = item?;
+        let symbol = String::from_utf8(key)?;
+        if matches(&ref_data) {
+            symbols.push(symbol.clone());
+        }
+        scanned += 1;
+        last = Some(symbol);
+    }
+    let next_start_after = if scanned == limit { last } else { None };
+    Ok(SymbolsPageResponse { symbols, next_start_after })
+}
+
+fn query_relayers(deps: Deps) -> Result<RelayersResponse, ContractError> {
+    let state = load_state(deps)?;
+    let active_after = state
+        .relayers
+        .iter()
+        .map(|relayer| state.relayer_active_after.get(relayer).copied().unwrap_or(0))
+        .collect();
+    Ok(RelayersResponse { relayers: state.relayers, active_after })
+}
+
+fn query_symbol_relayers(deps: Deps, symbol: String) -> Result<RelayersResponse, ContractError> {
+    let state = load_state(deps)?;
+    let relayers = state.symbol_relayers.get(&symbol).cloned().unwrap_or_default();
+    let active_after = vec![0; relayers.len()];
+    Ok(RelayersResponse { relayers, active_after })
+}
+
+fn query_config(deps: Deps) -> Result<FullConfigResponse, ContractError> {
+    let state = load_state(deps)?;
+    Ok(FullConfigResponse {
+        owner: state.owner,
+        pending_owner: state.pending_owner,
+        relayers: state.relayers,
+        paused: state.paused,
+        symbol_relayers: state.symbol_relayers.into_iter().collect(),
+        symbol_count: state.symbol_count,
+        max_staleness_secs: state.max_staleness_secs,
+        base_decimals: state.base_decimals,
+        usd_decimals: state.usd_decimals,
+        usd_rate: state.usd_rate,
+        symbol_separator: state.symbol_separator,
+        max_future_skew_secs: state.max_future_skew_secs,
+        max_rate_change_bps: state.max_rate_change_bps,
+        max_batch_size: state.max_batch_size,
+        relayer_active_after: state.relayer_active_after.into_iter().collect(),
+        last_relay_time: state.last_relay_time,
+        last_relay_block: state.last_relay_block,
+        relayer_pubkeys: state.relayer_pubkeys,
+        min_relay_interval_secs: state.min_relay_interval_secs,
+        allow_signed_rates: state.allow_signed_rates,
+        relay_seq: state.relay_seq,
+        rounding: state.rounding,
+        frozen: state.frozen,
+        strict_request_id: state.strict_request_id,
+        event_namespace: state.event_namespace,
+        usd_timestamp_mode: state.usd_timestamp_mode,
+        twap_samples: state.twap_samples,
+    })
+}
+
+fn query_last_relay(deps: Deps) -> Result<LastRelayResponse, ContractError> {
+    let state = load_state(deps)?;
+    Ok(LastRelayResponse { last_relay_time: state.last_relay_time, last_relay_block: state.last_relay_block, relay_seq: state.relay_seq })
+}
+
+fn query_ownership(deps: Deps) -> Result<OwnershipResponse, ContractError> {
+    let state = load_state(deps)?;
+    Ok(OwnershipResponse { owner: state.owner, pending_owner: state.pending_owner })
+}
+
+/// Each sample holds until the next one, the last until now; a sample resolved after now counts for nothing.
+fn query_twap(deps: Deps, env: Env, symbol: String, window_secs: u64) -> Result<TwapResponse, ContractError> {
+    let ref_data = query_ref(deps, env.clone(), symbol.clone())?;
+    if ref_data.negative {
+        return Err(ContractError::NegativeRate { symbol });
+    }
+    let mut samples = match symbol.as_str() {
+        "USD" => vec![RateSample { rate: ref_data.rate, resolve_time: ref_data.resolve_time }],
+        _ => SAMPLES.may_load(deps.storage, &symbol)?.unwrap_or_default(),
+    };
+    samples.sort_by_key(|sample| sample.resolve_time);
+    let now = env.block.time.nanos();
+    let start = now.saturating_sub(window_secs.saturating_mul(NANOS_PER_SECOND));
+    // the last sample at or before the start is still in effect when the window opens
+    let first = samples.iter().rposition(|sample| sample.resolve_time <= start).unwrap_or(0);
+    let samples = &samples[first..];
+    let mut weighted = 0u128;
+    let mut total = 0u128;
+    for (idx, sample) in samples.iter().enumerate() {
+        let from = sample.resolve_time.max(start).min(now);
+        let until = samples.get(idx + 1).map_or(now, |next| next.resolve_time.max(start).min(now));
+        weighted += sample.rate as u128 * (until - from) as u128;
+        total += (until - from) as u128;
+    }
+    let rate = match (total, samples.last()) {
+        (0, Some(last)) => last.rate as u128,
+        (0, None) => ref_data.rate as u128,
+        _ => weighted / total,
+    };
+    Ok(TwapResponse {
+        rate: Uint128::from(rate),
+        decimals: ref_data.decimals,
+        num_samples: samples.len() as u32,
+    })
+}
+
+fn query_ref(deps: Deps, env: Env, symbol: String) -> Result<RefData, ContractError> {
+    let state = load_state(deps)?;
+    if symbol == "USD" {
+        let time = match state.usd_timestamp_mode {
+            UsdTimestampMode::BlockTime => env.block.time.nanos(),
+            UsdTimestampMode::Zero => 0,
+            UsdTimestampMode::MaxU64 => u64::MAX,
+        };
+        return Ok(usd_ref_data(state.usd_rate, state.usd_decimals, time, env.contract.address));
+    }
+    let ref_data = match REFS.may_load(deps.storage, &symbol)? {
+        Some(ref_data) => ref_data,
+        None => return Err(ContractError::SymbolNotFound { symbol }),
+    };
+    // a zero resolve time means the symbol was never resolved by the oracle
+    if ref_data.resolve_time == 0 || (state.strict_request_id && ref_data.request_id == 0) {
+        return Err(ContractError::RefDataNotAvailable {});
+    }
+    if let Some(max_staleness_secs) = state.max_staleness_secs {
+        let age = env.block.time.nanos().saturating_sub(ref_data.resolve_time_nanos()) / NANOS_PER_SECOND;
+        if age > max_staleness_secs {
+            return Err(ContractError::RefDataTooOld { symbol, age });
+        }
+    }
+    Ok(ref_data)
+}
+
+/// The unsigned rate math only accepts non-negative legs; see `signed_ref_data` for signed feeds.
+fn get_ref_data(deps: Deps, env: Env, symbol: String) -> Result<RefDataResponse, ContractError> {
+    let ref_data = signed_ref_data(deps, env, symbol.clone())?;
+    if ref_data.negative {
+        return Err(ContractError::NegativeRate { symbol });
+    }
+    Ok(ref_data)
+}
+
+fn resolve_alias(deps: Deps, symbol: String) -> StdResult<String> {
+    Ok(ALIASES.may_load(deps.storage, &symbol)?.unwrap_or(symbol))
+}
+
+fn signed_ref_data(deps: Deps, env: Env, symbol: String) -> Result<RefDataResponse, ContractError> {
+    let ref_data = query_ref(deps, env, resolve_alias(deps, symbol)?)?;
+    Ok(RefDataResponse {
+        rate: Uint128::from(ref_data.rate),
+        last_update: Uint128::from(ref_data.resolve_time),
+        request_id: ref_data.request_id,
+        std_dev: ref_data.std_dev,
+        decimals: ref_data.decimals,
+        negative: ref_data.negative,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cosmwasm_std::testing::{mock_dependencies, mock_env, mock_info};
+    use cosmwasm_std::{attr, from_binary, Addr, CosmosMsg, SubcallResponse};
+    use cw2::ContractVersion;
+
+    const E9: u128 = 1_000_000_000;
+    const E18: u128 = 1_000_000_000_000_000_000;
+
+    fn legacy_state(refs: HashMap<String, RefData>) -> LegacyState {
+        LegacyState {
+            owner: Addr::unchecked("creator"),
+            pending_owner: None,
+            relayers: vec![Addr::unchecked("relayer1")],
+            paused: false,
+            symbol_relayers: HashMap::new(),
+            symbol_count: refs.len() as u64,
+            max_staleness_secs: None,
+            base_decimals: 18,
+            usd_decimals: 9,
+            refs,
+        }
+    }
+    use std::collections::HashMap;
+
+    #[test]
+    fn proper_initialization() {
+        let mut deps = mock_dependencies(&[]);
+
+        let msg = InstantiateMsg::default();
+        let info = mock_info("creator", &[]);
+
+        // we can just call .unwrap() to assert this was a success
+        let res = instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+        assert_eq!(0, res.messages.len());
+
+        // it worked, let's query the state
+        let res = query(deps.as_ref(), mock_env(), QueryMsg::GetAllRefs {}).unwrap();
+        let value: ConfigResponse = from_binary(&res).unwrap();
+        assert_eq!(BTreeMap::new(), value.refs);
+    }
+
+    #[test]
+    fn insert_one() {
+        let mut deps = mock_dependencies(&[]);
+
+        let msg = InstantiateMsg::default();
+        let info = mock_info("creator", &[]);
+        let _res = instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+        let info = mock_info("creator", &[]);
+        let msg = ExecuteMsg::Relay { symbols: vec![String::from("ETH")], rates: vec![1u64], resolve_times: vec![2u64], request_ids: vec![3u64] };
+        let _res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+        let res = query(deps.as_ref(), mock_env(), QueryMsg::GetAllRefs {}).unwrap();
+        let value: ConfigResponse = from_binary(&res).unwrap();
+        let mut mock_map = BTreeMap::new();
+
+        mock_map.insert(String::from("ETH"), RefData{rate: 1u64, resolve_time: 2u64, request_id: 3u64, std_dev: None, updated_by: Addr::unchecked("creator"), decimals: 9, negative: false, relay_seq: 1});
+
+        assert_eq!(mock_map, value.refs);
+    }
+
+    #[test]
+    fn insert_batch() {
+        let mut deps = mock_dependencies(&[]);
+
+        let msg = InstantiateMsg::default();
+        let info = mock_info("creator", &[]);
+        let _res = instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+        let info = mock_info("creator", &[]);
+        let msg = ExecuteMsg::Relay { symbols: vec![String::from("ETH"), String::from("BAND")], rates: vec![1u64, 100u64], resolve_times: vec![2u64, 200u64], request_ids: vec![3u64, 300u64] };
+        let _res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+        let res = query(deps.as_ref(), mock_env(), QueryMsg::GetAllRefs {}).unwrap();
+        let value: ConfigResponse = from_binary(&res).unwrap();
+        let mut mock_map = BTreeMap::new();
+
+        mock_map.insert(String::from("ETH"), RefData{rate: 1u64, resolve_time: 2u64, request_id: 3u64, std_dev: None, updated_by: Addr::unchecked("creator"), decimals: 9, negative: false, relay_seq: 1});
+        mock_map.insert(String::from("BAND"), RefData{rate: 100u64, resolve_time: 200u64, request_id: 300u64, std_dev: None, updated_by: Addr::unchecked("creator"), decimals: 9, negative: false, relay_seq: 1});
+
+        assert_eq!(mock_map, value.refs);
+    }
+
+    #[test]
+    fn update_rate() {
+        let mut deps = mock_dependencies(&[]);
+
+        let msg = InstantiateMsg::default();
+        let info = mock_info("creator", &[]);
+        let _res = instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+        let info = mock_info("creator", &[]);
+        let msg = ExecuteMsg::Relay { symbols: vec![String::from("MATIC")], rates: vec![12u64], resolve_times: vec![124824u64], request_ids: vec![69u64] };
+        let _res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+        let res = query(deps.as_ref(), mock_env(), QueryMsg::GetAllRefs {}).unwrap();
+        let value: ConfigResponse = from_binary(&res).unwrap();
+
+        let mut mock_map01 = BTreeMap::new();
+        mock_map01.insert(String::from("MATIC"), RefData{rate: 12u64, resolve_time: 124824u64, request_id: 69u64, std_dev: None, updated_by: Addr::unchecked("creator"), decimals: 9, negative: false, relay_seq: 1});
+        assert_eq!(mock_map01, value.refs);
+
+        let info = mock_info("creator", &[]);
+        let msg = ExecuteMsg::Relay { symbols: vec![String::from("MATIC")], rates: vec![24u64], resolve_times: vec![124824u64], request_ids: vec![69u64] };
+        let _res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+        let res = query(deps.as_ref(), mock_env(), QueryMsg::GetAllRefs {}).unwrap();
+        let value: ConfigResponse = from_binary(&res).unwrap();
+
+        let mut mock_map02 = BTreeMap::new();
+        mock_map02.insert(String::from("MATIC"), RefData{rate: 24u64, resolve_time: 124824u64, request_id: 69u64, std_dev: None, updated_by: Addr::unchecked("creator"), decimals: 9, negative: false, relay_seq: 2});
+        assert_eq!(mock_map02, value.refs);
+    }
+
+    #[test]
+    fn relay_owner_only() {
+        let mut deps = mock_dependencies(&[]);
+
+        let msg = InstantiateMsg::default();
+        let info = mock_info("creator", &[]);
+        let _res = instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+        let info = mock_info("sender", &[]);
+        let msg = ExecuteMsg::Relay { symbols: vec![String::from("ETH")], rates: vec![1u64], resolve_times: vec![2u64], request_ids: vec![3u64] };
+        let err = execute(deps.as_mut(), mock_env(), info, msg).unwrap_err();
+        assert!(matches!(err, ContractError::Unauthorized {}));
+
+        let res = query(deps.as_ref(), mock_env(), QueryMsg::GetAllRefs {}).unwrap();
+        let value: ConfigResponse = from_binary(&res).unwrap();
+        assert_eq!(BTreeMap::new(), value.refs);
+
+        let info = mock_info("creator", &[]);
+        let msg = ExecuteMsg::Relay { symbols: vec![String::from("ETH")], rates: vec![1u64], resolve_times: vec![2u64], request_ids: vec![3u64] };
+        let _res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+        let res = query(deps.as_ref(), mock_env(), QueryMsg::GetAllRefs {}).unwrap();
+        let value: ConfigResponse = from_binary(&res).unwrap();
+        let mut mock_map = BTreeMap::new();
+        mock_map.insert(String::from("ETH"), RefData{rate: 1u64, resolve_time: 2u64, request_id: 3u64, std_dev: None, updated_by: Addr::unchecked("creator"), decimals: 9, negative: false, relay_seq: 1});
+        assert_eq!(mock_map, value.refs);
+
+        let res = query(deps.as_ref(), mock_env(), QueryMsg::GetOwnership {}).unwrap();
+        let value: OwnershipResponse = from_binary(&res).unwrap();
+        assert_eq!(Some(Addr::unchecked("creator")), value.owner);
+    }
+
+    #[test]
+    fn relayer_whitelist() {
+        let mut deps = mock_dependencies(&[]);
+
+        let msg = InstantiateMsg::default();
+        let info = mock_info("creator", &[]);
+        let _res = instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+        let info = mock_info("relayer1", &[]);
+        let msg = ExecuteMsg::Relay { symbols: vec![String::from("ETH")], rates: vec![1u64], resolve_times: vec![2u64], request_ids: vec![3u64] };
+        let err = execute(deps.as_mut(), mock_env(), info, msg).unwrap_err();
+        assert!(matches!(err, ContractError::Unauthorized {}));
+
+        // only the owner can manage relayers
+        let info = mock_info("relayer1", &[]);
+        let msg = ExecuteMsg::AddRelayer { address: String::from("relayer1") };
+        let err = execute(deps.as_mut(), mock_env(), info, msg).unwrap_err();
+        assert!(matches!(err, ContractError::Unauthorized {}));
+
+        let info = mock_info("creator", &[]);
+        let msg = ExecuteMsg::AddRelayer { address: String::from("relayer1") };
+        let _res = execute(deps.as_mut(), mock_env(), info.clone(), msg.clone()).unwrap();
+        // adding the same relayer twice is a no-op
+        let _res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+        let info = mock_info("creator", &[]);
+        let msg = ExecuteMsg::AddRelayer { address: String::from("relayer2") };
+        let _res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+        let res = query(deps.as_ref(), mock_env(), QueryMsg::GetRelayers {}).unwrap();
+        let value: RelayersResponse = from_binary(&res).unwrap();
+        assert_eq!(vec![Addr::unchecked("relayer1"), Addr::unchecked("relayer2")], value.relayers);
+
+        let info = mock_info("relayer1", &[]);
+        let msg = ExecuteMsg::Relay { symbols: vec![String::from("ETH")], rates: vec![1u64], resolve_times: vec![2u64], request_ids: vec![3u64] };
+        let _res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+        let info = mock_info("creator", &[]);
+        let msg = ExecuteMsg::RemoveRelayer { address: String::from("relayer1") };
+        let _res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+        let res = query(deps.as_ref(), mock_env(), QueryMsg::GetRelayers {}).unwrap();
+        let value: RelayersResponse = from_binary(&res).unwrap();
+        assert_eq!(vec![Addr::unchecked("relayer2")], value.relayers);
+
+        let info = mock_info("relayer1", &[]);
+        let msg = ExecuteMsg::Relay { symbols: vec![String::from("ETH")], rates: vec![4u64], resolve_times: vec![5u64], request_ids: vec![6u64] };
+        let err = execute(deps.as_mut(), mock_env(), info, msg).unwrap_err();
+        assert!(matches!(err, ContractError::Unauthorized {}));
+    }
+
+    #[test]
+    fn per_symbol_relayers() {
+        let mut deps = mock_dependencies(&[]);
+
+        let msg = InstantiateMsg::default();
+        let info = mock_info("creator", &[]);
+        let _res = instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+        let info = mock_info("relayer1", &[]);
+        let msg = ExecuteMsg::SetSymbolRelayers { symbol: String::from("ETH"), relayers: vec![String::from("relayer1")] };
+        let err = execute(deps.as_mut(), mock_env(), info, msg).unwrap_err();
+        assert!(matches!(err, ContractError::Unauthorized {}));
+
+        let info = mock_info("creator", &[]);
+        let msg = ExecuteMsg::SetSymbolRelayers { symbol: String::from("ETH"), relayers: vec![String::from("relayer1")] };
+        let _res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+        let res = query(deps.as_ref(), mock_env(), QueryMsg::GetSymbolRelayers { symbol: String::from("ETH") }).unwrap();
+        let value: RelayersResponse = from_binary(&res).unwrap();
+        assert_eq!(vec![Addr::unchecked("relayer1")], value.relayers);
+        let res = query(deps.as_ref(), mock_env(), QueryMsg::GetSymbolRelayers { symbol: String::from("BAND") }).unwrap();
+        let value: RelayersResponse = from_binary(&res).unwrap();
+        assert!(value.relayers.is_empty());
+
+        let info = mock_info("relayer1", &[]);
+        let msg = ExecuteMsg::Relay { symbols: vec![String::from("ETH")], rates: vec![1u64], resolve_times: vec![2u64], request_ids: vec![3u64] };
+        let _res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+        // one unauthorized symbol rejects the whole batch
+        let info = mock_info("relayer1", &[]);
+        let msg = ExecuteMsg::Relay { symbols: vec![String::from("ETH"), String::from("BAND")], rates: vec![10u64, 100u64], resolve_times: vec![20u64, 200u64], request_ids: vec![30u64, 300u64] };
+        let err = execute(deps.as_mut(), mock_env(), info, msg).unwrap_err();
+        assert!(matches!(err, ContractError::Unauthorized {}));
+
+        let res = query(deps.as_ref(), mock_env(), QueryMsg::GetAllRefs {}).unwrap();
+        let value: ConfigResponse = from_binary(&res).unwrap();
+        let mut mock_map = BTreeMap::new();
+        mock_map.insert(String::from("ETH"), RefData{rate: 1u64, resolve_time: 2u64, request_id: 3u64, std_dev: None, updated_by: Addr::unchecked("relayer1"), decimals: 9, negative: false, relay_seq: 1});
+        assert_eq!(mock_map, value.refs);
+
+        // global relayers are not restricted per symbol
+        let info = mock_info("creator", &[]);
+        let msg = ExecuteMsg::AddRelayer { address: String::from("relayer2") };
+        let _res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+        let info = mock_info("relayer2", &[]);
+        let msg = ExecuteMsg::Relay { symbols: vec![String::from("ETH"), String::from("BAND")], rates: vec![10u64, 100u64], resolve_times: vec![20u64, 200u64], request_ids: vec![30u64, 300u64] };
+        let _res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+    }
+
+    #[test]
+    fn two_step_ownership_transfer() {
+        let mut deps = mock_dependencies(&[]);
+
+        let msg = InstantiateMsg::default();
+        let info = mock_info("creator", &[]);
+        let _res = instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+        let info = mock_info("intruder", &[]);
+        let msg = ExecuteMsg::TransferOwnership { new_owner: String::from("intruder") };
+        let err = execute(deps.as_mut(), mock_env(), info, msg).unwrap_err();
+        assert!(matches!(err, ContractError::Unauthorized {}));
+
+        // nothing is pending yet
+        let info = mock_info("alice", &[]);
+        let err = execute(deps.as_mut(), mock_env(), info, ExecuteMsg::AcceptOwnership {}).unwrap_err();
+        assert!(matches!(err, ContractError::Unauthorized {}));
+
+        let info = mock_info("creator", &[]);
+        let msg = ExecuteMsg::TransferOwnership { new_owner: String::from("alice") };
+        let _res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+        // a second transfer overwrites the pending owner
+        let info = mock_info("creator", &[]);
+        let msg = ExecuteMsg::TransferOwnership { new_owner: String::from("bob") };
+        let _res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+        let res = query(deps.as_ref(), mock_env(), QueryMsg::GetOwnership {}).unwrap();
+        let value: OwnershipResponse = from_binary(&res).unwrap();
+        assert_eq!(OwnershipResponse { owner: Some(Addr::unchecked("creator")), pending_owner: Some(Addr::unchecked("bob")) }, value);
+
+        let info = mock_info("alice", &[]);
+        let err = execute(deps.as_mut(), mock_env(), info, ExecuteMsg::AcceptOwnership {}).unwrap_err();
+        assert!(matches!(err, ContractError::Unauthorized {}));
+
+        let info = mock_info("bob", &[]);
+        let _res = execute(deps.as_mut(), mock_env(), info, ExecuteMsg::AcceptOwnership {}).unwrap();
+
+        let res = query(deps.as_ref(), mock_env(), QueryMsg::GetOwnership {}).unwrap();
+        let value: OwnershipResponse = from_binary(&res).unwrap();
+        assert_eq!(OwnershipResponse { owner: Some(Addr::unchecked("bob")), pending_owner: None }, value);
+
+        let info = mock_info("creator", &[]);
+        let msg = ExecuteMsg::Relay { symbols: vec![String::from("ETH")], rates: vec![1u64], resolve_times: vec![2u64], request_ids: vec![3u64] };
+        let err = execute(deps.as_mut(), mock_env(), info, msg).unwrap_err();
+        assert!(matches!(err, ContractError::Unauthorized {}));
+    }
+
+    #[test]
+    fn pause_blocks_relay() {
+        let mut deps = mock_dependencies(&[]);
+
+        let msg = InstantiateMsg::default();
+        let info = mock_info("creator", &[]);
+        let _res = instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+        let info = mock_info("creator", &[]);
+        let msg = ExecuteMsg::Relay { symbols: vec![String::from("ETH")], rates: vec![1u64], resolve_times: vec![2u64], request_ids: vec![3u64] };
+        let _res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+        let info = mock_info("sender", &[]);
+        let err = execute(deps.as_mut(), mock_env(), info, ExecuteMsg::Pause {}).unwrap_err();
+        assert!(matches!(err, ContractError::Unauthorized {}));
+
+        let info = mock_info("creator", &[]);
+        let _res = execute(deps.as_mut(), mock_env(), info, ExecuteMsg::Pause {}).unwrap();
+        let res = query(deps.as_ref(), mock_env(), QueryMsg::IsPaused {}).unwrap();
+        let value: bool = from_binary(&res).unwrap();
+        assert!(value);
+
+        let info = mock_info("creator", &[]);
+        let msg = ExecuteMsg::Relay { symbols: vec![String::from("ETH")], rates: vec![10u64], resolve_times: vec![20u64], request_ids: vec![30u64] };
+        let err = execute(deps.as_mut(), mock_env(), info, msg).unwrap_err();
+        assert!(matches!(err, ContractError::ContractPaused {}));
+
+        let info = mock_info("creator", &[]);
+        let err = execute(deps.as_mut(), mock_env(), info, ExecuteMsg::RemoveSymbol { symbols: vec![String::from("ETH")] }).unwrap_err();
+        assert!(matches!(err, ContractError::ContractPaused {}));
+
+        // last known data is still readable while paused
+        let res = query(deps.as_ref(), mock_env(), QueryMsg::GetAllRefs {}).unwrap();
+        let value: ConfigResponse = from_binary(&res).unwrap();
+        let mut mock_map = BTreeMap::new();
+        mock_map.insert(String::from("ETH"), RefData{rate: 1u64, resolve_time: 2u64, request_id: 3u64, std_dev: None, updated_by: Addr::unchecked("creator"), decimals: 9, negative: false, relay_seq: 1});
+        assert_eq!(mock_map, value.refs);
+
+        let info = mock_info("creator", &[]);
+        let _res = execute(deps.as_mut(), mock_env(), info, ExecuteMsg::Unpause {}).unwrap();
+        let res = query(deps.as_ref(), mock_env(), QueryMsg::IsPaused {}).unwrap();
+        let value: bool = from_binary(&res).unwrap();
+        assert!(!value);
+
+        let info = mock_info("creator", &[]);
+        let msg = ExecuteMsg::Relay { symbols: vec![String::from("ETH")], rates: vec![10u64], resolve_times: vec![20u64], request_ids: vec![30u64] };
+        let _res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+        let res = query(deps.as_ref(), mock_env(), QueryMsg::GetAllRefs {}).unwrap();
+        let value: ConfigResponse = from_binary(&res).unwrap();
+        let mut mock_map = BTreeMap::new();
+        mock_map.insert(String::from("ETH"), RefData{rate: 10u64, resolve_time: 20u64, request_id: 30u64, std_dev: None, updated_by: Addr::unchecked("creator"), decimals: 9, negative: false, relay_seq: 2});
+        assert_eq!(mock_map, value.refs);
+    }
+
+    #[test]
+    fn query_test_valid() {
+        let mut deps = mock_dependencies(&[]);
+
+        let msg = InstantiateMsg::default();
+        let info = mock_info("creator", &[]);
+        let _res = instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+        let info = mock_info("creator", &[]);
+        let msg = ExecuteMsg::Relay { symbols: vec![String::from("MATIC")], rates: vec![112u64], resolve_times: vec![1625108298000000000u64], request_ids: vec![124u64] };
+        let _res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+        let _info = mock_info("querier", &[]);
+        let msg = QueryMsg::GetReferenceData { base: String::from("USD"), quote: String::from("MATIC"), decimals: None };
+        let res = query(deps.as_ref(), mock_env(), msg).unwrap();
+        let value: ReferenceData = from_binary(&res).unwrap();
+
+        assert_eq!(ReferenceData{rate: Uint128::from(8928571428571428571428571u128), last_updated_base: Uint128::from(1571797419879305533u128), last_updated_quote: Uint128::from(1625108298000000000u128)}, value);
+        assert_eq!(r#"{"rate":"8928571428571428571428571","last_updated_base":"1571797419879305533","last_updated_quote":"1625108298000000000"}"#, String::from_utf8(res.to_vec()).unwrap());
+    }
+
+    #[test]
+    fn query_missing_symbol() {
+        let mut deps = mock_dependencies(&[]);
+
+        let msg = InstantiateMsg::default();
+        let info = mock_info("creator", &[]);
+        let _res = instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+        let info = mock_info("creator", &[]);
+        let msg = ExecuteMsg::Relay { symbols: vec![String::from("MATIC")], rates: vec![112u64], resolve_times: vec![1625108298000000000u64], request_ids: vec![124u64] };
+        let _res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+        let err = query_reference_data(deps.as_ref(), mock_env(), String::from("ETH"), String::from("MATIC"), None).unwrap_err();
+        match err {
+            ContractError::SymbolNotFound { symbol } => assert_eq!(String::from("ETH"), symbol),
+            err => panic!("unexpected error: {:?}", err),
+        }
+
+        let err = query_reference_data(deps.as_ref(), mock_env(), String::from("MATIC"), String::from("BAND"), None).unwrap_err();
+        match err {
+            ContractError::SymbolNotFound { symbol } => assert_eq!(String::from("BAND"), symbol),
+            err => panic!("unexpected error: {:?}", err),
+        }
+
+        let msg = QueryMsg::GetReferenceData { base: String::from("ETH"), quote: String::from("MATIC"), decimals: None };
+        let err = query(deps.as_ref(), mock_env(), msg).unwrap_err();
+        assert!(matches!(err, ContractError::SymbolNotFound { symbol } if symbol == "ETH"));
+    }
+
+    #[test]
+    fn query_zero_quote_rate() {
+        let mut deps = mock_dependencies(&[]);
+
+        let msg = InstantiateMsg::default();
+        let info = mock_info("creator", &[]);
+        let _res = instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+        let info = mock_info("creator", &[]);
+        let msg = ExecuteMsg::Relay { symbols: vec![String::from("LUNA")], rates: vec![0u64], resolve_times: vec![1625108298000000000u64], request_ids: vec![124u64] };
+        let _res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+        let err = query_reference_data(deps.as_ref(), mock_env(), String::from("USD"), String::from("LUNA"), None).unwrap_err();
+        assert!(matches!(err, ContractError::DivideByZero {}));
+
+        let msg = QueryMsg::GetReferenceData { base: String::from("USD"), quote: String::from("LUNA"), decimals: None };
+        let err = query(deps.as_ref(), mock_env(), msg).unwrap_err();
+        assert!(matches!(err, ContractError::DivideByZero {}));
+
+        // a zero base rate is still a valid price
+        let value = query_reference_data(deps.as_ref(), mock_env(), String::from("LUNA"), String::from("USD"), None).unwrap();
+        assert_eq!(Uint128::from(0u8), value.rate);
+    }
+
+    #[test]
+    fn query_unresolved_symbol() {
+        let mut deps = mock_dependencies(&[]);
+
+        let msg = InstantiateMsg::default();
+        let info = mock_info("creator", &[]);
+        let _res = instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+        let info = mock_info("creator", &[]);
+        let msg = ExecuteMsg::Relay { symbols: vec![String::from("MATIC")], rates: vec![112u64], resolve_times: vec![0u64], request_ids: vec![124u64] };
+        let _res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+        let err = get_ref_data(deps.as_ref(), mock_env(), String::from("MATIC")).unwrap_err();
+        assert!(matches!(err, ContractError::RefDataNotAvailable {}));
+        let err = query(deps.as_ref(), mock_env(), QueryMsg::GetRef { symbol: String::from("MATIC") }).unwrap_err();
+        assert!(matches!(err, ContractError::RefDataNotAvailable {}));
+        let err = query(deps.as_ref(), mock_env(), QueryMsg::GetRefData { symbol: String::from("MATIC") }).unwrap_err();
+        assert!(matches!(err, ContractError::RefDataNotAvailable {}));
+
+        let err = query_reference_data(deps.as_ref(), mock_env(), String::from("USD"), String::from("MATIC"), None).unwrap_err();
+        assert!(matches!(err, ContractError::RefDataNotAvailable {}));
+        let err = query_reference_data(deps.as_ref(), mock_env(), String::from("MATIC"), String::from("USD"), None).unwrap_err();
+        assert!(matches!(err, ContractError::RefDataNotAvailable {}));
+
+        let msg = QueryMsg::GetReferenceData { base: String::from("USD"), quote: String::from("MATIC"), decimals: None };
+        let err = query(deps.as_ref(), mock_env(), msg).unwrap_err();
+        assert!(matches!(err, ContractError::RefDataNotAvailable {}));
+
+        // the same query succeeds once the symbol is resolved
+        let info = mock_info("creator", &[]);
+        let msg = ExecuteMsg::Relay { symbols: vec![String::from("MATIC")], rates: vec![112u64], resolve_times: vec![100u64], request_ids: vec![125u64] };
+        let _res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+        let res = query(deps.as_ref(), mock_env(), QueryMsg::GetRefData { symbol: String::from("MATIC") }).unwrap();
+        let value: RefDataResponse = from_binary(&res).unwrap();
+        assert_eq!(RefDataResponse { rate: Uint128::from(112u64), last_update: Uint128::from(100u64), request_id: 125, std_dev: None, decimals: 9, negative: false }, value);
+    }
+
+    #[test]
+    fn query_exact_precision() {
+        let mut deps = mock_dependencies(&[]);
+
+        let msg = InstantiateMsg::default();
+        let info = mock_info("creator", &[]);
+        let _res = instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+        let info = mock_info("creator", &[]);
+        let msg = ExecuteMsg::Relay { symbols: vec![String::from("ETH"), String::from("MATIC")], rates: vec![3000000000000u64, 112u64], resolve_times: vec![1625108297000000000u64, 1625108298000000000u64], request_ids: vec![123u64, 124u64] };
+        let _res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+        let value = query_reference_data(deps.as_ref(), mock_env(), String::from("USD"), String::from("MATIC"), None).unwrap();
+        assert_eq!(Uint128::from(8928571428571428571428571u128), value.rate);
+
+        let value = query_reference_data(deps.as_ref(), mock_env(), String::from("ETH"), String::from("MATIC"), None).unwrap();
+        assert_eq!(ReferenceData{rate: Uint128::from(26785714285714285714285714285u128), last_updated_base: Uint128::from(1625108297000000000u128), last_updated_quote: Uint128::from(1625108298000000000u128)}, value);
+
+        let value = query_reference_data(deps.as_ref(), mock_env(), String::from("ETH"), String::from("USD"), None).unwrap();
+        assert_eq!(Uint128::from(3000u128 * E18), value.rate);
+    }
+
+    #[test]
+    fn query_bulk() {
+        let mut deps = mock_dependencies(&[]);
+
+        let msg = InstantiateMsg::default();
+        let info = mock_info("creator", &[]);
+        let _res = instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+        let info = mock_info("creator", &[]);
+        let msg = ExecuteMsg::Relay { symbols: vec![String::from("MATIC"), String::from("BAND")], rates: vec![112u64, 4000000000u64], resolve_times: vec![1625108298000000000u64, 1625108299000000000u64], request_ids: vec![124u64, 125u64] };
+        let _res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+        let msg = QueryMsg::GetReferenceDataBulk { pairs: vec![(String::from("USD"), String::from("MATIC")), (String::from("BAND"), String::from("USD"))] };
+        let res = query(deps.as_ref(), mock_env(), msg).unwrap();
+        let value: Vec<ReferenceData> = from_binary(&res).unwrap();
+        assert_eq!(vec![
+            ReferenceData{rate: Uint128::from(8928571428571428571428571u128), last_updated_base: Uint128::from(1571797419879305533u128), last_updated_quote: Uint128::from(1625108298000000000u128)},
+            ReferenceData{rate: Uint128::from(4u128 * E18), last_updated_base: Uint128::from(1625108299000000000u128), last_updated_quote: Uint128::from(1571797419879305533u128)},
+        ], value);
+
+        let pairs = vec![(String::from("USD"), String::from("MATIC")), (String::from("ETH"), String::from("USD"))];
+        let err = query_reference_data_bulk(deps.as_ref(), mock_env(), pairs).unwrap_err();
+        match err {
+            ContractError::SymbolNotFound { symbol } => assert_eq!(String::from("ETH"), symbol),
+            err => panic!("unexpected error: {:?}", err),
+        }
+    }
+
+    #[test]
+    fn query_refs_pages() {
+        let mut deps = mock_dependencies(&[]);
+
+        let msg = InstantiateMsg::default();
+        let info = mock_info("creator", &[]);
+        let _res = instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+        let info = mock_info("creator", &[]);
+        let symbols = vec![String::from("MATIC"), String::from("BAND"), String::from("ETH"), String::from("ATOM"), String::from("BTC")];
+        let msg = ExecuteMsg::Relay { symbols, rates: vec![1u64, 2u64, 3u64, 4u64, 5u64], resolve_times: vec![10u64, 20u64, 30u64, 40u64, 50u64], request_ids: vec![100u64, 200u64, 300u64, 400u64, 500u64] };
+        let _res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+        let msg = QueryMsg::GetRefsPaginated { start_after: None, limit: Some(2) };
+        let res = query(deps.as_ref(), mock_env(), msg).unwrap();
+        let value: RefsPageResponse = from_binary(&res).unwrap();
+        assert_eq!(vec![String::from("ATOM"), String::from("BAND")], value.symbols);
+        assert_eq!((String::from("ATOM"), RefData{rate: 4u64, resolve_time: 40u64, request_id: 400u64, std_dev: None, updated_by: Addr::unchecked("creator"), decimals: 9, negative: false, relay_seq: 1}), value.refs[0]);
+
+        let msg = QueryMsg::GetRefsPaginated { start_after: Some(String::from("BAND")), limit: Some(2) };
+        let res = query(deps.as_ref(), mock_env(), msg).unwrap();
+        let value: RefsPageResponse = from_binary(&res).unwrap();
+        assert_eq!(vec![String::from("BTC"), String::from("ETH")], value.symbols);
+
+        let msg = QueryMsg::GetRefsPaginated { start_after: Some(String::from("ETH")), limit: Some(2) };
+        let res = query(deps.as_ref(), mock_env(), msg).unwrap();
+        let value: RefsPageResponse = from_binary(&res).unwrap();
+        assert_eq!(vec![String::from("MATIC")], value.symbols);
+        assert_eq!(vec![(String::from("MATIC"), RefData{rate: 1u64, resolve_time: 10u64, request_id: 100u64, std_dev: None, updated_by: Addr::unchecked("creator"), decimals: 9, negative: false, relay_seq: 1})], value.refs);
+
+        let msg = QueryMsg::GetRefsPaginated { start_after: Some(String::from("MATIC")), limit: None };
+        let res = query(deps.as_ref(), mock_env(), msg).unwrap();
+        let value: RefsPageResponse = from_binary(&res).unwrap();
+        assert!(value.symbols.is_empty());
+
+        let msg = QueryMsg::GetRefsPaginated { start_after: None, limit: Some(1000) };
+        let res = query(deps.as_ref(), mock_env(), msg).unwrap();
+        let value: RefsPageResponse = from_binary(&res).unwrap();
+        assert_eq!(5, value.symbols.len());
+    }
+
+    #[test]
+    fn query_single_ref() {
+        let mut deps = mock_dependencies(&[]);
+
+        let msg = InstantiateMsg::default();
+        let info = mock_info("creator", &[]);
+        let _res = instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+        let info = mock_info("creator", &[]);
+        let msg = ExecuteMsg::Relay { symbols: vec![String::from("MATIC")], rates: vec![112u64], resolve_times: vec![1625108298000000000u64], request_ids: vec![124u64] };
+        let _res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+        let res = query(deps.as_ref(), mock_env(), QueryMsg::GetRef { symbol: String::from("MATIC") }).unwrap();
+        let value: RefData = from_binary(&res).unwrap();
+        assert_eq!(RefData{rate: 112u64, resolve_time: 1625108298000000000u64, request_id: 124u64, std_dev: None, updated_by: Addr::unchecked("creator"), decimals: 9, negative: false, relay_seq: 1}, value);
+
+        let res = query(deps.as_ref(), mock_env(), QueryMsg::GetRef { symbol: String::from("USD") }).unwrap();
+        let value: RefData = from_binary(&res).unwrap();
+        assert_eq!(RefData{rate: 1000000000u64, resolve_time: mock_env().block.time.nanos(), request_id: 0u64, std_dev: None, updated_by: mock_env().contract.address, decimals: 9, negative: false, relay_seq: 0}, value);
+
+        let err = query_ref(deps.as_ref(), mock_env(), String::from("ETH")).unwrap_err();
+        match err {
+            ContractError::SymbolNotFound { symbol } => assert_eq!(String::from("ETH"), symbol),
+            err => panic!("unexpected error: {:?}", err),
+        }
+    }
+
+    #[test]
+    fn query_symbol_count() {
+        let mut deps = mock_dependencies(&[]);
+
+        let msg = InstantiateMsg::default();
+        let info = mock_info("creator", &[]);
+        let _res = instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+        let res = query(deps.as_ref(), mock_env(), QueryMsg::GetSymbolCount {}).unwrap();
+        let value: u64 = from_binary(&res).unwrap();
+        assert_eq!(0u64, value);
+
+        let info = mock_info("creator", &[]);
+        let msg = ExecuteMsg::Relay { symbols: vec![String::from("ETH")], rates: vec![1u64], resolve_times: vec![2u64], request_ids: vec![3u64] };
+        let _res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+        let res = query(deps.as_ref(), mock_env(), QueryMsg::GetSymbolCount {}).unwrap();
+        let value: u64 = from_binary(&res).unwrap();
+        assert_eq!(1u64, value);
+
+        let info = mock_info("creator", &[]);
+        let msg = ExecuteMsg::Relay { symbols: vec![String::from("BAND"), String::from("MATIC")], rates: vec![1u64, 100u64], resolve_times: vec![2u64, 200u64], request_ids: vec![3u64, 300u64] };
+        let _res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+        let res = query(deps.as_ref(), mock_env(), QueryMsg::GetSymbolCount {}).unwrap();
+        let value: u64 = from_binary(&res).unwrap();
+        assert_eq!(3u64, value);
+
+        let info = mock_info("creator", &[]);
+        let msg = ExecuteMsg::Relay { symbols: vec![String::from("ETH")], rates: vec![10u64], resolve_times: vec![20u64], request_ids: vec![30u64] };
+        let _res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+        let res = query(deps.as_ref(), mock_env(), QueryMsg::GetSymbolCount {}).unwrap();
+        let value: u64 = from_binary(&res).unwrap();
+        assert_eq!(3u64, value);
+    }
+
+    #[test]
+    fn stale_relay() {
+        let mut deps = mock_dependencies(&[]);
+
+        let msg = InstantiateMsg::default();
+        let info = mock_info("creator", &[]);
+        let _res = instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+        let info = mock_info("creator", &[]);
+        let msg = ExecuteMsg::Relay { symbols: vec![String::from("MATIC")], rates: vec![12u64], resolve_times: vec![200u64], request_ids: vec![69u64] };
+        let _res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+        // older data is skipped silently while the rest of the batch is applied
+        let info = mock_info("creator", &[]);
+        let msg = ExecuteMsg::Relay { symbols: vec![String::from("MATIC"), String::from("ETH")], rates: vec![24u64, 1u64], resolve_times: vec![100u64, 100u64], request_ids: vec![70u64, 71u64] };
+        let _res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+        let res = query(deps.as_ref(), mock_env(), QueryMsg::GetAllRefs {}).unwrap();
+        let value: ConfigResponse = from_binary(&res).unwrap();
+        let mut mock_map = BTreeMap::new();
+        mock_map.insert(String::from("MATIC"), RefData{rate: 12u64, resolve_time: 200u64, request_id: 69u64, std_dev: None, updated_by: Addr::unchecked("creator"), decimals: 9, negative: false, relay_seq: 1});
+        mock_map.insert(String::from("ETH"), RefData{rate: 1u64, resolve_time: 100u64, request_id: 71u64, std_dev: None, updated_by: Addr::unchecked("creator"), decimals: 9, negative: false, relay_seq: 2});
+        assert_eq!(mock_map, value.refs);
+
+        // strict relays reject the whole batch instead
+        let info = mock_info("creator", &[]);
+        let msg = ExecuteMsg::RelayStrict { symbols: vec![String::from("ETH"), String::from("MATIC")], rates: vec![2u64, 24u64], resolve_times: vec![300u64, 100u64], request_ids: vec![72u64, 70u64] };
+        let err = execute(deps.as_mut(), mock_env(), info, msg).unwrap_err();
+        match err {
+            ContractError::StaleData { symbol } => assert_eq!(String::from("MATIC"), symbol),
+            err => panic!("unexpected error: {:?}", err),
+        }
+
+        let res = query(deps.as_ref(), mock_env(), QueryMsg::GetAllRefs {}).unwrap();
+        let value: ConfigResponse = from_binary(&res).unwrap();
+        assert_eq!(mock_map, value.refs);
+
+        let info = mock_info("creator", &[]);
+        let msg = ExecuteMsg::RelayStrict { symbols: vec![String::from("MATIC")], rates: vec![24u64], resolve_times: vec![200u64], request_ids: vec![70u64] };
+        let _res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+        let res = query(deps.as_ref(), mock_env(), QueryMsg::GetRef { symbol: String::from("MATIC") }).unwrap();
+        let value: RefData = from_binary(&res).unwrap();
+        assert_eq!(RefData{rate: 24u64, resolve_time: 200u64, request_id: 70u64, std_dev: None, updated_by: Addr::unchecked("creator"), decimals: 9, negative: false, relay_seq: 3}, value);
+    }
+
+    #[test]
+    fn query_max_staleness() {
+        let mut deps = mock_dependencies(&[]);
+
+        let msg = InstantiateMsg::default();
+        let info = mock_info("creator", &[]);
+        let _res = instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+        let resolve_time = mock_env().block.time.nanos();
+        let info = mock_info("creator", &[]);
+        let msg = ExecuteMsg::Relay { symbols: vec![String::from("MATIC")], rates: vec![112u64], resolve_times: vec![resolve_time], request_ids: vec![124u64] };
+        let _res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+        let info = mock_info("sender", &[]);
+        let msg = ExecuteMsg::UpdateConfig { owner: None, max_staleness_secs: Some(Some(60u64)), max_future_skew_secs: None, max_rate_change_bps: None, base_decimals: None, usd_decimals: None, max_batch_size: None, min_relay_interval_secs: None, allow_signed_rates: None, rounding: None, strict_request_id: None, usd_timestamp_mode: None, twap_samples: None };
+        let err = execute(deps.as_mut(), mock_env(), info, msg).unwrap_err();
+        assert!(matches!(err, ContractError::Unauthorized {}));
+
+        let info = mock_info("creator", &[]);
+        let msg = ExecuteMsg::UpdateConfig { owner: None, max_staleness_secs: Some(Some(60u64)), max_future_skew_secs: None, max_rate_change_bps: None, base_decimals: None, usd_decimals: None, max_batch_size: None, min_relay_interval_secs: None, allow_signed_rates: None, rounding: None, strict_request_id: None, usd_timestamp_mode: None, twap_samples: None };
+        let _res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+        let mut env = mock_env();
+        env.block.time = env.block.time.plus_seconds(60);
+        let _value = query_reference_data(deps.as_ref(), env, String::from("USD"), String::from("MATIC"), None).unwrap();
+
+        let mut env = mock_env();
+        env.block.time = env.block.time.plus_seconds(61);
+        let err = query_reference_data(deps.as_ref(), env.clone(), String::from("USD"), String::from("MATIC"), None).unwrap_err();
+        match err {
+            ContractError::RefDataTooOld { symbol, age } => {
+                assert_eq!(String::from("MATIC"), symbol);
+                assert_eq!(61u64, age);
+            }
+            err => panic!("unexpected error: {:?}", err),
+        }
+
+        // the synthetic USD leg never goes stale
+        let _value = query_ref(deps.as_ref(), env.clone(), String::from("USD")).unwrap();
+
+        let info = mock_info("creator", &[]);
+        let msg = ExecuteMsg::UpdateConfig { owner: None, max_staleness_secs: Some(None), max_future_skew_secs: None, max_rate_change_bps: None, base_decimals: None, usd_decimals: None, max_batch_size: None, min_relay_interval_secs: None, allow_signed_rates: None, rounding: None, strict_request_id: None, usd_timestamp_mode: None, twap_samples: None };
+        let _res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+        let _value = query_reference_data(deps.as_ref(), env, String::from("USD"), String::from("MATIC"), None).unwrap();
+    }
+
+    #[test]
+    fn remove_symbol() {
+        let mut deps = mock_dependencies(&[]);
+
+        let msg = InstantiateMsg::default();
+        let info = mock_info("creator", &[]);
+        let _res = instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+        let info = mock_info("creator", &[]);
+        let msg = ExecuteMsg::Relay { symbols: vec![String::from("ETH"), String::from("BAND")], rates: vec![1u64, 100u64], resolve_times: vec![2u64, 200u64], request_ids: vec![3u64, 300u64] };
+        let _res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+        let info = mock_info("sender", &[]);
+        let msg = ExecuteMsg::RemoveSymbol { symbols: vec![String::from("ETH")] };
+        let err = execute(deps.as_mut(), mock_env(), info, msg).unwrap_err();
+        assert!(matches!(err, ContractError::Unauthorized {}));
+
+        let info = mock_info("sender", &[]);
+        let err = execute(deps.as_mut(), mock_env(), info, ExecuteMsg::RemoveSymbol { symbols: vec![] }).unwrap_err();
+        assert!(matches!(err, ContractError::EmptyRelay {}));
+
+        // missing symbols are ignored
+        let info = mock_info("creator", &[]);
+        let msg = ExecuteMsg::RemoveSymbol { symbols: vec![String::from("ETH"), String::from("MATIC")] };
+        let res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+        assert_eq!(vec![attr("action", "remove_symbol"), attr("num_removed", "1")], res.attributes);
+
+        let res = query(deps.as_ref(), mock_env(), QueryMsg::GetAllRefs {}).unwrap();
+        let value: ConfigResponse = from_binary(&res).unwrap();
+        let mut mock_map = BTreeMap::new();
+        mock_map.insert(String::from("BAND"), RefData{rate: 100u64, resolve_time: 200u64, request_id: 300u64, std_dev: None, updated_by: Addr::unchecked("creator"), decimals: 9, negative: false, relay_seq: 1});
+        assert_eq!(mock_map, value.refs);
+
+        let res = query(deps.as_ref(), mock_env(), QueryMsg::GetSymbolCount {}).unwrap();
+        let value: u64 = from_binary(&res).unwrap();
+        assert_eq!(1u64, value);
+    }
+
+    #[test]
+    fn relay_attributes() {
+        let mut deps = mock_dependencies(&[]);
+
+        let msg = InstantiateMsg::default();
+        let info = mock_info("creator", &[]);
+        let _res = instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+        let info = mock_info("creator", &[]);
+        let msg = ExecuteMsg::Relay { symbols: vec![String::from("ETH"), String::from("BAND")], rates: vec![1u64, 100u64], resolve_times: vec![2u64, 200u64], request_ids: vec![3u64, 300u64] };
+        let res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+        assert_eq!(vec![
+            attr("action", "relay"),
+            attr("num_symbols", "2"),
+            attr("num_updated", "2"),
+            attr("symbol:ETH", "1@2"),
+            attr("symbol:BAND", "100@200"),
+        ], res.attributes);
+
+        // a failed batch returns no response at all and writes nothing, not even the valid BAND entry
+        let before = query(deps.as_ref(), mock_env(), QueryMsg::GetAllRefs {}).unwrap();
+        let info = mock_info("creator", &[]);
+        let msg = ExecuteMsg::RelayStrict { symbols: vec![String::from("BAND"), String::from("ETH")], rates: vec![100u64, 1u64], resolve_times: vec![300u64, 1u64], request_ids: vec![3u64, 300u64] };
+        let err = execute(deps.as_mut(), mock_env(), info, msg).unwrap_err();
+        assert!(matches!(err, ContractError::StaleData { symbol } if symbol == "ETH"));
+        assert_eq!(before, query(deps.as_ref(), mock_env(), QueryMsg::GetAllRefs {}).unwrap());
+        let band = REFS.load(&deps.storage, "BAND").unwrap();
+        assert_eq!((100u64, 200u64, 300u64), (band.rate, band.resolve_time, band.request_id));
+    }
+
+    #[test]
+    fn migrate_version() {
+        let mut deps = mock_dependencies(&[]);
+
+        let msg = InstantiateMsg::default();
+        let info = mock_info("creator", &[]);
+        let _res = instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+        let res = query(deps.as_ref(), mock_env(), QueryMsg::GetContractVersion {}).unwrap();
+        let value: ContractVersion = from_binary(&res).unwrap();
+        assert_eq!(ContractVersion { contract: String::from(CONTRACT_NAME), version: String::from(CONTRACT_VERSION) }, value);
+
+        legacy_config(deps.as_mut().storage).save(&legacy_state(HashMap::new())).unwrap();
+        set_contract_version(deps.as_mut().storage, CONTRACT_NAME, "0.2.0").unwrap();
+        let _res = migrate(deps.as_mut(), mock_env(), MigrateMsg::default()).unwrap();
+        let value = get_contract_version(deps.as_ref().storage).unwrap();
+        assert_eq!(String::from(CONTRACT_VERSION), value.version);
+
+        // refuse downgrades
+        set_contract_version(deps.as_mut().storage, CONTRACT_NAME, "99.0.0").unwrap();
+        let err = migrate(deps.as_mut(), mock_env(), MigrateMsg::default()).unwrap_err();
+        assert!(matches!(err, ContractError::CannotMigrate {}));
+
+        // refuse other contracts
+        set_contract_version(deps.as_mut().storage, "crates.io:cw20-base", "0.1.0").unwrap();
+        let err = migrate(deps.as_mut(), mock_env(), MigrateMsg::default()).unwrap_err();
+        assert!(matches!(err, ContractError::CannotMigrate {}));
+    }
+
+    #[test]
+    fn configurable_decimals() {
+        let mut deps = mock_dependencies(&[]);
+
+        let msg = InstantiateMsg { base_decimals: Some(39), ..InstantiateMsg::default() };
+        let info = mock_info("creator", &[]);
+        let err = instantiate(deps.as_mut(), mock_env(), info, msg).unwrap_err();
+        assert!(matches!(err, ContractError::InvalidDecimals {}));
+
+        let msg = InstantiateMsg { base_decimals: Some(6), ..InstantiateMsg::default() };
+        let info = mock_info("creator", &[]);
+        let _res = instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+        let info = mock_info("creator", &[]);
+        let msg = ExecuteMsg::Relay { symbols: vec![String::from("MATIC")], rates: vec![112u64], resolve_times: vec![1625108298000000000u64], request_ids: vec![124u64] };
+        let _res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+        let value = query_reference_data(deps.as_ref(), mock_env(), String::from("USD"), String::from("MATIC"), None).unwrap();
+        assert_eq!(Uint128::from(8928571428571u128), value.rate);
+
+        let info = mock_info("creator", &[]);
+        let msg = ExecuteMsg::UpdateConfig { owner: None, max_staleness_secs: None, max_future_skew_secs: None, max_rate_change_bps: None, base_decimals: Some(18), usd_decimals: Some(6), max_batch_size: None, min_relay_interval_secs: None, allow_signed_rates: None, rounding: None, strict_request_id: None, usd_timestamp_mode: None, twap_samples: None };
+        let _res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+        // MATIC keeps the 9 decimals it was relayed with, so only the USD leg is rescaled
+        let value = query_reference_data(deps.as_ref(), mock_env(), String::from("USD"), String::from("MATIC"), None).unwrap();
+        assert_eq!(Uint128::from(8928571428571428571428571u128), value.rate);
+        let value = query_ref(deps.as_ref(), mock_env(), String::from("USD")).unwrap();
+        assert_eq!(1000000u64, value.rate);
+
+        let info = mock_info("creator", &[]);
+        let msg = ExecuteMsg::UpdateConfig { owner: None, max_staleness_secs: None, max_future_skew_secs: None, max_rate_change_bps: None, base_decimals: None, usd_decimals: Some(20), max_batch_size: None, min_relay_interval_secs: None, allow_signed_rates: None, rounding: None, strict_request_id: None, usd_timestamp_mode: None, twap_samples: None };
+        let err = execute(deps.as_mut(), mock_env(), info, msg).unwrap_err();
+        assert!(matches!(err, ContractError::InvalidDecimals {}));
+    }
+
+    #[test]
+    fn query_full_request_ids() {
+        let mut deps = mock_dependencies(&[]);
+
+        let msg = InstantiateMsg::default();
+        let info = mock_info("creator", &[]);
+        let _res = instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+        let info = mock_info("creator", &[]);
+        let msg = ExecuteMsg::Relay { symbols: vec![String::from("ETH"), String::from("MATIC")], rates: vec![3000000000000u64, 112u64], resolve_times: vec![1625108297000000000u64, 1625108298000000000u64], request_ids: vec![123u64, 124u64] };
+        let _res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+        let msg = QueryMsg::GetReferenceDataFull { base: String::from("ETH"), quote: String::from("MATIC") };
+        let res = query(deps.as_ref(), mock_env(), msg).unwrap();
+        let value: ReferenceDataFull = from_binary(&res).unwrap();
+        assert_eq!(ReferenceDataFull{rate: Uint128::from(26785714285714285714285714285u128), last_updated_base: Uint128::from(1625108297000000000u128), last_updated_quote: Uint128::from(1625108298000000000u128), base_request_id: 123u64, quote_request_id: 124u64, base_std_dev: None, quote_std_dev: None}, value);
+
+        let value = query_reference_data_full(deps.as_ref(), mock_env(), String::from("USD"), String::from("ETH"), None).unwrap();
+        assert_eq!(0u64, value.base_request_id);
+        assert_eq!(123u64, value.quote_request_id);
+    }
+
+    #[test]
+    fn relay_invalid_arrays() {
+        let mut deps = mock_dependencies(&[]);
+
+        let msg = InstantiateMsg::default();
+        let info = mock_info("creator", &[]);
+        let _res = instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+        let info = mock_info("creator", &[]);
+        let msg = ExecuteMsg::Relay { symbols: vec![String::from("ETH"), String::from("BAND")], rates: vec![1u64], resolve_times: vec![2u64, 200u64], request_ids: vec![3u64, 300u64, 3000u64] };
+        let err = execute(deps.as_mut(), mock_env(), info, msg).unwrap_err();
+        match err {
+            ContractError::DifferentArrayLength { symbols_len, rates_len, resolve_times_len, request_ids_len, .. } => {
+                assert_eq!((2, 1, 2, 3), (symbols_len, rates_len, resolve_times_len, request_ids_len));
+            }
+            err => panic!("unexpected error: {:?}", err),
+        }
+
+        let info = mock_info("creator", &[]);
+        let msg = ExecuteMsg::Relay { symbols: vec![], rates: vec![], resolve_times: vec![], request_ids: vec![] };
+        let err = execute(deps.as_mut(), mock_env(), info, msg).unwrap_err();
+        assert!(matches!(err, ContractError::EmptyRelay {}));
+    }
+
+    #[test]
+    fn relay_duplicate_symbol() {
+        let mut deps = mock_dependencies(&[]);
+
+        let msg = InstantiateMsg::default();
+        let info = mock_info("creator", &[]);
+        let _res = instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+        let info = mock_info("creator", &[]);
+        let msg = ExecuteMsg::Relay { symbols: vec![String::from("ETH"), String::from("ETH")], rates: vec![1u64, 100u64], resolve_times: vec![2u64, 200u64], request_ids: vec![3u64, 300u64] };
+        let err = execute(deps.as_mut(), mock_env(), info, msg).unwrap_err();
+        match err {
+            ContractError::DuplicateSymbol { symbol } => assert_eq!(String::from("ETH"), symbol),
+            err => panic!("unexpected error: {:?}", err),
+        }
+
+        let res = query(deps.as_ref(), mock_env(), QueryMsg::GetAllRefs {}).unwrap();
+        let value: ConfigResponse = from_binary(&res).unwrap();
+        assert_eq!(BTreeMap::new(), value.refs);
+
+        let info = mock_info("creator", &[]);
+        let msg = ExecuteMsg::Relay { symbols: vec![String::from("ETH"), String::from("BAND")], rates: vec![1u64, 100u64], resolve_times: vec![2u64, 200u64], request_ids: vec![3u64, 300u64] };
+        let _res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+        let res = query(deps.as_ref(), mock_env(), QueryMsg::GetSymbolCount {}).unwrap();
+        let value: u64 = from_binary(&res).unwrap();
+        assert_eq!(2u64, value);
+    }
+
+    #[test]
+    fn relay_touches_only_written_keys() {
+        let mut deps = mock_dependencies(&[]);
+
+        let msg = InstantiateMsg::default();
+        let info = mock_info("creator", &[]);
+        let _res = instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+        let symbols: Vec<String> = (0..50).map(|i| format!("SYM{}", i)).collect();
+        let info = mock_info("creator", &[]);
+        let msg = ExecuteMsg::Relay { symbols: symbols.clone(), rates: vec![1u64; 50], resolve_times: vec![2u64; 50], request_ids: vec![3u64; 50] };
+        let _res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+        let config_before = deps.storage.get(&to_length_prefixed(CONFIG_KEY)).unwrap();
+        let others_before: Vec<Vec<u8>> = symbols[1..].iter().map(|symbol| deps.storage.get(&REFS.key(symbol)).unwrap()).collect();
+
+        let info = mock_info("creator", &[]);
+        let msg = ExecuteMsg::Relay { symbols: vec![symbols[0].clone()], rates: vec![10u64], resolve_times: vec![20u64], request_ids: vec![30u64] };
+        let _res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+        // the config blob no longer holds refs, so its size does not grow with the symbol set
+        assert_eq!(config_before.len(), deps.storage.get(&to_length_prefixed(CONFIG_KEY)).unwrap().len());
+        let others_after: Vec<Vec<u8>> = symbols[1..].iter().map(|symbol| deps.storage.get(&REFS.key(symbol)).unwrap()).collect();
+        assert_eq!(others_before, others_after);
+        assert_eq!(RefData{rate: 10u64, resolve_time: 20u64, request_id: 30u64, std_dev: None, updated_by: Addr::unchecked("creator"), decimals: 9, negative: false, relay_seq: 2}, REFS.load(&deps.storage, &symbols[0]).unwrap());
+    }
+
+    #[test]
+    fn migrate_refs_blob_to_map() {
+        let mut deps = mock_dependencies(&[]);
+
+        let mut refs = HashMap::new();
+        refs.insert(String::from("ETH"), RefData{rate: 1u64, resolve_time: 2u64, request_id: 3u64, std_dev: None, updated_by: Addr::unchecked("creator"), decimals: 9, negative: false, relay_seq: 0});
+        refs.insert(String::from("BAND"), RefData{rate: 100u64, resolve_time: 200u64, request_id: 300u64, std_dev: None, updated_by: Addr::unchecked("creator"), decimals: 9, negative: false, relay_seq: 0});
+        legacy_config(deps.as_mut().storage).save(&legacy_state(refs.clone())).unwrap();
+        set_contract_version(deps.as_mut().storage, CONTRACT_NAME, "0.2.0").unwrap();
+
+        let _res = migrate(deps.as_mut(), mock_env(), MigrateMsg::default()).unwrap();
+
+        let res = query(deps.as_ref(), mock_env(), QueryMsg::GetAllRefs {}).unwrap();
+        let value: ConfigResponse = from_binary(&res).unwrap();
+        assert_eq!(refs.into_iter().collect::<BTreeMap<_, _>>(), value.refs);
+
+        let res = query(deps.as_ref(), mock_env(), QueryMsg::GetSymbolCount {}).unwrap();
+        let value: u64 = from_binary(&res).unwrap();
+        assert_eq!(2u64, value);
+
+        let res = query(deps.as_ref(), mock_env(), QueryMsg::GetRelayers {}).unwrap();
+        let value: RelayersResponse = from_binary(&res).unwrap();
+        assert_eq!(vec![Addr::unchecked("relayer1")], value.relayers);
+
+        let value = get_contract_version(deps.as_ref().storage).unwrap();
+        assert_eq!(String::from(CONTRACT_VERSION), value.version);
+    }
+
+    #[test]
+    fn relay_invalid_symbol() {
+        let mut deps = mock_dependencies(&[]);
+
+        let msg = InstantiateMsg { symbol_separator: Some(String::from("A")), ..InstantiateMsg::default() };
+        let info = mock_info("creator", &[]);
+        let err = instantiate(deps.as_mut(), mock_env(), info, msg).unwrap_err();
+        assert!(matches!(err, ContractError::InvalidSeparator {}));
+
+        let msg = InstantiateMsg { symbol_separator: Some(String::from("_")), ..InstantiateMsg::default() };
+        let info = mock_info("creator", &[]);
+        let _res = instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+        let invalid = vec![String::from(""), "A".repeat(33), String::from("eth"), String::from("USD"), String::from("ETH-USD")];
+        for symbol in invalid {
+            let info = mock_info("creator", &[]);
+            let msg = ExecuteMsg::Relay { symbols: vec![String::from("BAND"), symbol.clone()], rates: vec![1u64, 1u64], resolve_times: vec![2u64, 2u64], request_ids: vec![3u64, 3u64] };
+            let err = execute(deps.as_mut(), mock_env(), info, msg).unwrap_err();
+            match err {
+                ContractError::InvalidSymbol { symbol: invalid_symbol } => assert_eq!(symbol, invalid_symbol),
+                err => panic!("unexpected error: {:?}", err),
+            }
+        }
+
+        let res = query(deps.as_ref(), mock_env(), QueryMsg::GetSymbolCount {}).unwrap();
+        let value: u64 = from_binary(&res).unwrap();
+        assert_eq!(0u64, value);
+
+        let info = mock_info("creator", &[]);
+        let msg = ExecuteMsg::Relay { symbols: vec![String::from("ETH_2X"), "A".repeat(32)], rates: vec![1u64, 1u64], resolve_times: vec![2u64, 2u64], request_ids: vec![3u64, 3u64] };
+        let _res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+    }
+
+    #[test]
+    fn query_missing_leg() {
+        let mut deps = mock_dependencies(&[]);
+
+        let msg = InstantiateMsg::default();
+        let info = mock_info("creator", &[]);
+        let _res = instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+        let info = mock_info("creator", &[]);
+        let msg = ExecuteMsg::Relay { symbols: vec![String::from("ETH")], rates: vec![3000000000000u64], resolve_times: vec![1625108297000000000u64], request_ids: vec![123u64] };
+        let _res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+        // only the base exists
+        let msg = QueryMsg::GetReferenceData { base: String::from("ETH"), quote: String::from("BAND"), decimals: None };
+        let err = query(deps.as_ref(), mock_env(), msg).unwrap_err();
+        assert!(matches!(err, ContractError::SymbolNotFound { symbol } if symbol == "BAND"));
+
+        let info = mock_info("creator", &[]);
+        let msg = ExecuteMsg::RemoveSymbol { symbols: vec![String::from("ETH")] };
+        let _res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+        let info = mock_info("creator", &[]);
+        let msg = ExecuteMsg::Relay { symbols: vec![String::from("BAND")], rates: vec![4000000000u64], resolve_times: vec![1625108299000000000u64], request_ids: vec![125u64] };
+        let _res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+        // only the quote exists
+        let msg = QueryMsg::GetReferenceData { base: String::from("ETH"), quote: String::from("BAND"), decimals: None };
+        let err = query(deps.as_ref(), mock_env(), msg).unwrap_err();
+        assert!(matches!(err, ContractError::SymbolNotFound { symbol } if symbol == "ETH"));
+    }
+
+    #[test]
+    fn relay_with_std_dev() {
+        let mut deps = mock_dependencies(&[]);
+
+        let msg = InstantiateMsg::default();
+        let info = mock_info("creator", &[]);
+        let _res = instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+        let info = mock_info("creator", &[]);
+        let msg = ExecuteMsg::RelayWithStdDev { symbols: vec![String::from("ETH")], rates: vec![3000000000000u64], resolve_times: vec![1625108297000000000u64], request_ids: vec![123u64], std_devs: vec![15000000u64] };
+        let _res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+        let info = mock_info("creator", &[]);
+        let msg = ExecuteMsg::Relay { symbols: vec![String::from("MATIC")], rates: vec![112u64], resolve_times: vec![1625108298000000000u64], request_ids: vec![124u64] };
+        let _res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+        let res = query(deps.as_ref(), mock_env(), QueryMsg::GetRef { symbol: String::from("ETH") }).unwrap();
+        let value: RefData = from_binary(&res).unwrap();
+        assert_eq!(RefData{rate: 3000000000000u64, resolve_time: 1625108297000000000u64, request_id: 123u64, std_dev: Some(15000000u64), updated_by: Addr::unchecked("creator"), decimals: 9, negative: false, relay_seq: 1}, value);
+        let res = query(deps.as_ref(), mock_env(), QueryMsg::GetRef { symbol: String::from("MATIC") }).unwrap();
+        let value: RefData = from_binary(&res).unwrap();
+        assert_eq!(None, value.std_dev);
+
+        let msg = QueryMsg::GetReferenceDataFull { base: String::from("ETH"), quote: String::from("MATIC") };
+        let res = query(deps.as_ref(), mock_env(), msg).unwrap();
+        let value: ReferenceDataFull = from_binary(&res).unwrap();
+        assert_eq!(Some(15000000u64), value.base_std_dev);
+        assert_eq!(None, value.quote_std_dev);
+
+        let info = mock_info("creator", &[]);
+        let msg = ExecuteMsg::RelayWithStdDev { symbols: vec![String::from("ETH")], rates: vec![3000000000000u64], resolve_times: vec![1625108297000000000u64], request_ids: vec![123u64], std_devs: vec![] };
+        let err = execute(deps.as_mut(), mock_env(), info, msg).unwrap_err();
+        assert!(matches!(err, ContractError::DifferentArrayLength { std_devs_len: Some(0), .. }));
+    }
+
+    #[test]
+    fn migrate_refs_without_std_dev() {
+        let mut deps = mock_dependencies(&[]);
+
+        let msg = InstantiateMsg::default();
+        let info = mock_info("creator", &[]);
+        let _res = instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+        // a 0.3.0 entry has no std_dev field at all
+        deps.storage.set(&REFS.key("ETH"), br#"{"rate":1,"resolve_time":2,"request_id":3}"#);
+        set_contract_version(deps.as_mut().storage, CONTRACT_NAME, "0.3.0").unwrap();
+
+        let _res = migrate(deps.as_mut(), mock_env(), MigrateMsg::default()).unwrap();
+
+        assert_eq!(br#"{"rate":1,"resolve_time":2,"request_id":3,"std_dev":null,"updated_by":"creator","decimals":9,"negative":false,"relay_seq":0}"#.to_vec(), deps.storage.get(&REFS.key("ETH")).unwrap());
+        let res = query(deps.as_ref(), mock_env(), QueryMsg::GetRef { symbol: String::from("ETH") }).unwrap();
+        let value: RefData = from_binary(&res).unwrap();
+        assert_eq!(RefData{rate: 1u64, resolve_time: 2u64, request_id: 3u64, std_dev: None, updated_by: Addr::unchecked("creator"), decimals: 9, negative: false, relay_seq: 0}, value);
+    }
+
+    #[test]
+    fn force_relay_overwrites_newer_data() {
+        let mut deps = mock_dependencies(&[]);
+
+        let msg = InstantiateMsg::default();
+        let info = mock_info("creator", &[]);
+        let _res = instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+        let info = mock_info("creator", &[]);
+        let msg = ExecuteMsg::AddRelayer { address: String::from("relayer") };
+        let _res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+        let info = mock_info("relayer", &[]);
+        let msg = ExecuteMsg::Relay { symbols: vec![String::from("ETH")], rates: vec![3000000000000u64], resolve_times: vec![1625108297000000000u64], request_ids: vec![124u64] };
+        let _res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+        // relayers cannot force
+        let info = mock_info("relayer", &[]);
+        let msg = ExecuteMsg::ForceRelay { symbols: vec![String::from("ETH")], rates: vec![2900000000000u64], resolve_times: vec![1625108296000000000u64], request_ids: vec![123u64] };
+        let err = execute(deps.as_mut(), mock_env(), info, msg).unwrap_err();
+        assert!(matches!(err, ContractError::Unauthorized {}));
+
+        let info = mock_info("creator", &[]);
+        let msg = ExecuteMsg::ForceRelay { symbols: vec![String::from("ETH")], rates: vec![2900000000000u64], resolve_times: vec![1625108296000000000u64], request_ids: vec![123u64] };
+        let _res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+        let res = query(deps.as_ref(), mock_env(), QueryMsg::GetRef { symbol: String::from("ETH") }).unwrap();
+        let value: RefData = from_binary(&res).unwrap();
+        assert_eq!(RefData{rate: 2900000000000u64, resolve_time: 1625108296000000000u64, request_id: 123u64, std_dev: None, updated_by: Addr::unchecked("creator"), decimals: 9, negative: false, relay_seq: 2}, value);
+    }
+
+    #[test]
+    fn query_refs_sorted() {
+        let mut deps = mock_dependencies(&[]);
+
+        let msg = InstantiateMsg::default();
+        let info = mock_info("creator", &[]);
+        let _res = instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+        let info = mock_info("creator", &[]);
+        let msg = ExecuteMsg::Relay { symbols: vec![String::from("MATIC"), String::from("BAND"), String::from("ETH"), String::from("ATOM")], rates: vec![1u64, 2u64, 3u64, 4u64], resolve_times: vec![100u64, 100u64, 100u64, 100u64], request_ids: vec![1u64, 1u64, 1u64, 1u64] };
+        let _res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+        let res = query(deps.as_ref(), mock_env(), QueryMsg::GetAllRefs {}).unwrap();
+        let value: ConfigResponse = from_binary(&res).unwrap();
+        let symbols: Vec<String> = value.refs.keys().cloned().collect();
+        assert_eq!(vec!["ATOM", "BAND", "ETH", "MATIC"], symbols);
+        // the serialized pairs are in the same order, so the raw output is stable
+        let json = String::from_utf8(res.to_vec()).unwrap();
+        assert!(json.starts_with(r#"{"refs":[["ATOM",{"rate":4,"#));
+    }
+
+    #[test]
+    fn instantiate_with_seed() {
+        let mut deps = mock_dependencies(&[]);
+
+        let msg = InstantiateMsg {
+            initial_symbols: Some(vec![String::from("ETH"), String::from("BAND")]),
+            initial_rates: Some(vec![3000000000000u64, 7000000000u64]),
+            initial_resolve_times: Some(vec![1625108297000000000u64, 1625108298000000000u64]),
+            initial_request_ids: Some(vec![123u64, 124u64]),
+            ..InstantiateMsg::default()
+        };
+        let info = mock_info("creator", &[]);
+        let _res = instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+        let res = query(deps.as_ref(), mock_env(), QueryMsg::GetRef { symbol: String::from("ETH") }).unwrap();
+        let value: RefData = from_binary(&res).unwrap();
+        assert_eq!(RefData{rate: 3000000000000u64, resolve_time: 1625108297000000000u64, request_id: 123u64, std_dev: None, updated_by: Addr::unchecked("creator"), decimals: 9, negative: false, relay_seq: 1}, value);
+        let res = query(deps.as_ref(), mock_env(), QueryMsg::GetRef { symbol: String::from("BAND") }).unwrap();
+        let value: RefData = from_binary(&res).unwrap();
+        assert_eq!(RefData{rate: 7000000000u64, resolve_time: 1625108298000000000u64, request_id: 124u64, std_dev: None, updated_by: Addr::unchecked("creator"), decimals: 9, negative: false, relay_seq: 1}, value);
+        let res = query(deps.as_ref(), mock_env(), QueryMsg::GetSymbolCount {}).unwrap();
+        let value: u64 = from_binary(&res).unwrap();
+        assert_eq!(2u64, value);
+
+        let mut deps = mock_dependencies(&[]);
+        let msg = InstantiateMsg {
+            initial_symbols: Some(vec![String::from("ETH"), String::from("BAND")]),
+            initial_rates: Some(vec![3000000000000u64]),
+            ..InstantiateMsg::default()
+        };
+        let info = mock_info("creator", &[]);
+        let err = instantiate(deps.as_mut(), mock_env(), info, msg).unwrap_err();
+        assert!(matches!(err, ContractError::DifferentArrayLength { symbols_len: 2, rates_len: 1, .. }));
+    }
+
+    #[test]
+    fn query_raw_reference_data() {
+        let mut deps = mock_dependencies(&[]);
+
+        let msg = InstantiateMsg::default();
+        let info = mock_info("creator", &[]);
+        let _res = instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+        let info = mock_info("creator", &[]);
+        let msg = ExecuteMsg::Relay { symbols: vec![String::from("ETH"), String::from("MATIC")], rates: vec![3000000000000u64, 112u64], resolve_times: vec![1625108297000000000u64, 1625108298000000000u64], request_ids: vec![123u64, 124u64] };
+        let _res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+        let msg = QueryMsg::GetRawReferenceData { base: String::from("ETH"), quote: String::from("MATIC") };
+        let res = query(deps.as_ref(), mock_env(), msg).unwrap();
+        let raw: RawReferenceData = from_binary(&res).unwrap();
+        assert_eq!(RawReferenceData{base_rate: 3000000000000u64, quote_rate: 112u64, last_updated_base: 1625108297000000000u64, last_updated_quote: 1625108298000000000u64, base_request_id: 123u64, quote_request_id: 124u64}, raw);
+
+        let msg = QueryMsg::GetReferenceData { base: String::from("ETH"), quote: String::from("MATIC"), decimals: None };
+        let res = query(deps.as_ref(), mock_env(), msg).unwrap();
+        let scaled: ReferenceData = from_binary(&res).unwrap();
+        assert_eq!(Uint128::from(raw.base_rate as u128 * E18 / raw.quote_rate as u128), scaled.rate);
+        assert_eq!(Uint128::from(raw.last_updated_base), scaled.last_updated_base);
+        assert_eq!(Uint128::from(raw.last_updated_quote), scaled.last_updated_quote);
+
+        let msg = QueryMsg::GetRawReferenceData { base: String::from("USD"), quote: String::from("MATIC") };
+        let res = query(deps.as_ref(), mock_env(), msg).unwrap();
+        let raw: RawReferenceData = from_binary(&res).unwrap();
+        assert_eq!(E9 as u64, raw.base_rate);
+        assert_eq!(0u64, raw.base_request_id);
+    }
+
+    #[test]
+    fn query_usd_usd() {
+        let mut deps = mock_dependencies(&[]);
+
+        let msg = InstantiateMsg::default();
+        let info = mock_info("creator", &[]);
+        let _res = instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+        let msg = QueryMsg::GetReferenceData { base: String::from("USD"), quote: String::from("USD"), decimals: None };
+        let res = query(deps.as_ref(), mock_env(), msg).unwrap();
+        let value: ReferenceData = from_binary(&res).unwrap();
+        let now = Uint128::from(mock_env().block.time.nanos());
+        assert_eq!(ReferenceData{rate: Uint128::from(E18), last_updated_base: now, last_updated_quote: now}, value);
+    }
+
+    #[test]
+    fn query_max_rate_does_not_overflow() {
+        let mut deps = mock_dependencies(&[]);
+
+        let msg = InstantiateMsg::default();
+        let info = mock_info("creator", &[]);
+        let _res = instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+        let info = mock_info("creator", &[]);
+        let msg = ExecuteMsg::Relay { symbols: vec![String::from("ETH"), String::from("MATIC")], rates: vec![u64::MAX, 1u64], resolve_times: vec![1625108297000000000u64, 1625108298000000000u64], request_ids: vec![123u64, 124u64] };
+        let _res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+        let msg = QueryMsg::GetReferenceData { base: String::from("ETH"), quote: String::from("MATIC"), decimals: None };
+        let res = query(deps.as_ref(), mock_env(), msg).unwrap();
+        let value: ReferenceData = from_binary(&res).unwrap();
+        assert_eq!(Uint128::from(u64::MAX as u128 * E18), value.rate);
+        assert_eq!("18446744073709551615000000000000000000", value.rate.to_string());
+
+        // 10^38 times the rate no longer fits the response type
+        let msg = QueryMsg::GetReferenceData { base: String::from("ETH"), quote: String::from("MATIC"), decimals: Some(38) };
+        let err = query(deps.as_ref(), mock_env(), msg).unwrap_err();
+        assert!(matches!(err, ContractError::Overflow {}));
+    }
+
+    #[test]
+    fn query_last_relay() {
+        let mut deps = mock_dependencies(&[]);
+
+        let msg = InstantiateMsg::default();
+        let info = mock_info("creator", &[]);
+        let _res = instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+        let res = query(deps.as_ref(), mock_env(), QueryMsg::GetLastRelay {}).unwrap();
+        let value: LastRelayResponse = from_binary(&res).unwrap();
+        assert_eq!(LastRelayResponse{last_relay_time: 0u64, last_relay_block: 0u64, relay_seq: 0u64}, value);
+
+        let info = mock_info("creator", &[]);
+        let msg = ExecuteMsg::Relay { symbols: vec![String::from("ETH")], rates: vec![3000000000000u64], resolve_times: vec![1625108297000000000u64], request_ids: vec![123u64] };
+        let _res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+        let res = query(deps.as_ref(), mock_env(), QueryMsg::GetLastRelay {}).unwrap();
+        let value: LastRelayResponse = from_binary(&res).unwrap();
+        let env = mock_env();
+        assert_eq!(LastRelayResponse{last_relay_time: env.block.time.nanos(), last_relay_block: env.block.height, relay_seq: 1u64}, value);
+    }
+
+    #[test]
+    fn gated_messages_unauthorized() {
+        let mut deps = mock_dependencies(&[]);
+
+        let msg = InstantiateMsg::default();
+        let info = mock_info("creator", &[]);
+        let _res = instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+        let msgs = vec![
+            ExecuteMsg::Relay { symbols: vec![String::from("ETH")], rates: vec![1u64], resolve_times: vec![100u64], request_ids: vec![1u64] },
+            ExecuteMsg::RelayStrict { symbols: vec![String::from("ETH")], rates: vec![1u64], resolve_times: vec![100u64], request_ids: vec![1u64] },
+            ExecuteMsg::RelayWithStdDev { symbols: vec![String::from("ETH")], rates: vec![1u64], resolve_times: vec![100u64], request_ids: vec![1u64], std_devs: vec![1u64] },
+            ExecuteMsg::ForceRelay { symbols: vec![String::from("ETH")], rates: vec![1u64], resolve_times: vec![100u64], request_ids: vec![1u64] },
+            ExecuteMsg::RemoveSymbol { symbols: vec![String::from("ETH")] },
+            ExecuteMsg::Clear {},
+            ExecuteMsg::BatchUpdate { relays: RelayPayload::default(), remove: vec![String::from("ETH")] },
+            ExecuteMsg::AddRelayer { address: String::from("relayer") },
+            ExecuteMsg::RemoveRelayer { address: String::from("relayer") },
+            ExecuteMsg::AddRelayerDelayed { address: String::from("relayer"), delay_secs: 60u64 },
+            ExecuteMsg::RemoveRelayerPubkey { pubkey: Binary::from(vec![2u8; 33]) },
+            ExecuteMsg::TransferOwnership { new_owner: String::from("intruder") },
+            ExecuteMsg::AcceptOwnership {},
+            ExecuteMsg::Pause {},
+            ExecuteMsg::Unpause {},
+            ExecuteMsg::SetSymbolRelayers { symbol: String::from("ETH"), relayers: vec![String::from("intruder")] },
+            ExecuteMsg::UpdateConfig { owner: Some(String::from("intruder")), max_staleness_secs: None, max_future_skew_secs: None, max_rate_change_bps: None, base_decimals: None, usd_decimals: None, max_batch_size: None, min_relay_interval_secs: None, allow_signed_rates: None, rounding: None, strict_request_id: None, usd_timestamp_mode: None, twap_samples: None },
+        ];
+        for msg in msgs {
+            let info = mock_info("intruder", &[]);
+            let err = execute(deps.as_mut(), mock_env(), info, msg.clone()).unwrap_err();
+            assert!(matches!(err, ContractError::Unauthorized {}), "{:?} returned {:?}", msg, err);
+        }
+    }
+
+    #[test]
+    fn query_custom_decimals() {
+        let mut deps = mock_dependencies(&[]);
+
+        let msg = InstantiateMsg::default();
+        let info = mock_info("creator", &[]);
+        let _res = instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+        let info = mock_info("creator", &[]);
+        let msg = ExecuteMsg::Relay { symbols: vec![String::from("ETH"), String::from("MATIC")], rates: vec![3000000000000u64, 112u64], resolve_times: vec![1625108297000000000u64, 1625108298000000000u64], request_ids: vec![123u64, 124u64] };
+        let _res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+        let msg = QueryMsg::GetReferenceData { base: String::from("ETH"), quote: String::from("MATIC"), decimals: None };
+        let res = query(deps.as_ref(), mock_env(), msg).unwrap();
+        let default: ReferenceData = from_binary(&res).unwrap();
+        let msg = QueryMsg::GetReferenceData { base: String::from("ETH"), quote: String::from("MATIC"), decimals: Some(6) };
+        let res = query(deps.as_ref(), mock_env(), msg).unwrap();
+        let scaled: ReferenceData = from_binary(&res).unwrap();
+
+        // 3000000000000 / 112 = 26785714285.714285..., truncated at each precision
+        assert_eq!(Uint128::from(26785714285714285714285714285u128), default.rate);
+        assert_eq!(Uint128::from(26785714285714285u128), scaled.rate);
+        assert_eq!(Uint128::from(default.rate.u128() / 10u128.pow(12)), scaled.rate);
+        assert_eq!(default.last_updated_base, scaled.last_updated_base);
+        assert_eq!(default.last_updated_quote, scaled.last_updated_quote);
+
+        let msg = QueryMsg::GetReferenceData { base: String::from("ETH"), quote: String::from("MATIC"), decimals: Some(39) };
+        let err = query(deps.as_ref(), mock_env(), msg).unwrap_err();
+        assert!(matches!(err, ContractError::InvalidDecimals {}));
+    }
+
+    #[test]
+    fn query_symbols_pages() {
+        let mut deps = mock_dependencies(&[]);
+
+        let msg = InstantiateMsg::default();
+        let info = mock_info("creator", &[]);
+        let _res = instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+        let info = mock_info("creator", &[]);
+        let msg = ExecuteMsg::Relay { symbols: vec![String::from("MATIC"), String::from("BAND"), String::from("ETH"), String::from("ATOM")], rates: vec![1u64, 2u64, 3u64, 4u64], resolve_times: vec![100u64, 100u64, 100u64, 100u64], request_ids: vec![1u64, 1u64, 1u64, 1u64] };
+        let _res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+        let res = query(deps.as_ref(), mock_env(), QueryMsg::GetSymbols { start_after: None, limit: None }).unwrap();
+        let value: Vec<String> = from_binary(&res).unwrap();
+        assert_eq!(vec!["ATOM", "BAND", "ETH", "MATIC"], value);
+
+        let res = query(deps.as_ref(), mock_env(), QueryMsg::GetSymbols { start_after: None, limit: Some(2) }).unwrap();
+        let value: Vec<String> = from_binary(&res).unwrap();
+        assert_eq!(vec!["ATOM", "BAND"], value);
+
+        let res = query(deps.as_ref(), mock_env(), QueryMsg::GetSymbols { start_after: Some(String::from("BAND")), limit: Some(2) }).unwrap();
+        let value: Vec<String> = from_binary(&res).unwrap();
+        assert_eq!(vec!["ETH", "MATIC"], value);
+
+        let res = query(deps.as_ref(), mock_env(), QueryMsg::GetSymbols { start_after: Some(String::from("MATIC")), limit: Some(2) }).unwrap();
+        let value: Vec<String> = from_binary(&res).unwrap();
+        assert!(value.is_empty());
+    }
+
+    #[test]
+    fn batch_update() {
+        let mut deps = mock_dependencies(&[]);
+
+        let msg = InstantiateMsg::default();
+        let info = mock_info("creator", &[]);
+        let _res = instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+        let info = mock_info("creator", &[]);
+        let msg = ExecuteMsg::Relay { symbols: vec![String::from("BAND")], rates: vec![7000000000u64], resolve_times: vec![100u64], request_ids: vec![1u64] };
+        let _res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+        // a bad relay payload leaves BAND in place and ETH unwritten
+        let info = mock_info("creator", &[]);
+        let relays = RelayPayload { symbols: vec![String::from("ETH")], rates: vec![3000000000000u64], resolve_times: vec![], request_ids: vec![2u64] };
+        let msg = ExecuteMsg::BatchUpdate { relays, remove: vec![String::from("BAND")] };
+        let err = execute(deps.as_mut(), mock_env(), info, msg).unwrap_err();
+        assert!(matches!(err, ContractError::DifferentArrayLength { .. }));
+        let res = query(deps.as_ref(), mock_env(), QueryMsg::GetSymbols { start_after: None, limit: None }).unwrap();
+        let value: Vec<String> = from_binary(&res).unwrap();
+        assert_eq!(vec!["BAND"], value);
+
+        let info = mock_info("creator", &[]);
+        let relays = RelayPayload { symbols: vec![String::from("ETH")], rates: vec![3000000000000u64], resolve_times: vec![200u64], request_ids: vec![2u64] };
+        let msg = ExecuteMsg::BatchUpdate { relays, remove: vec![String::from("BAND")] };
+        let res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+        assert!(res.attributes.contains(&attr("num_removed", "1")));
+
+        let res = query(deps.as_ref(), mock_env(), QueryMsg::GetSymbols { start_after: None, limit: None }).unwrap();
+        let value: Vec<String> = from_binary(&res).unwrap();
+        assert_eq!(vec!["ETH"], value);
+        let res = query(deps.as_ref(), mock_env(), QueryMsg::GetSymbolCount {}).unwrap();
+        let value: u64 = from_binary(&res).unwrap();
+        assert_eq!(1u64, value);
+
+        let info = mock_info("creator", &[]);
+        let relays = RelayPayload { symbols: vec![String::from("ETH")], rates: vec![3000000000000u64], resolve_times: vec![300u64], request_ids: vec![3u64] };
+        let msg = ExecuteMsg::BatchUpdate { relays, remove: vec![String::from("ETH")] };
+        let err = execute(deps.as_mut(), mock_env(), info, msg).unwrap_err();
+        assert!(matches!(err, ContractError::DuplicateSymbol { .. }));
+
+        // only symbols that were actually stored are counted
+        let info = mock_info("creator", &[]);
+        let relays = RelayPayload::default();
+        let msg = ExecuteMsg::BatchUpdate { relays: relays.clone(), remove: vec![String::from("BAND"), String::from("ETH")] };
+        let res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+        assert_eq!(vec![attr("num_removed", "1")], res.attributes);
+
+        // a removal-only batch is still refused while paused
+        let info = mock_info("creator", &[]);
+        let msg = ExecuteMsg::Relay { symbols: vec![String::from("BAND")], rates: vec![7000000000u64], resolve_times: vec![400u64], request_ids: vec![4u64] };
+        let _res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+        let _res = execute(deps.as_mut(), mock_env(), mock_info("creator", &[]), ExecuteMsg::Pause {}).unwrap();
+        let msg = ExecuteMsg::BatchUpdate { relays, remove: vec![String::from("BAND")] };
+        let err = execute(deps.as_mut(), mock_env(), mock_info("creator", &[]), msg).unwrap_err();
+        assert!(matches!(err, ContractError::ContractPaused {}));
+        assert!(REFS.may_load(&deps.storage, "BAND").unwrap().is_some());
+    }
+
+    #[test]
+    fn relay_records_updater() {
+        let mut deps = mock_dependencies(&[]);
+
+        let msg = InstantiateMsg::default();
+        let info = mock_info("creator", &[]);
+        let _res = instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+        let info = mock_info("creator", &[]);
+        let msg = ExecuteMsg::AddRelayer { address: String::from("relayer1") };
+        let _res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+        let info = mock_info("creator", &[]);
+        let msg = ExecuteMsg::Relay { symbols: vec![String::from("ETH")], rates: vec![1u64], resolve_times: vec![2u64], request_ids: vec![3u64] };
+        let _res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+        let info = mock_info("relayer1", &[]);
+        let msg = ExecuteMsg::Relay { symbols: vec![String::from("BAND")], rates: vec![10u64], resolve_times: vec![20u64], request_ids: vec![30u64] };
+        let _res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+        let res = query(deps.as_ref(), mock_env(), QueryMsg::GetRef { symbol: String::from("ETH") }).unwrap();
+        let value: RefData = from_binary(&res).unwrap();
+        assert_eq!(Addr::unchecked("creator"), value.updated_by);
+        let res = query(deps.as_ref(), mock_env(), QueryMsg::GetRef { symbol: String::from("BAND") }).unwrap();
+        let value: RefData = from_binary(&res).unwrap();
+        assert_eq!(Addr::unchecked("relayer1"), value.updated_by);
+
+        // a newer write moves the attribution
+        let info = mock_info("relayer1", &[]);
+        let msg = ExecuteMsg::Relay { symbols: vec![String::from("ETH")], rates: vec![2u64], resolve_times: vec![4u64], request_ids: vec![5u64] };
+        let _res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+        let res = query(deps.as_ref(), mock_env(), QueryMsg::GetRef { symbol: String::from("ETH") }).unwrap();
+        let value: RefData = from_binary(&res).unwrap();
+        assert_eq!(Addr::unchecked("relayer1"), value.updated_by);
+    }
+
+    #[test]
+    fn relay_future_resolve_time() {
+        let mut deps = mock_dependencies(&[]);
+
+        let msg = InstantiateMsg::default();
+        let info = mock_info("creator", &[]);
+        let _res = instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+        let info = mock_info("creator", &[]);
+        let msg = ExecuteMsg::UpdateConfig { owner: None, max_staleness_secs: None, max_future_skew_secs: Some(Some(30u64)), max_rate_change_bps: None, base_decimals: None, usd_decimals: None, max_batch_size: None, min_relay_interval_secs: None, allow_signed_rates: None, rounding: None, strict_request_id: None, usd_timestamp_mode: None, twap_samples: None };
+        let _res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+        let now = mock_env().block.time.nanos();
+
+        // within the skew tolerance
+        let info = mock_info("creator", &[]);
+        let msg = ExecuteMsg::Relay { symbols: vec![String::from("ETH")], rates: vec![1u64], resolve_times: vec![now + 30 * 1_000_000_000], request_ids: vec![1u64] };
+        let _res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+        let info = mock_info("creator", &[]);
+        let msg = ExecuteMsg::Relay { symbols: vec![String::from("BAND"), String::from("ATOM")], rates: vec![1u64, 2u64], resolve_times: vec![now, now + 3600 * 1_000_000_000], request_ids: vec![2u64, 2u64] };
+        let err = execute(deps.as_mut(), mock_env(), info, msg).unwrap_err();
+        match err {
+            ContractError::ResolveTimeInFuture { symbol } => assert_eq!("ATOM", symbol),
+            err => panic!("unexpected error: {:?}", err),
+        }
+        let res = query(deps.as_ref(), mock_env(), QueryMsg::GetSymbolCount {}).unwrap();
+        let value: u64 = from_binary(&res).unwrap();
+        assert_eq!(1u64, value);
+    }
+
+    #[test]
+    fn try_query_reference_data() {
+        let mut deps = mock_dependencies(&[]);
+
+        let msg = InstantiateMsg::default();
+        let info = mock_info("creator", &[]);
+        let _res = instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+        let info = mock_info("creator", &[]);
+        let msg = ExecuteMsg::Relay { symbols: vec![String::from("ETH"), String::from("MATIC"), String::from("LUNA")], rates: vec![3000000000000u64, 112u64, 1u64], resolve_times: vec![1625108297000000000u64, 1625108298000000000u64, 0u64], request_ids: vec![123u64, 124u64, 125u64] };
+        let _res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+        let msg = QueryMsg::TryGetReferenceData { base: String::from("ETH"), quote: String::from("MATIC") };
+        let res = query(deps.as_ref(), mock_env(), msg).unwrap();
+        let value: Option<ReferenceData> = from_binary(&res).unwrap();
+        assert_eq!(Some(ReferenceData{rate: Uint128::from(26785714285714285714285714285u128), last_updated_base: Uint128::from(1625108297000000000u128), last_updated_quote: Uint128::from(1625108298000000000u128)}), value);
+
+        let msg = QueryMsg::TryGetReferenceData { base: String::from("ETH"), quote: String::from("BAND") };
+        let res = query(deps.as_ref(), mock_env(), msg).unwrap();
+        let value: Option<ReferenceData> = from_binary(&res).unwrap();
+        assert_eq!(None, value);
+
+        let msg = QueryMsg::TryGetReferenceData { base: String::from("LUNA"), quote: String::from("USD") };
+        let res = query(deps.as_ref(), mock_env(), msg).unwrap();
+        let value: Option<ReferenceData> = from_binary(&res).unwrap();
+        assert_eq!(None, value);
+    }
+
+    #[test]
+    fn delayed_relayer() {
+        let mut deps = mock_dependencies(&[]);
+
+        let msg = InstantiateMsg::default();
+        let info = mock_info("creator", &[]);
+        let _res = instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+        let info = mock_info("creator", &[]);
+        let msg = ExecuteMsg::AddRelayerDelayed { address: String::from("relayer1"), delay_secs: 3600u64 };
+        let _res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+        let active_after = mock_env().block.time.plus_seconds(3600).nanos();
+        let res = query(deps.as_ref(), mock_env(), QueryMsg::GetRelayers {}).unwrap();
+        let value: RelayersResponse = from_binary(&res).unwrap();
+        assert_eq!(RelayersResponse{relayers: vec![Addr::unchecked("relayer1")], active_after: vec![active_after]}, value);
+
+        let mut env = mock_env();
+        env.block.time = env.block.time.plus_seconds(3599);
+        let info = mock_info("relayer1", &[]);
+        let msg = ExecuteMsg::Relay { symbols: vec![String::from("ETH")], rates: vec![1u64], resolve_times: vec![2u64], request_ids: vec![3u64] };
+        let err = execute(deps.as_mut(), env, info, msg).unwrap_err();
+        assert!(matches!(err, ContractError::Unauthorized {}));
+
+        let mut env = mock_env();
+        env.block.time = env.block.time.plus_seconds(3600);
+        let info = mock_info("relayer1", &[]);
+        let msg = ExecuteMsg::Relay { symbols: vec![String::from("ETH")], rates: vec![1u64], resolve_times: vec![2u64], request_ids: vec![3u64] };
+        let _res = execute(deps.as_mut(), env, info, msg).unwrap();
+
+        // an absurd delay saturates instead of overflowing
+        let info = mock_info("creator", &[]);
+        let msg = ExecuteMsg::AddRelayerDelayed { address: String::from("relayer2"), delay_secs: u64::MAX };
+        let res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+        assert_eq!(attr("active_after", u64::MAX.to_string()), res.attributes[2]);
+        let info = mock_info("creator", &[]);
+        let _res = execute(deps.as_mut(), mock_env(), info, ExecuteMsg::RemoveRelayer { address: String::from("relayer2") }).unwrap();
+
+        // adding without delay activates right away
+        let info = mock_info("creator", &[]);
+        let msg = ExecuteMsg::AddRelayer { address: String::from("relayer1") };
+        let _res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+        let res = query(deps.as_ref(), mock_env(), QueryMsg::GetRelayers {}).unwrap();
+        let value: RelayersResponse = from_binary(&res).unwrap();
+        assert_eq!(vec![0u64], value.active_after);
+    }
+
+    #[test]
+    fn query_cross_rate() {
+        let mut deps = mock_dependencies(&[]);
+
+        let msg = InstantiateMsg::default();
+        let info = mock_info("creator", &[]);
+        let _res = instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+        let info = mock_info("creator", &[]);
+        let msg = ExecuteMsg::Relay { symbols: vec![String::from("ETH"), String::from("BAND")], rates: vec![3000000000000u64, 7500000000u64], resolve_times: vec![1625108297000000000u64, 1625108298000000000u64], request_ids: vec![123u64, 124u64] };
+        let _res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+        let msg = QueryMsg::GetCrossRate { base: String::from("ETH"), quote: String::from("BAND") };
+        let res = query(deps.as_ref(), mock_env(), msg).unwrap();
+        let cross: ReferenceData = from_binary(&res).unwrap();
+        assert_eq!(ReferenceData{rate: Uint128::from(3000000000000u128 * E18 / 7500000000u128), last_updated_base: Uint128::from(1625108297000000000u128), last_updated_quote: Uint128::from(1625108298000000000u128)}, cross);
+
+        let msg = QueryMsg::GetReferenceData { base: String::from("ETH"), quote: String::from("BAND"), decimals: None };
+        let res = query(deps.as_ref(), mock_env(), msg).unwrap();
+        let direct: ReferenceData = from_binary(&res).unwrap();
+        assert_eq!(direct, cross);
+
+        // USD on either side is the plain USD rate
+        let msg = QueryMsg::GetCrossRate { base: String::from("USD"), quote: String::from("BAND") };
+        let res = query(deps.as_ref(), mock_env(), msg).unwrap();
+        let value: ReferenceData = from_binary(&res).unwrap();
+        assert_eq!(Uint128::from(E9 * E18 / 7500000000u128), value.rate);
+        let msg = QueryMsg::GetCrossRate { base: String::from("ETH"), quote: String::from("USD") };
+        let res = query(deps.as_ref(), mock_env(), msg).unwrap();
+        let value: ReferenceData = from_binary(&res).unwrap();
+        assert_eq!(Uint128::from(3000u128 * E18), value.rate);
+    }
+
+    #[test]
+    fn clear_refs() {
+        let mut deps = mock_dependencies(&[]);
+
+        let msg = InstantiateMsg::default();
+        let info = mock_info("creator", &[]);
+        let _res = instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+        let info = mock_info("creator", &[]);
+        let msg = ExecuteMsg::AddRelayer { address: String::from("relayer1") };
+        let _res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+        let info = mock_info("relayer1", &[]);
+        let msg = ExecuteMsg::Relay { symbols: vec![String::from("ETH"), String::from("BAND"), String::from("MATIC")], rates: vec![1u64, 2u64, 3u64], resolve_times: vec![100u64, 100u64, 100u64], request_ids: vec![1u64, 1u64, 1u64] };
+        let _res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+        let info = mock_info("creator", &[]);
+        let _res = execute(deps.as_mut(), mock_env(), info, ExecuteMsg::Clear {}).unwrap();
+
+        let res = query(deps.as_ref(), mock_env(), QueryMsg::GetAllRefs {}).unwrap();
+        let value: ConfigResponse = from_binary(&res).unwrap();
+        assert_eq!(BTreeMap::new(), value.refs);
+        let res = query(deps.as_ref(), mock_env(), QueryMsg::GetSymbolCount {}).unwrap();
+        let value: u64 = from_binary(&res).unwrap();
+        assert_eq!(0u64, value);
+        let res = query(deps.as_ref(), mock_env(), QueryMsg::GetLastRelay {}).unwrap();
+        let value: LastRelayResponse = from_binary(&res).unwrap();
+        assert_eq!(LastRelayResponse{last_relay_time: 0u64, last_relay_block: 0u64, relay_seq: 1u64}, value);
+
+        let res = query(deps.as_ref(), mock_env(), QueryMsg::GetOwnership {}).unwrap();
+        let value: OwnershipResponse = from_binary(&res).unwrap();
+        assert_eq!(Some(Addr::unchecked("creator")), value.owner);
+        let res = query(deps.as_ref(), mock_env(), QueryMsg::GetRelayers {}).unwrap();
+        let value: RelayersResponse = from_binary(&res).unwrap();
+        assert_eq!(vec![Addr::unchecked("relayer1")], value.relayers);
+    }
+
+    #[test]
+    fn query_before_instantiate() {
+        let deps = mock_dependencies(&[]);
+
+        let err = query(deps.as_ref(), mock_env(), QueryMsg::GetOwnership {}).unwrap_err();
+        assert!(matches!(err, ContractError::NotInitialized {}));
+        let err = query(deps.as_ref(), mock_env(), QueryMsg::IsPaused {}).unwrap_err();
+        assert!(matches!(err, ContractError::NotInitialized {}));
+
+        let err = query_ref(deps.as_ref(), mock_env(), String::from("ETH")).unwrap_err();
+        assert!(matches!(err, ContractError::NotInitialized {}));
+        let err = query_reference_data(deps.as_ref(), mock_env(), String::from("ETH"), String::from("USD"), None).unwrap_err();
+        assert!(matches!(err, ContractError::NotInitialized {}));
+    }
+
+    #[test]
+    fn relay_rate_jump() {
+        let mut deps = mock_dependencies(&[]);
+
+        let msg = InstantiateMsg::default();
+        let info = mock_info("creator", &[]);
+        let _res = instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+        let info = mock_info("creator", &[]);
+        let msg = ExecuteMsg::UpdateConfig { owner: None, max_staleness_secs: None, max_future_skew_secs: None, max_rate_change_bps: Some(Some(500u16)), base_decimals: None, usd_decimals: None, max_batch_size: None, min_relay_interval_secs: None, allow_signed_rates: None, rounding: None, strict_request_id: None, usd_timestamp_mode: None, twap_samples: None };
+        let _res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+        // first insert has nothing to compare against
+        let info = mock_info("creator", &[]);
+        let msg = ExecuteMsg::Relay { symbols: vec![String::from("ETH")], rates: vec![1000u64], resolve_times: vec![100u64], request_ids: vec![1u64] };
+        let _res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+        // exactly 5% is accepted
+        let info = mock_info("creator", &[]);
+        let msg = ExecuteMsg::Relay { symbols: vec![String::from("ETH")], rates: vec![950u64], resolve_times: vec![200u64], request_ids: vec![2u64] };
+        let _res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+        let info = mock_info("creator", &[]);
+        let msg = ExecuteMsg::Relay { symbols: vec![String::from("ETH")], rates: vec![1000u64], resolve_times: vec![300u64], request_ids: vec![3u64] };
+        let err = execute(deps.as_mut(), mock_env(), info, msg).unwrap_err();
+        match err {
+            ContractError::RateJumpTooLarge { symbol } => assert_eq!("ETH", symbol),
+            err => panic!("unexpected error: {:?}", err),
+        }
+
+        let info = mock_info("creator", &[]);
+        let msg = ExecuteMsg::ForceRelay { symbols: vec![String::from("ETH")], rates: vec![1000u64], resolve_times: vec![300u64], request_ids: vec![3u64] };
+        let _res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+        let res = query(deps.as_ref(), mock_env(), QueryMsg::GetRef { symbol: String::from("ETH") }).unwrap();
+        let value: RefData = from_binary(&res).unwrap();
+        assert_eq!(1000u64, value.rate);
+
+        // a 90% drop is still a jump when it comes with fewer decimals
+        let info = mock_info("creator", &[]);
+        let msg = ExecuteMsg::RelayWithDecimals { symbols: vec![String::from("ETH")], rates: vec![10u64], resolve_times: vec![400u64], request_ids: vec![4u64], decimals: vec![8] };
+        let err = execute(deps.as_mut(), mock_env(), info, msg).unwrap_err();
+        assert!(matches!(err, ContractError::RateJumpTooLarge { symbol } if symbol == "ETH"));
+
+        // the same value at another precision is no jump, in either direction
+        let info = mock_info("creator", &[]);
+        let msg = ExecuteMsg::RelayWithDecimals { symbols: vec![String::from("ETH")], rates: vec![100u64], resolve_times: vec![400u64], request_ids: vec![4u64], decimals: vec![8] };
+        let _res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+        let info = mock_info("creator", &[]);
+        let msg = ExecuteMsg::Relay { symbols: vec![String::from("ETH")], rates: vec![100u64], resolve_times: vec![500u64], request_ids: vec![5u64] };
+        let err = execute(deps.as_mut(), mock_env(), info, msg).unwrap_err();
+        assert!(matches!(err, ContractError::RateJumpTooLarge { symbol } if symbol == "ETH"));
+        let info = mock_info("creator", &[]);
+        let msg = ExecuteMsg::Relay { symbols: vec![String::from("ETH")], rates: vec![1020u64], resolve_times: vec![500u64], request_ids: vec![5u64] };
+        let _res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+    }
+
+    #[test]
+    fn query_multi() {
+        let mut deps = mock_dependencies(&[]);
+
+        let msg = InstantiateMsg::default();
+        let info = mock_info("creator", &[]);
+        let _res = instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+        let info = mock_info("creator", &[]);
+        let msg = ExecuteMsg::Relay { symbols: vec![String::from("ETH"), String::from("BAND"), String::from("MATIC")], rates: vec![3000000000000u64, 7500000000u64, 112u64], resolve_times: vec![1625108297000000000u64, 1625108298000000000u64, 1625108299000000000u64], request_ids: vec![123u64, 124u64, 125u64] };
+        let _res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+        let quotes = vec![String::from("ETH"), String::from("BAND"), String::from("MATIC")];
+        let msg = QueryMsg::GetReferenceDataMulti { base: String::from("USD"), quotes: quotes.clone() };
+        let res = query(deps.as_ref(), mock_env(), msg).unwrap();
+        let value: Vec<ReferenceData> = from_binary(&res).unwrap();
+        let now = Uint128::from(mock_env().block.time.nanos());
+        assert_eq!(vec![
+            ReferenceData{rate: Uint128::from(333333333333333u128), last_updated_base: now, last_updated_quote: Uint128::from(1625108297000000000u128)},
+            ReferenceData{rate: Uint128::from(133333333333333333u128), last_updated_base: now, last_updated_quote: Uint128::from(1625108298000000000u128)},
+            ReferenceData{rate: Uint128::from(8928571428571428571428571u128), last_updated_base: now, last_updated_quote: Uint128::from(1625108299000000000u128)},
+        ], value);
+
+        // matches the pairwise query for every quote
+        for (quote, data) in quotes.into_iter().zip(value) {
+            assert_eq!(query_reference_data(deps.as_ref(), mock_env(), String::from("USD"), quote, None).unwrap(), data);
+        }
+
+        let msg = QueryMsg::GetReferenceDataMulti { base: String::from("USD"), quotes: vec![String::from("ETH"), String::from("LUNA")] };
+        let err = query(deps.as_ref(), mock_env(), msg).unwrap_err();
+        assert!(matches!(err, ContractError::SymbolNotFound { symbol } if symbol == "LUNA"));
+
+        // a quote equal to the base is exactly one, like the pairwise query, even at a zero stored rate
+        let info = mock_info("creator", &[]);
+        let msg = ExecuteMsg::Relay { symbols: vec![String::from("UST")], rates: vec![0u64], resolve_times: vec![1625108300000000000u64], request_ids: vec![126u64] };
+        let _res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+        for base in ["USD", "ETH", "UST"] {
+            let msg = QueryMsg::GetReferenceDataMulti { base: String::from(base), quotes: vec![String::from(base)] };
+            let value: Vec<ReferenceData> = from_binary(&query(deps.as_ref(), mock_env(), msg).unwrap()).unwrap();
+            assert_eq!(Uint128::from(E18), value[0].rate);
+            assert_eq!(query_reference_data(deps.as_ref(), mock_env(), String::from(base), String::from(base), None).unwrap(), value[0]);
+        }
+        let msg = QueryMsg::GetReferenceDataMulti { base: String::from("ETH"), quotes: vec![String::from("UST")] };
+        let err = query(deps.as_ref(), mock_env(), msg).unwrap_err();
+        assert!(matches!(err, ContractError::DivideByZero {}));
+    }
+
+    #[test]
+    fn query_config() {
+        let mut deps = mock_dependencies(&[]);
+
+        let msg = InstantiateMsg { usd_decimals: Some(6), symbol_separator: Some(String::from("_")), ..InstantiateMsg::default() };
+        let info = mock_info("creator", &[]);
+        let _res = instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+        let res = query(deps.as_ref(), mock_env(), QueryMsg::GetConfig {}).unwrap();
+        let value: FullConfigResponse = from_binary(&res).unwrap();
+        assert_eq!(FullConfigResponse {
+            owner: Some(Addr::unchecked("creator")),
+            pending_owner: None,
+            relayers: vec![],
+            paused: false,
+            symbol_relayers: BTreeMap::new(),
+            symbol_count: 0,
+            max_staleness_secs: None,
+            base_decimals: 18,
+            usd_decimals: 6,
+            usd_rate: None,
+            symbol_separator: Some(String::from("_")),
+            max_future_skew_secs: None,
+            max_rate_change_bps: None,
+            max_batch_size: 256,
+            relayer_active_after: BTreeMap::new(),
+            last_relay_time: 0,
+            last_relay_block: 0,
+            relayer_pubkeys: vec![],
+            min_relay_interval_secs: None,
+            allow_signed_rates: false,
+            relay_seq: 0,
+            rounding: RoundingMode::Trunc,
+            frozen: BTreeSet::new(),
+            strict_request_id: false,
+            event_namespace: String::new(),
+            usd_timestamp_mode: UsdTimestampMode::BlockTime,
+            twap_samples: 8,
+        }, value);
+
+        let info = mock_info("creator", &[]);
+        let msg = ExecuteMsg::UpdateConfig { owner: None, max_staleness_secs: Some(Some(60u64)), max_future_skew_secs: Some(Some(5u64)), max_rate_change_bps: Some(Some(100u16)), base_decimals: Some(9), usd_decimals: None, max_batch_size: None, min_relay_interval_secs: None, allow_signed_rates: None, rounding: None, strict_request_id: None, usd_timestamp_mode: None, twap_samples: None };
+        let _res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+        let info = mock_info("creator", &[]);
+        let msg = ExecuteMsg::SetSymbolRelayers { symbol: String::from("ETH"), relayers: vec![String::from("relayer1")] };
+        let _res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+        let res = query(deps.as_ref(), mock_env(), QueryMsg::GetConfig {}).unwrap();
+        let value: FullConfigResponse = from_binary(&res).unwrap();
+        assert_eq!(Some(60u64), value.max_staleness_secs);
+        assert_eq!(Some(5u64), value.max_future_skew_secs);
+        assert_eq!(Some(100u16), value.max_rate_change_bps);
+        assert_eq!(9u32, value.base_decimals);
+        assert_eq!(6u32, value.usd_decimals);
+        assert_eq!(Some(&vec![Addr::unchecked("relayer1")]), value.symbol_relayers.get("ETH"));
+    }
+
+    #[test]
+    fn relay_batch_size() {
+        let mut deps = mock_dependencies(&[]);
+
+        let msg = InstantiateMsg::default();
+        let info = mock_info("creator", &[]);
+        let _res = instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+        let relay = |len: usize| ExecuteMsg::Relay {
+            symbols: (0..len).map(|idx| format!("S{}", idx)).collect(),
+            rates: vec![1u64; len],
+            resolve_times: vec![100u64; len],
+            request_ids: vec![1u64; len],
+        };
+
+        let info = mock_info("creator", &[]);
+        let err = execute(deps.as_mut(), mock_env(), info, relay(257)).unwrap_err();
+        assert!(matches!(err, ContractError::BatchTooLarge { size: 257, max: 256 }));
+
+        let info = mock_info("creator", &[]);
+        let _res = execute(deps.as_mut(), mock_env(), info, relay(256)).unwrap();
+
+        let info = mock_info("creator", &[]);
+        let msg = ExecuteMsg::UpdateConfig { owner: None, max_staleness_secs: None, max_future_skew_secs: None, max_rate_change_bps: None, base_decimals: None, usd_decimals: None, max_batch_size: Some(2u32), min_relay_interval_secs: None, allow_signed_rates: None, rounding: None, strict_request_id: None, usd_timestamp_mode: None, twap_samples: None };
+        let _res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+        let info = mock_info("creator", &[]);
+        let err = execute(deps.as_mut(), mock_env(), info, relay(3)).unwrap_err();
+        assert!(matches!(err, ContractError::BatchTooLarge { size: 3, max: 2 }));
+    }
+
+    #[test]
+    fn query_has_symbol() {
+        let mut deps = mock_dependencies(&[]);
+
+        let msg = InstantiateMsg::default();
+        let info = mock_info("creator", &[]);
+        let _res = instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+        let info = mock_info("creator", &[]);
+        let msg = ExecuteMsg::Relay { symbols: vec![String::from("ETH")], rates: vec![1u64], resolve_times: vec![100u64], request_ids: vec![1u64] };
+        let _res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+        let res = query(deps.as_ref(), mock_env(), QueryMsg::HasSymbol { symbol: String::from("ETH") }).unwrap();
+        assert!(from_binary::<bool>(&res).unwrap());
+        let res = query(deps.as_ref(), mock_env(), QueryMsg::HasSymbol { symbol: String::from("BAND") }).unwrap();
+        assert!(!from_binary::<bool>(&res).unwrap());
+        let res = query(deps.as_ref(), mock_env(), QueryMsg::HasSymbol { symbol: String::from("USD") }).unwrap();
+        assert!(from_binary::<bool>(&res).unwrap());
+    }
+
+    #[test]
+    fn custom_usd_rate() {
+        let mut deps = mock_dependencies(&[]);
+
+        let msg = InstantiateMsg { usd_rate: Some(0u64), ..InstantiateMsg::default() };
+        let info = mock_info("creator", &[]);
+        let err = instantiate(deps.as_mut(), mock_env(), info, msg).unwrap_err();
+        assert!(matches!(err, ContractError::InvalidUsdRate {}));
+
+        // 1 USD is worth 1.25 units of the reference currency
+        let msg = InstantiateMsg { usd_rate: Some(1250000000u64), ..InstantiateMsg::default() };
+        let info = mock_info("creator", &[]);
+        let _res = instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+        let info = mock_info("creator", &[]);
+        let msg = ExecuteMsg::Relay { symbols: vec![String::from("MATIC")], rates: vec![112u64], resolve_times: vec![1625108298000000000u64], request_ids: vec![124u64] };
+        let _res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+        let msg = QueryMsg::GetReferenceData { base: String::from("USD"), quote: String::from("MATIC"), decimals: None };
+        let res = query(deps.as_ref(), mock_env(), msg).unwrap();
+        let value: ReferenceData = from_binary(&res).unwrap();
+        assert_eq!(Uint128::from(1250000000u128 * E18 / 112u128), value.rate);
+
+        let res = query(deps.as_ref(), mock_env(), QueryMsg::GetRef { symbol: String::from("USD") }).unwrap();
+        let value: RefData = from_binary(&res).unwrap();
+        assert_eq!(1250000000u64, value.rate);
+
+        // the pinned rate is at 9 decimals; reading it at 6 would make USD 1000x more valuable
+        let info = mock_info("creator", &[]);
+        let msg = ExecuteMsg::UpdateConfig { owner: None, max_staleness_secs: None, max_future_skew_secs: None, max_rate_change_bps: None, base_decimals: None, usd_decimals: Some(6), max_batch_size: None, min_relay_interval_secs: None, allow_signed_rates: None, rounding: None, strict_request_id: None, usd_timestamp_mode: None, twap_samples: None };
+        let err = execute(deps.as_mut(), mock_env(), info, msg).unwrap_err();
+        assert!(matches!(err, ContractError::UsdRatePinned {}));
+
+        let info = mock_info("creator", &[]);
+        let msg = ExecuteMsg::UpdateConfig { owner: None, max_staleness_secs: None, max_future_skew_secs: None, max_rate_change_bps: None, base_decimals: None, usd_decimals: Some(9), max_batch_size: None, min_relay_interval_secs: None, allow_signed_rates: None, rounding: None, strict_request_id: None, usd_timestamp_mode: None, twap_samples: None };
+        let _res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+        let msg = QueryMsg::GetReferenceData { base: String::from("MATIC"), quote: String::from("USD"), decimals: None };
+        let res = query(deps.as_ref(), mock_env(), msg).unwrap();
+        let value: ReferenceData = from_binary(&res).unwrap();
+        assert_eq!(Uint128::from(112u128 * E18 / 1250000000u128), value.rate);
+    }
+
+    #[test]
+    fn admin_attributes() {
+        let mut deps = mock_dependencies(&[]);
+
+        let msg = InstantiateMsg::default();
+        let info = mock_info("creator", &[]);
+        let _res = instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+        let cases = vec![
+            (ExecuteMsg::AddRelayer { address: String::from("relayer1") }, vec![attr("action", "add_relayer"), attr("relayer", "relayer1")]),
+            (ExecuteMsg::RemoveRelayer { address: String::from("relayer1") }, vec![attr("action", "remove_relayer"), attr("relayer", "relayer1")]),
+            (ExecuteMsg::AddRelayerDelayed { address: String::from("relayer2"), delay_secs: 60u64 }, vec![attr("action", "add_relayer_delayed"), attr("relayer", "relayer2"), attr("active_after", mock_env().block.time.plus_seconds(60).nanos().to_string())]),
+            (ExecuteMsg::SetSymbolRelayers { symbol: String::from("ETH"), relayers: vec![String::from("relayer3")] }, vec![attr("action", "set_symbol_relayers"), attr("symbol", "ETH"), attr("num_relayers", "1")]),
+            (ExecuteMsg::Pause {}, vec![attr("action", "pause")]),
+            (ExecuteMsg::Unpause {}, vec![attr("action", "unpause")]),
+            (ExecuteMsg::UpdateConfig { owner: None, max_staleness_secs: Some(Some(60u64)), max_future_skew_secs: None, max_rate_change_bps: None, base_decimals: None, usd_decimals: None, max_batch_size: None, min_relay_interval_secs: None, allow_signed_rates: None, rounding: None, strict_request_id: None, usd_timestamp_mode: None, twap_samples: None }, vec![
+                attr("action", "update_config"),
+                attr("owner", "creator"),
+                attr("max_staleness_secs", "60"),
+                attr("max_future_skew_secs", "none"),
+                attr("max_rate_change_bps", "none"),
+                attr("base_decimals", "18"),
+                attr("usd_decimals", "9"),
+                attr("max_batch_size", "256"),
+                attr("min_relay_interval_secs", "none"),
+                attr("allow_signed_rates", "false"),
+                attr("rounding", "trunc"),
+                attr("strict_request_id", "false"),
+                attr("usd_timestamp_mode", "blocktime"),
+                attr("twap_samples", "8"),
+            ]),
+            (ExecuteMsg::Clear {}, vec![attr("action", "clear"), attr("num_removed", "0")]),
+            (ExecuteMsg::TransferOwnership { new_owner: String::from("owner2") }, vec![attr("action", "transfer_ownership"), attr("pending_owner", "owner2")]),
+        ];
+        for (msg, attributes) in cases {
+            let info = mock_info("creator", &[]);
+            let res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+            assert_eq!(attributes, res.attributes);
+        }
+
+        let info = mock_info("owner2", &[]);
+        let res = execute(deps.as_mut(), mock_env(), info, ExecuteMsg::AcceptOwnership {}).unwrap();
+        assert_eq!(vec![attr("action", "accept_ownership"), attr("owner", "owner2")], res.attributes);
+    }
+
+    #[test]
+    fn query_oldest_resolve_time() {
+        let mut deps = mock_dependencies(&[]);
+
+        let msg = InstantiateMsg::default();
+        let info = mock_info("creator", &[]);
+        let _res = instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+        let res = query(deps.as_ref(), mock_env(), QueryMsg::GetOldestResolveTime {}).unwrap();
+        let value: Option<(String, u64)> = from_binary(&res).unwrap();
+        assert_eq!(None, value);
+
+        let info = mock_info("creator", &[]);
+        let msg = ExecuteMsg::Relay { symbols: vec![String::from("ETH"), String::from("BAND"), String::from("MATIC")], rates: vec![1u64, 2u64, 3u64], resolve_times: vec![300u64, 100u64, 200u64], request_ids: vec![1u64, 1u64, 1u64] };
+        let _res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+        let res = query(deps.as_ref(), mock_env(), QueryMsg::GetOldestResolveTime {}).unwrap();
+        let value: Option<(String, u64)> = from_binary(&res).unwrap();
+        assert_eq!(Some((String::from("BAND"), 100u64)), value);
+
+        // a newer value moves BAND out of the way
+        let info = mock_info("creator", &[]);
+        let msg = ExecuteMsg::Relay { symbols: vec![String::from("BAND")], rates: vec![2u64], resolve_times: vec![400u64], request_ids: vec![2u64] };
+        let _res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+        let res = query(deps.as_ref(), mock_env(), QueryMsg::GetOldestResolveTime {}).unwrap();
+        let value: Option<(String, u64)> = from_binary(&res).unwrap();
+        assert_eq!(Some((String::from("MATIC"), 200u64)), value);
+
+        let info = mock_info("creator", &[]);
+        let _res = execute(deps.as_mut(), mock_env(), info, ExecuteMsg::RemoveSymbol { symbols: vec![String::from("MATIC")] }).unwrap();
+        let res = query(deps.as_ref(), mock_env(), QueryMsg::GetOldestResolveTime {}).unwrap();
+        let value: Option<(String, u64)> = from_binary(&res).unwrap();
+        assert_eq!(Some((String::from("ETH"), 300u64)), value);
+
+        let info = mock_info("creator", &[]);
+        let _res = execute(deps.as_mut(), mock_env(), info, ExecuteMsg::Clear {}).unwrap();
+        let res = query(deps.as_ref(), mock_env(), QueryMsg::GetOldestResolveTime {}).unwrap();
+        let value: Option<(String, u64)> = from_binary(&res).unwrap();
+        assert_eq!(None, value);
+    }
+
+    #[test]
+    fn relay_unchanged_skips_write() {
+        let mut deps = mock_dependencies(&[]);
+
+        let msg = InstantiateMsg::default();
+        let info = mock_info("creator", &[]);
+        let _res = instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+        let info = mock_info("creator", &[]);
+        let msg = ExecuteMsg::Relay { symbols: vec![String::from("ETH"), String::from("BAND")], rates: vec![1u64, 2u64], resolve_times: vec![100u64, 100u64], request_ids: vec![1u64, 1u64] };
+        let res = execute(deps.as_mut(), mock_env(), info, msg.clone()).unwrap();
+        assert!(res.attributes.contains(&attr("num_updated", "2")));
+
+        let info = mock_info("creator", &[]);
+        let res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+        assert_eq!(vec![attr("action", "relay"), attr("num_symbols", "2"), attr("num_updated", "0")], res.attributes);
+
+        let info = mock_info("creator", &[]);
+        let msg = ExecuteMsg::Relay { symbols: vec![String::from("ETH"), String::from("BAND")], rates: vec![1u64, 3u64], resolve_times: vec![100u64, 200u64], request_ids: vec![1u64, 2u64] };
+        let res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+        assert_eq!(vec![attr("action", "relay"), attr("num_symbols", "2"), attr("num_updated", "1"), attr("symbol:BAND", "3@200")], res.attributes);
+    }
+
+    #[test]
+    fn resolve_time_units() {
+        let mut deps = mock_dependencies(&[]);
+
+        let msg = InstantiateMsg::default();
+        let info = mock_info("creator", &[]);
+        let _res = instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+        let resolve_time = mock_env().block.time.nanos() - 5 * NANOS_PER_SECOND;
+        let info = mock_info("creator", &[]);
+        let msg = ExecuteMsg::Relay { symbols: vec![String::from("ETH")], rates: vec![1u64], resolve_times: vec![resolve_time], request_ids: vec![1u64] };
+        let _res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+        let res = query(deps.as_ref(), mock_env(), QueryMsg::GetRef { symbol: String::from("USD") }).unwrap();
+        let usd: RefData = from_binary(&res).unwrap();
+        let res = query(deps.as_ref(), mock_env(), QueryMsg::GetRef { symbol: String::from("ETH") }).unwrap();
+        let eth: RefData = from_binary(&res).unwrap();
+
+        assert_eq!(5 * NANOS_PER_SECOND, usd.resolve_time_nanos() - eth.resolve_time_nanos());
+        assert_eq!(mock_env().block.time.nanos() / NANOS_PER_SECOND, usd.resolve_time_secs());
+        assert_eq!(usd.resolve_time_secs() - 5, eth.resolve_time_secs());
+    }
+
+    #[test]
+    fn query_reference_data_status() {
+        let mut deps = mock_dependencies(&[]);
+
+        let msg = InstantiateMsg::default();
+        let info = mock_info("creator", &[]);
+        let _res = instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+        let info = mock_info("creator", &[]);
+        let msg = ExecuteMsg::Relay { symbols: vec![String::from("BAND"), String::from("ZERO"), String::from("UNRESOLVED")], rates: vec![2000000000u64, 0u64, 1u64], resolve_times: vec![100u64, 100u64, 0u64], request_ids: vec![1u64, 1u64, 1u64] };
+        let _res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+        let status = |base: &str, quote: &str| -> ReferenceDataStatus {
+            let msg = QueryMsg::GetReferenceDataStatus { base: String::from(base), quote: String::from(quote) };
+            from_binary(&query(deps.as_ref(), mock_env(), msg).unwrap()).unwrap()
+        };
+
+        let res = status("BAND", "USD");
+        assert_eq!(RefStatus::Ok, res.status);
+        assert_eq!(Uint128::from(2000000000000000000u128), res.data.unwrap().rate);
+        assert_eq!(ReferenceDataStatus { status: RefStatus::BaseMissing, data: None }, status("ETH", "USD"));
+        assert_eq!(ReferenceDataStatus { status: RefStatus::BaseMissing, data: None }, status("UNRESOLVED", "ETH"));
+        assert_eq!(ReferenceDataStatus { status: RefStatus::QuoteMissing, data: None }, status("BAND", "ETH"));
+        assert_eq!(ReferenceDataStatus { status: RefStatus::QuoteMissing, data: None }, status("BAND", "UNRESOLVED"));
+        assert_eq!(ReferenceDataStatus { status: RefStatus::QuoteZero, data: None }, status("BAND", "ZERO"));
+    }
+
+    #[test]
+    fn relay_signed() {
+        use k256::ecdsa::{signature::DigestSigner, Signature, SigningKey};
+
+        let mut deps = mock_dependencies(&[]);
+
+        let msg = InstantiateMsg::default();
+        let info = mock_info("creator", &[]);
+        let _res = instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+        let signing_key = SigningKey::from_bytes(&[7u8; 32]).unwrap();
+        let pubkey = Binary::from(signing_key.verify_key().to_bytes().as_slice());
+        let info = mock_info("creator", &[]);
+        let msg = ExecuteMsg::AddRelayerPubkey { pubkey: pubkey.clone() };
+        let _res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+        let sign = |key: &SigningKey, payload: &Binary| -> Binary {
+            let signature: Signature = key.sign_digest(Sha256::new().chain(payload.as_slice()));
+            Binary::from(signature.as_ref())
+        };
+        let signed = |nonce: u64, rate: u64, resolve_time: u64| SignedRelayPayload {
+            contract_addr: mock_env().contract.address.to_string(),
+            chain_id: mock_env().block.chain_id,
+            nonce,
+            relays: RelayPayload { symbols: vec![String::from("ETH")], rates: vec![rate], resolve_times: vec![resolve_time], request_ids: vec![1u64] },
+        };
+        let payload = to_binary(&signed(1, 3000, 100)).unwrap();
+        let signature = sign(&signing_key, &payload);
+
+        // tampered payload with the original signature
+        let tampered = to_binary(&signed(1, 1, 100)).unwrap();
+        let info = mock_info("anyone", &[]);
+        let msg = ExecuteMsg::RelaySigned { payload: tampered, signature: signature.clone(), pubkey: pubkey.clone() };
+        let err = execute(deps.as_mut(), mock_env(), info, msg).unwrap_err();
+        assert!(matches!(err, ContractError::InvalidSignature {}));
+
+        // a valid signature from an unregistered key
+        let other_key = SigningKey::from_bytes(&[8u8; 32]).unwrap();
+        let info = mock_info("anyone", &[]);
+        let msg = ExecuteMsg::RelaySigned { payload: payload.clone(), signature: sign(&other_key, &payload), pubkey: Binary::from(other_key.verify_key().to_bytes().as_slice()) };
+        let err = execute(deps.as_mut(), mock_env(), info, msg).unwrap_err();
+        assert!(matches!(err, ContractError::Unauthorized {}));
+
+        // a payload signed for another deployment
+        let foreign = to_binary(&SignedRelayPayload { chain_id: String::from("other-chain"), ..signed(1, 3000, 100) }).unwrap();
+        let info = mock_info("anyone", &[]);
+        let msg = ExecuteMsg::RelaySigned { payload: foreign.clone(), signature: sign(&signing_key, &foreign), pubkey: pubkey.clone() };
+        let err = execute(deps.as_mut(), mock_env(), info, msg).unwrap_err();
+        assert!(matches!(err, ContractError::WrongSignatureDomain {}));
+
+        let res = query(deps.as_ref(), mock_env(), QueryMsg::GetSignerNonce { pubkey: pubkey.clone() }).unwrap();
+        assert_eq!(None, from_binary::<Option<u64>>(&res).unwrap());
+        let info = mock_info("anyone", &[]);
+        let msg = ExecuteMsg::RelaySigned { payload: payload.clone(), signature: signature.clone(), pubkey: pubkey.clone() };
+        let _res = execute(deps.as_mut(), mock_env(), info, msg.clone()).unwrap();
+        let res = query(deps.as_ref(), mock_env(), QueryMsg::GetRef { symbol: String::from("ETH") }).unwrap();
+        let value: RefData = from_binary(&res).unwrap();
+        assert_eq!(RefData { rate: 3000u64, resolve_time: 100u64, request_id: 1u64, std_dev: None, updated_by: Addr::unchecked(pubkey.to_base64()), decimals: 9, negative: false, relay_seq: 1 }, value);
+        let res = query(deps.as_ref(), mock_env(), QueryMsg::GetSignerNonce { pubkey: pubkey.clone() }).unwrap();
+        assert_eq!(Some(1u64), from_binary::<Option<u64>>(&res).unwrap());
+
+        // replaying the payload fails even once its symbol has been removed
+        let info = mock_info("creator", &[]);
+        let _res = execute(deps.as_mut(), mock_env(), info, ExecuteMsg::RemoveSymbol { symbols: vec![String::from("ETH")] }).unwrap();
+        let err = execute(deps.as_mut(), mock_env(), mock_info("anyone", &[]), msg).unwrap_err();
+        assert!(matches!(err, ContractError::NonceAlreadyUsed { nonce: 1, last: 1 }));
+        assert!(REFS.may_load(&deps.storage, "ETH").unwrap().is_none());
+
+        // skipped nonces are fine, going back is not
+        let newer = to_binary(&signed(5, 3100, 200)).unwrap();
+        let msg = ExecuteMsg::RelaySigned { payload: newer.clone(), signature: sign(&signing_key, &newer), pubkey: pubkey.clone() };
+        let _res = execute(deps.as_mut(), mock_env(), mock_info("anyone", &[]), msg).unwrap();
+        assert_eq!(3100u64, REFS.load(&deps.storage, "ETH").unwrap().rate);
+        let older = to_binary(&signed(4, 3050, 300)).unwrap();
+        let msg = ExecuteMsg::RelaySigned { payload: older.clone(), signature: sign(&signing_key, &older), pubkey: pubkey.clone() };
+        let err = execute(deps.as_mut(), mock_env(), mock_info("anyone", &[]), msg).unwrap_err();
+        assert!(matches!(err, ContractError::NonceAlreadyUsed { nonce: 4, last: 5 }));
+
+        // only the owner can revoke a key, and a revoked key's payloads are refused
+        let msg = ExecuteMsg::RemoveRelayerPubkey { pubkey: pubkey.clone() };
+        let err = execute(deps.as_mut(), mock_env(), mock_info("anyone", &[]), msg.clone()).unwrap_err();
+        assert!(matches!(err, ContractError::Unauthorized {}));
+        let res = execute(deps.as_mut(), mock_env(), mock_info("creator", &[]), msg).unwrap();
+        assert_eq!(vec![attr("action", "remove_relayer_pubkey"), attr("pubkey", pubkey.to_base64())], res.attributes);
+        let revoked = to_binary(&signed(6, 3150, 400)).unwrap();
+        let msg = ExecuteMsg::RelaySigned { payload: revoked.clone(), signature: sign(&signing_key, &revoked), pubkey };
+        let err = execute(deps.as_mut(), mock_env(), mock_info("anyone", &[]), msg).unwrap_err();
+        assert!(matches!(err, ContractError::Unauthorized {}));
+        assert_eq!(3100u64, REFS.load(&deps.storage, "ETH").unwrap().rate);
+    }
+
+    #[test]
+    fn query_symbols_by_request_id() {
+        let mut deps = mock_dependencies(&[]);
+
+        let msg = InstantiateMsg::default();
+        let info = mock_info("creator", &[]);
+        let _res = instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+        let info = mock_info("creator", &[]);
+        let msg = ExecuteMsg::Relay { symbols: vec![String::from("MATIC"), String::from("BAND"), String::from("ETH"), String::from("ATOM")], rates: vec![1u64, 2u64, 3u64, 4u64], resolve_times: vec![100u64, 100u64, 100u64, 100u64], request_ids: vec![1u64, 2u64, 1u64, 1u64] };
+        let _res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+        // a newer value moves ETH to another request
+        let info = mock_info("creator", &[]);
+        let msg = ExecuteMsg::Relay { symbols: vec![String::from("ETH")], rates: vec![5u64], resolve_times: vec![200u64], request_ids: vec![3u64] };
+        let _res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+        let by_request_id = |request_id: u64, start_after: Option<&str>, limit: Option<u32>| -> SymbolsPageResponse {
+            let msg = QueryMsg::GetSymbolsByRequestId { request_id, start_after: start_after.map(String::from), limit };
+            from_binary(&query(deps.as_ref(), mock_env(), msg).unwrap()).unwrap()
+        };
+        assert_eq!(SymbolsPageResponse { symbols: vec![String::from("ATOM"), String::from("MATIC")], next_start_after: None }, by_request_id(1, None, None));
+        // a page bounds the refs scanned, not the matches
+        assert_eq!(SymbolsPageResponse { symbols: vec![String::from("ATOM")], next_start_after: Some(String::from("BAND")) }, by_request_id(1, None, Some(2)));
+        assert_eq!(SymbolsPageResponse { symbols: vec![String::from("MATIC")], next_start_after: Some(String::from("MATIC")) }, by_request_id(1, Some("BAND"), Some(2)));
+        assert_eq!(SymbolsPageResponse { symbols: vec![], next_start_after: None }, by_request_id(1, Some("MATIC"), Some(2)));
+        assert_eq!(vec!["ETH"], by_request_id(3, None, None).symbols);
+        assert!(by_request_id(4, None, None).symbols.is_empty());
+    }
+
+    #[test]
+    fn update_config_partial() {
+        let mut deps = mock_dependencies(&[]);
+
+        let msg = InstantiateMsg::default();
+        let info = mock_info("creator", &[]);
+        let _res = instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+        let info = mock_info("creator", &[]);
+        let msg = ExecuteMsg::UpdateConfig { owner: None, max_staleness_secs: Some(Some(60u64)), max_future_skew_secs: Some(Some(5u64)), max_rate_change_bps: None, base_decimals: None, usd_decimals: None, max_batch_size: Some(10u32), min_relay_interval_secs: None, allow_signed_rates: None, rounding: None, strict_request_id: None, usd_timestamp_mode: None, twap_samples: None };
+        let _res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+        // omitted fields are kept, an explicit null clears
+        let msg: ExecuteMsg = from_slice(br#"{"update_config":{"max_future_skew_secs":null,"base_decimals":9}}"#).unwrap();
+        assert_eq!(ExecuteMsg::UpdateConfig { owner: None, max_staleness_secs: None, max_future_skew_secs: Some(None), max_rate_change_bps: None, base_decimals: Some(9u32), usd_decimals: None, max_batch_size: None, min_relay_interval_secs: None, allow_signed_rates: None, rounding: None, strict_request_id: None, usd_timestamp_mode: None, twap_samples: None }, msg);
+        let info = mock_info("creator", &[]);
+        let _res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+        let res = query(deps.as_ref(), mock_env(), QueryMsg::GetConfig {}).unwrap();
+        let value: FullConfigResponse = from_binary(&res).unwrap();
+        assert_eq!(Some(Addr::unchecked("creator")), value.owner);
+        assert_eq!(Some(60u64), value.max_staleness_secs);
+        assert_eq!(None, value.max_future_skew_secs);
+        assert_eq!(9u32, value.base_decimals);
+        assert_eq!(9u32, value.usd_decimals);
+        assert_eq!(10u32, value.max_batch_size);
+
+        let info = mock_info("creator", &[]);
+        let msg = ExecuteMsg::TransferOwnership { new_owner: String::from("owner2") };
+        let _res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+        let info = mock_info("creator", &[]);
+        let msg = ExecuteMsg::UpdateConfig { owner: Some(String::from("owner3")), max_staleness_secs: None, max_future_skew_secs: None, max_rate_change_bps: None, base_decimals: None, usd_decimals: None, max_batch_size: None, min_relay_interval_secs: None, allow_signed_rates: None, rounding: None, strict_request_id: None, usd_timestamp_mode: None, twap_samples: None };
+        let _res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+        let res = query(deps.as_ref(), mock_env(), QueryMsg::GetConfig {}).unwrap();
+        let value: FullConfigResponse = from_binary(&res).unwrap();
+        assert_eq!(Some(Addr::unchecked("owner3")), value.owner);
+        assert_eq!(None, value.pending_owner);
+        assert_eq!(Some(60u64), value.max_staleness_secs);
+        assert_eq!(10u32, value.max_batch_size);
+    }
+
+    #[test]
+    fn min_relay_interval() {
+        let mut deps = mock_dependencies(&[]);
+
+        let msg = InstantiateMsg::default();
+        let info = mock_info("creator", &[]);
+        let _res = instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+        let info = mock_info("creator", &[]);
+        let msg = ExecuteMsg::UpdateConfig { owner: None, max_staleness_secs: None, max_future_skew_secs: None, max_rate_change_bps: None, base_decimals: None, usd_decimals: None, max_batch_size: None, min_relay_interval_secs: Some(Some(60u64)), allow_signed_rates: None, rounding: None, strict_request_id: None, usd_timestamp_mode: None, twap_samples: None };
+        let _res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+        let relay = |rate: u64, resolve_time: u64| ExecuteMsg::Relay { symbols: vec![String::from("ETH")], rates: vec![rate], resolve_times: vec![resolve_time], request_ids: vec![1u64] };
+        let info = mock_info("creator", &[]);
+        let _res = execute(deps.as_mut(), mock_env(), info, relay(1u64, 100u64)).unwrap();
+
+        let mut env = mock_env();
+        env.block.time = env.block.time.plus_seconds(59);
+        let info = mock_info("creator", &[]);
+        let err = execute(deps.as_mut(), env.clone(), info, relay(2u64, 200u64)).unwrap_err();
+        match err {
+            ContractError::RelayTooSoon { symbol } => assert_eq!(String::from("ETH"), symbol),
+            err => panic!("unexpected error: {:?}", err),
+        }
+
+        // identical data and ForceRelay are not throttled
+        let info = mock_info("creator", &[]);
+        let _res = execute(deps.as_mut(), env.clone(), info, relay(1u64, 100u64)).unwrap();
+        let info = mock_info("creator", &[]);
+        let msg = ExecuteMsg::ForceRelay { symbols: vec![String::from("ETH")], rates: vec![3u64], resolve_times: vec![300u64], request_ids: vec![1u64] };
+        let _res = execute(deps.as_mut(), env.clone(), info, msg).unwrap();
+
+        env.block.time = env.block.time.plus_seconds(60);
+        let info = mock_info("creator", &[]);
+        let _res = execute(deps.as_mut(), env.clone(), info, relay(4u64, 400u64)).unwrap();
+        let res = query(deps.as_ref(), env, QueryMsg::GetRef { symbol: String::from("ETH") }).unwrap();
+        let value: RefData = from_binary(&res).unwrap();
+        assert_eq!(4u64, value.rate);
+    }
+
+    #[test]
+    fn query_reference_data_split() {
+        let mut deps = mock_dependencies(&[]);
+
+        let msg = InstantiateMsg::default();
+        let info = mock_info("creator", &[]);
+        let _res = instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+        let info = mock_info("creator", &[]);
+        let msg = ExecuteMsg::Relay { symbols: vec![String::from("ETH"), String::from("BAND")], rates: vec![3001250000000u64, 7000000000u64], resolve_times: vec![100u64, 100u64], request_ids: vec![1u64, 1u64] };
+        let _res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+        for (base, quote) in [("ETH", "USD"), ("ETH", "BAND"), ("BAND", "ETH"), ("USD", "USD")] {
+            let res = query(deps.as_ref(), mock_env(), QueryMsg::GetReferenceDataSplit { base: String::from(base), quote: String::from(quote) }).unwrap();
+            let split: ReferenceDataSplit = from_binary(&res).unwrap();
+            let data = query_reference_data(deps.as_ref(), mock_env(), String::from(base), String::from(quote), None).unwrap();
+            assert_eq!(18u32, split.decimals);
+            assert!(split.fraction.u128() < E18);
+            assert_eq!(data.rate.u128(), split.integer.u128() * E18 + split.fraction.u128());
+        }
+
+        let res = query(deps.as_ref(), mock_env(), QueryMsg::GetReferenceDataSplit { base: String::from("ETH"), quote: String::from("USD") }).unwrap();
+        let split: ReferenceDataSplit = from_binary(&res).unwrap();
+        assert_eq!(ReferenceDataSplit { integer: Uint128::from(3001u128), fraction: Uint128::from(250000000000000000u128), decimals: 18 }, split);
+    }
+
+    #[test]
+    fn query_reference_data_same_symbol() {
+        let mut deps = mock_dependencies(&[]);
+
+        let msg = InstantiateMsg::default();
+        let info = mock_info("creator", &[]);
+        let _res = instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+        let info = mock_info("creator", &[]);
+        let msg = ExecuteMsg::Relay { symbols: vec![String::from("ETH"), String::from("ZERO")], rates: vec![3333333333333u64, 0u64], resolve_times: vec![100u64, 200u64], request_ids: vec![1u64, 2u64] };
+        let _res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+        let value = query_reference_data(deps.as_ref(), mock_env(), String::from("ETH"), String::from("ETH"), None).unwrap();
+        assert_eq!(ReferenceData { rate: Uint128::from(E18), last_updated_base: Uint128::from(100u64), last_updated_quote: Uint128::from(100u64) }, value);
+
+        let value = query_reference_data(deps.as_ref(), mock_env(), String::from("ZERO"), String::from("ZERO"), None).unwrap();
+        assert_eq!(ReferenceData { rate: Uint128::from(E18), last_updated_base: Uint128::from(200u64), last_updated_quote: Uint128::from(200u64) }, value);
+
+        let err = query_reference_data(deps.as_ref(), mock_env(), String::from("BAND"), String::from("BAND"), None).unwrap_err();
+        assert!(matches!(err, ContractError::SymbolNotFound { .. }));
+    }
+
+    #[test]
+    fn query_reference_data_try_bulk() {
+        let mut deps = mock_dependencies(&[]);
+
+        let msg = InstantiateMsg::default();
+        let info = mock_info("creator", &[]);
+        let _res = instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+        let info = mock_info("creator", &[]);
+        let msg = ExecuteMsg::Relay { symbols: vec![String::from("ETH"), String::from("BAND")], rates: vec![2000000000000u64, 5000000000u64], resolve_times: vec![100u64, 200u64], request_ids: vec![1u64, 2u64] };
+        let _res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+        let pairs = vec![(String::from("ETH"), String::from("BAND")), (String::from("ATOM"), String::from("USD")), (String::from("BAND"), String::from("USD"))];
+        let res = query(deps.as_ref(), mock_env(), QueryMsg::GetReferenceDataTryBulk { pairs: pairs.clone() }).unwrap();
+        let value: Vec<ReferenceDataResult> = from_binary(&res).unwrap();
+        assert_eq!(vec![
+            ReferenceDataResult { data: Some(ReferenceData { rate: Uint128::from(400000000000000000000u128), last_updated_base: Uint128::from(100u64), last_updated_quote: Uint128::from(200u64) }), error: None },
+            ReferenceDataResult { data: None, error: Some(String::from("Symbol not found: ATOM")) },
+            ReferenceDataResult { data: Some(ReferenceData { rate: Uint128::from(5000000000000000000u128), last_updated_base: Uint128::from(200u64), last_updated_quote: Uint128::from(mock_env().block.time.nanos()) }), error: None },
+        ], value);
+
+        // the strict version still fails as a whole
+        let err = query(deps.as_ref(), mock_env(), QueryMsg::GetReferenceDataBulk { pairs }).unwrap_err();
+        assert!(matches!(err, ContractError::SymbolNotFound { .. }));
+    }
+
+    #[test]
+    fn relay_with_decimals() {
+        let mut deps = mock_dependencies(&[]);
+
+        let msg = InstantiateMsg::default();
+        let info = mock_info("creator", &[]);
+        let _res = instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+        let info = mock_info("creator", &[]);
+        let msg = ExecuteMsg::RelayWithDecimals { symbols: vec![String::from("ETH")], rates: vec![1u64], resolve_times: vec![100u64], request_ids: vec![1u64], decimals: vec![] };
+        let err = execute(deps.as_mut(), mock_env(), info, msg).unwrap_err();
+        assert!(matches!(err, ContractError::DifferentArrayLength { decimals_len: Some(0), .. }));
+
+        let info = mock_info("creator", &[]);
+        let msg = ExecuteMsg::RelayWithDecimals { symbols: vec![String::from("ETH")], rates: vec![1u64], resolve_times: vec![100u64], request_ids: vec![1u64], decimals: vec![20u32] };
+        let err = execute(deps.as_mut(), mock_env(), info, msg).unwrap_err();
+        assert!(matches!(err, ContractError::InvalidDecimals {}));
+
+        // ETH at 3000 USD with 6 decimals, BAND at 5 USD with the default 9
+        let info = mock_info("creator", &[]);
+        let msg = ExecuteMsg::RelayWithDecimals { symbols: vec![String::from("ETH")], rates: vec![3000000000u64], resolve_times: vec![100u64], request_ids: vec![1u64], decimals: vec![6u32] };
+        let _res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+        let info = mock_info("creator", &[]);
+        let msg = ExecuteMsg::Relay { symbols: vec![String::from("BAND")], rates: vec![5000000000u64], resolve_times: vec![100u64], request_ids: vec![1u64] };
+        let _res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+        let res = query(deps.as_ref(), mock_env(), QueryMsg::GetRef { symbol: String::from("ETH") }).unwrap();
+        let value: RefData = from_binary(&res).unwrap();
+        assert_eq!(6u32, value.decimals);
+        let res = query(deps.as_ref(), mock_env(), QueryMsg::GetRef { symbol: String::from("BAND") }).unwrap();
+        let value: RefData = from_binary(&res).unwrap();
+        assert_eq!(9u32, value.decimals);
+
+        let value = query_reference_data(deps.as_ref(), mock_env(), String::from("ETH"), String::from("BAND"), None).unwrap();
+        assert_eq!(Uint128::from(600u128 * E18), value.rate);
+        let value = query_reference_data(deps.as_ref(), mock_env(), String::from("BAND"), String::from("ETH"), None).unwrap();
+        assert_eq!(Uint128::from(E18 / 600), value.rate);
+        let value = query_reference_data(deps.as_ref(), mock_env(), String::from("ETH"), String::from("USD"), None).unwrap();
+        assert_eq!(Uint128::from(3000u128 * E18), value.rate);
+    }
+
+    #[test]
+    fn migrate_refs_decimals() {
+        let mut deps = mock_dependencies(&[]);
+
+        let msg = InstantiateMsg { usd_decimals: Some(6), ..InstantiateMsg::default() };
+        let info = mock_info("creator", &[]);
+        let _res = instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+        // a 0.5.0 entry was relayed at the configured usd_decimals
+        deps.storage.set(&REFS.key("ETH"), br#"{"rate":3000000000,"resolve_time":2,"request_id":3,"std_dev":null,"updated_by":"creator"}"#);
+        set_contract_version(deps.as_mut().storage, CONTRACT_NAME, "0.5.0").unwrap();
+
+        let _res = migrate(deps.as_mut(), mock_env(), MigrateMsg::default()).unwrap();
+
+        let res = query(deps.as_ref(), mock_env(), QueryMsg::GetRef { symbol: String::from("ETH") }).unwrap();
+        let value: RefData = from_binary(&res).unwrap();
+        assert_eq!(6u32, value.decimals);
+        let value = query_reference_data(deps.as_ref(), mock_env(), String::from("ETH"), String::from("USD"), None).unwrap();
+        assert_eq!(Uint128::from(3000u128 * E18), value.rate);
+    }
+
+    #[test]
+    fn migrate_resolve_time_index() {
+        let mut deps = mock_dependencies(&[]);
+
+        let msg = InstantiateMsg::default();
+        let info = mock_info("creator", &[]);
+        let _res = instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+        // schema 6 stored refs without the index
+        deps.storage.set(&REFS.key("ETH"), br#"{"rate":1,"resolve_time":300,"request_id":3,"std_dev":null,"updated_by":"creator","decimals":9}"#);
+        deps.storage.set(&REFS.key("BAND"), br#"{"rate":2,"resolve_time":100,"request_id":3,"std_dev":null,"updated_by":"creator","decimals":9}"#);
+        schema_version(deps.as_mut().storage).save(&6).unwrap();
+
+        let _res = migrate(deps.as_mut(), mock_env(), MigrateMsg::default()).unwrap();
+
+        let index = RESOLVE_TIMES.range(&deps.storage, None, None, Order::Ascending).map(|item| item.unwrap().1).collect::<Vec<String>>();
+        assert_eq!(vec!["BAND", "ETH"], index);
+        assert_eq!(Some((String::from("BAND"), 100u64)), super::query_oldest_resolve_time(deps.as_ref()).unwrap());
+    }
+
+    #[test]
+    fn query_refs_by_symbols() {
+        let mut deps = mock_dependencies(&[]);
+
+        let msg = InstantiateMsg::default();
+        let info = mock_info("creator", &[]);
+        let _res = instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+        let info = mock_info("creator", &[]);
+        let msg = ExecuteMsg::Relay { symbols: vec![String::from("ETH"), String::from("BAND")], rates: vec![1u64, 100u64], resolve_times: vec![2u64, 200u64], request_ids: vec![3u64, 300u64] };
+        let _res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+        let msg = QueryMsg::GetRefs { symbols: vec![String::from("ETH"), String::from("MISSING"), String::from("BAND")] };
+        let res = query(deps.as_ref(), mock_env(), msg).unwrap();
+        let value: Vec<Option<RefData>> = from_binary(&res).unwrap();
+        assert_eq!(vec![
+            Some(RefData{rate: 1u64, resolve_time: 2u64, request_id: 3u64, std_dev: None, updated_by: Addr::unchecked("creator"), decimals: 9, negative: false, relay_seq: 1}),
+            None,
+            Some(RefData{rate: 100u64, resolve_time: 200u64, request_id: 300u64, std_dev: None, updated_by: Addr::unchecked("creator"), decimals: 9, negative: false, relay_seq: 1}),
+        ], value);
+    }
+
+    #[test]
+    fn relay_rejects_whole_batch() {
+        let mut deps = mock_dependencies(&[]);
+
+        let msg = InstantiateMsg::default();
+        let info = mock_info("creator", &[]);
+        let _res = instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+        let info = mock_info("creator", &[]);
+        let msg = ExecuteMsg::Relay { symbols: vec![String::from("BAND")], rates: vec![100u64], resolve_times: vec![200u64], request_ids: vec![1u64] };
+        let _res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+        let info = mock_info("creator", &[]);
+        let msg = ExecuteMsg::UpdateConfig { owner: None, max_staleness_secs: None, max_future_skew_secs: None, max_rate_change_bps: Some(Some(500u16)), base_decimals: None, usd_decimals: None, max_batch_size: None, min_relay_interval_secs: None, allow_signed_rates: None, rounding: None, strict_request_id: None, usd_timestamp_mode: None, twap_samples: None };
+        let _res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+        // each batch is valid except for its last entry, which fails a different check
+        let symbols = |last: &str| (0..9).map(|idx| format!("S{}", idx)).chain(vec![String::from(last)]).collect::<Vec<String>>();
+        let batches = vec![
+            ExecuteMsg::Relay { symbols: symbols("eth"), rates: vec![1u64; 10], resolve_times: vec![100u64; 10], request_ids: vec![1u64; 10] },
+            ExecuteMsg::RelayStrict { symbols: symbols("BAND"), rates: vec![100u64; 10], resolve_times: vec![100u64; 10], request_ids: vec![1u64; 10] },
+            ExecuteMsg::Relay { symbols: symbols("BAND"), rates: vec![1u64; 10], resolve_times: vec![300u64; 10], request_ids: vec![1u64; 10] },
+            ExecuteMsg::RelayWithDecimals { symbols: symbols("ETH"), rates: vec![1u64; 10], resolve_times: vec![100u64; 10], request_ids: vec![1u64; 10], decimals: [vec![9u32; 9], vec![20u32]].concat() },
+        ];
+        for msg in batches {
+            let info = mock_info("creator", &[]);
+            let _err = execute(deps.as_mut(), mock_env(), info, msg).unwrap_err();
+            let res = query(deps.as_ref(), mock_env(), QueryMsg::GetSymbols { start_after: None, limit: None }).unwrap();
+            let value: Vec<String> = from_binary(&res).unwrap();
+            assert_eq!(vec!["BAND"], value);
+            let res = query(deps.as_ref(), mock_env(), QueryMsg::GetSymbolCount {}).unwrap();
+            assert_eq!(1u64, from_binary::<u64>(&res).unwrap());
+        }
+        let res = query(deps.as_ref(), mock_env(), QueryMsg::GetRef { symbol: String::from("BAND") }).unwrap();
+        let value: RefData = from_binary(&res).unwrap();
+        assert_eq!(100u64, value.rate);
+    }
+
+    #[test]
+    fn renounce_ownership() {
+        let mut deps = mock_dependencies(&[]);
+
+        let msg = InstantiateMsg::default();
+        let info = mock_info("creator", &[]);
+        let _res = instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+        let info = mock_info("creator", &[]);
+        let msg = ExecuteMsg::AddRelayer { address: String::from("relayer") };
+        let _res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+        let info = mock_info("creator", &[]);
+        let msg = ExecuteMsg::TransferOwnership { new_owner: String::from("bob") };
+        let _res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+        let info = mock_info("relayer", &[]);
+        let err = execute(deps.as_mut(), mock_env(), info, ExecuteMsg::RenounceOwnership {}).unwrap_err();
+        assert!(matches!(err, ContractError::Unauthorized {}));
+
+        let info = mock_info("creator", &[]);
+        let res = execute(deps.as_mut(), mock_env(), info, ExecuteMsg::RenounceOwnership {}).unwrap();
+        assert_eq!(vec![attr("action", "renounce_ownership")], res.attributes);
+
+        let res = query(deps.as_ref(), mock_env(), QueryMsg::GetOwnership {}).unwrap();
+        let value: OwnershipResponse = from_binary(&res).unwrap();
+        assert_eq!(OwnershipResponse { owner: None, pending_owner: None }, value);
+
+        let msgs = vec![
+            (mock_info("creator", &[]), ExecuteMsg::AddRelayer { address: String::from("relayer2") }),
+            (mock_info("creator", &[]), ExecuteMsg::Pause {}),
+            (mock_info("creator", &[]), ExecuteMsg::RenounceOwnership {}),
+            (mock_info("creator", &[]), ExecuteMsg::Relay { symbols: vec![String::from("ETH")], rates: vec![1u64], resolve_times: vec![100u64], request_ids: vec![1u64] }),
+            (mock_info("bob", &[]), ExecuteMsg::AcceptOwnership {}),
+        ];
+        for (info, msg) in msgs {
+            let err = execute(deps.as_mut(), mock_env(), info, msg).unwrap_err();
+            assert!(matches!(err, ContractError::Unauthorized {}));
+        }
+
+        let info = mock_info("relayer", &[]);
+        let msg = ExecuteMsg::Relay { symbols: vec![String::from("ETH")], rates: vec![1u64], resolve_times: vec![100u64], request_ids: vec![1u64] };
+        let _res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+        let res = query(deps.as_ref(), mock_env(), QueryMsg::GetRef { symbol: String::from("ETH") }).unwrap();
+        let value: RefData = from_binary(&res).unwrap();
+        assert_eq!(Addr::unchecked("relayer"), value.updated_by);
+    }
+
+    #[test]
+    fn query_health() {
+        let mut deps = mock_dependencies(&[]);
+
+        let msg = InstantiateMsg::default();
+        let info = mock_info("creator", &[]);
+        let _res = instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+        let res = query(deps.as_ref(), mock_env(), QueryMsg::Health {}).unwrap();
+        let value: HealthResponse = from_binary(&res).unwrap();
+        assert_eq!(HealthResponse { paused: false, symbol_count: 0, last_relay_time: 0, oldest_resolve_time: None }, value);
+
+        let info = mock_info("creator", &[]);
+        let msg = ExecuteMsg::Relay { symbols: vec![String::from("ETH"), String::from("BAND")], rates: vec![1u64, 2u64], resolve_times: vec![300u64, 100u64], request_ids: vec![1u64, 1u64] };
+        let _res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+        let info = mock_info("creator", &[]);
+        let _res = execute(deps.as_mut(), mock_env(), info, ExecuteMsg::Pause {}).unwrap();
+
+        let res = query(deps.as_ref(), mock_env(), QueryMsg::Health {}).unwrap();
+        let value: HealthResponse = from_binary(&res).unwrap();
+        assert_eq!(HealthResponse { paused: true, symbol_count: 2, last_relay_time: mock_env().block.time.nanos(), oldest_resolve_time: Some(100u64) }, value);
+
+        // the oldest resolve time is read from the index, so it follows removals without a scan
+        let info = mock_info("creator", &[]);
+        let _res = execute(deps.as_mut(), mock_env(), info, ExecuteMsg::Unpause {}).unwrap();
+        let info = mock_info("creator", &[]);
+        let _res = execute(deps.as_mut(), mock_env(), info, ExecuteMsg::RemoveSymbol { symbols: vec![String::from("BAND")] }).unwrap();
+        let res = query(deps.as_ref(), mock_env(), QueryMsg::Health {}).unwrap();
+        let value: HealthResponse = from_binary(&res).unwrap();
+        assert_eq!((1u64, Some(300u64)), (value.symbol_count, value.oldest_resolve_time));
+    }
+
+    #[test]
+    fn relay_signed_rates() {
+        let mut deps = mock_dependencies(&[]);
+
+        let msg = InstantiateMsg::default();
+        let info = mock_info("creator", &[]);
+        let _res = instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+        // funding rate of -0.0005 and basis of +2 at 9 decimals
+        let relay = || ExecuteMsg::RelaySignedRates { symbols: vec![String::from("FUNDING"), String::from("BASIS")], rates: vec![-500000i64, 2000000000i64], resolve_times: vec![100u64, 100u64], request_ids: vec![1u64, 1u64] };
+        let info = mock_info("creator", &[]);
+        let err = execute(deps.as_mut(), mock_env(), info, relay()).unwrap_err();
+        assert!(matches!(err, ContractError::SignedRatesDisabled {}));
+
+        let info = mock_info("creator", &[]);
+        let msg = ExecuteMsg::UpdateConfig { owner: None, max_staleness_secs: None, max_future_skew_secs: None, max_rate_change_bps: None, base_decimals: None, usd_decimals: None, max_batch_size: None, min_relay_interval_secs: None, allow_signed_rates: Some(true), rounding: None, strict_request_id: None, usd_timestamp_mode: None, twap_samples: None };
+        let _res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+        let info = mock_info("creator", &[]);
+        let _res = execute(deps.as_mut(), mock_env(), info, relay()).unwrap();
+
+        let res = query(deps.as_ref(), mock_env(), QueryMsg::GetRef { symbol: String::from("FUNDING") }).unwrap();
+        let value: RefData = from_binary(&res).unwrap();
+        assert_eq!(RefData{rate: 500000u64, resolve_time: 100u64, request_id: 1u64, std_dev: None, updated_by: Addr::unchecked("creator"), decimals: 9, negative: true, relay_seq: 1}, value);
+        assert_eq!(-500000i128, value.signed_rate());
+
+        let signed = |base: &str, quote: &str| -> SignedReferenceData {
+            let msg = QueryMsg::GetReferenceDataSigned { base: String::from(base), quote: String::from(quote) };
+            from_binary(&query(deps.as_ref(), mock_env(), msg).unwrap()).unwrap()
+        };
+        let value = signed("FUNDING", "USD");
+        assert_eq!(SignedReferenceData { rate: Uint128::from(500000000000000u128), negative: true, last_updated_base: Uint128::from(100u64), last_updated_quote: Uint128::from(mock_env().block.time.nanos()) }, value);
+        let value = signed("FUNDING", "BASIS");
+        assert_eq!((Uint128::from(250000000000000u128), true), (value.rate, value.negative));
+        let value = signed("BASIS", "FUNDING");
+        assert_eq!((Uint128::from(4000u128 * E18), true), (value.rate, value.negative));
+        let value = signed("FUNDING", "FUNDING");
+        assert_eq!((Uint128::from(E18), false), (value.rate, value.negative));
+        let value = signed("BASIS", "USD");
+        assert_eq!((Uint128::from(2u128 * E18), false), (value.rate, value.negative));
+
+        // unsigned queries refuse a negative leg instead of dropping its sign
+        let err = query(deps.as_ref(), mock_env(), QueryMsg::GetReferenceData { base: String::from("FUNDING"), quote: String::from("USD"), decimals: None }).unwrap_err();
+        assert!(matches!(err, ContractError::NegativeRate { .. }));
+        let err = query(deps.as_ref(), mock_env(), QueryMsg::GetRawReferenceData { base: String::from("USD"), quote: String::from("FUNDING") }).unwrap_err();
+        assert!(matches!(err, ContractError::NegativeRate { .. }));
+        let _value = query_reference_data(deps.as_ref(), mock_env(), String::from("BASIS"), String::from("USD"), None).unwrap();
+    }
+
+    #[test]
+    fn query_inverse_reference_data() {
+        let mut deps = mock_dependencies(&[]);
+
+        let msg = InstantiateMsg::default();
+        let info = mock_info("creator", &[]);
+        let _res = instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+        let info = mock_info("creator", &[]);
+        let msg = ExecuteMsg::Relay { symbols: vec![String::from("ETH"), String::from("ZERO")], rates: vec![3000000000000u64, 0u64], resolve_times: vec![100u64, 100u64], request_ids: vec![1u64, 1u64] };
+        let _res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+        let res = query(deps.as_ref(), mock_env(), QueryMsg::GetInverseReferenceData { base: String::from("ETH"), quote: String::from("USD") }).unwrap();
+        let inverse: ReferenceData = from_binary(&res).unwrap();
+        let res = query(deps.as_ref(), mock_env(), QueryMsg::GetReferenceData { base: String::from("USD"), quote: String::from("ETH"), decimals: None }).unwrap();
+        let swapped: ReferenceData = from_binary(&res).unwrap();
+        assert_eq!(swapped, inverse);
+        assert_eq!(Uint128::from(333333333333333u128), inverse.rate);
+
+        let err = query(deps.as_ref(), mock_env(), QueryMsg::GetInverseReferenceData { base: String::from("ZERO"), quote: String::from("USD") }).unwrap_err();
+        assert!(matches!(err, ContractError::DivideByZero {}));
+    }
+
+    #[test]
+    fn relay_seq() {
+        let mut deps = mock_dependencies(&[]);
+
+        let msg = InstantiateMsg::default();
+        let info = mock_info("creator", &[]);
+        let _res = instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+        let relays = vec![
+            ExecuteMsg::Relay { symbols: vec![String::from("ETH"), String::from("BAND")], rates: vec![1u64, 2u64], resolve_times: vec![100u64, 100u64], request_ids: vec![1u64, 1u64] },
+            ExecuteMsg::Relay { symbols: vec![String::from("BAND")], rates: vec![3u64], resolve_times: vec![200u64], request_ids: vec![2u64] },
+            ExecuteMsg::Relay { symbols: vec![String::from("ATOM")], rates: vec![4u64], resolve_times: vec![300u64], request_ids: vec![3u64] },
+        ];
+        for (idx, msg) in relays.into_iter().enumerate() {
+            let info = mock_info("creator", &[]);
+            let _res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+            let res = query(deps.as_ref(), mock_env(), QueryMsg::GetLastRelay {}).unwrap();
+            let value: LastRelayResponse = from_binary(&res).unwrap();
+            assert_eq!(idx as u64 + 1, value.relay_seq);
+        }
+
+        let seq = |deps: Deps, symbol: &str| {
+            let res = query(deps, mock_env(), QueryMsg::GetRef { symbol: String::from(symbol) }).unwrap();
+            from_binary::<RefData>(&res).unwrap().relay_seq
+        };
+        assert_eq!(1u64, seq(deps.as_ref(), "ETH"));
+        assert_eq!(2u64, seq(deps.as_ref(), "BAND"));
+        assert_eq!(3u64, seq(deps.as_ref(), "ATOM"));
+
+        // an identical resubmission still counts as a relay but keeps the stored seq
+        let info = mock_info("creator", &[]);
+        let msg = ExecuteMsg::Relay { symbols: vec![String::from("ETH")], rates: vec![1u64], resolve_times: vec![100u64], request_ids: vec![1u64] };
+        let _res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+        assert_eq!(1u64, seq(deps.as_ref(), "ETH"));
+        let res = query(deps.as_ref(), mock_env(), QueryMsg::GetLastRelay {}).unwrap();
+        assert_eq!(4u64, from_binary::<LastRelayResponse>(&res).unwrap().relay_seq);
+    }
+
+    #[test]
+    fn rounding_mode() {
+        let mut deps = mock_dependencies(&[]);
+
+        let msg = InstantiateMsg::default();
+        let info = mock_info("creator", &[]);
+        let _res = instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+        let info = mock_info("creator", &[]);
+        let msg = ExecuteMsg::Relay { symbols: vec![String::from("ONE"), String::from("TWO"), String::from("THREE")], rates: vec![1000000000u64, 2000000000u64, 3000000000u64], resolve_times: vec![100u64, 100u64, 100u64], request_ids: vec![1u64, 1u64, 1u64] };
+        let _res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+        let cases = vec![
+            (RoundingMode::Trunc, 333333333333333333u128, 666666666666666666u128),
+            (RoundingMode::Nearest, 333333333333333333u128, 666666666666666667u128),
+            (RoundingMode::Ceil, 333333333333333334u128, 666666666666666667u128),
+        ];
+        for (rounding, one_third, two_thirds) in cases {
+            let info = mock_info("creator", &[]);
+            let msg = ExecuteMsg::UpdateConfig { owner: None, max_staleness_secs: None, max_future_skew_secs: None, max_rate_change_bps: None, base_decimals: None, usd_decimals: None, max_batch_size: None, min_relay_interval_secs: None, allow_signed_rates: None, rounding: Some(rounding), strict_request_id: None, usd_timestamp_mode: None, twap_samples: None };
+            let _res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+            let value = query_reference_data(deps.as_ref(), mock_env(), String::from("ONE"), String::from("THREE"), None).unwrap();
+            assert_eq!(Uint128::from(one_third), value.rate);
+            let value = query_reference_data(deps.as_ref(), mock_env(), String::from("TWO"), String::from("THREE"), None).unwrap();
+            assert_eq!(Uint128::from(two_thirds), value.rate);
+            // exact results are the same in every mode
+            let value = query_reference_data(deps.as_ref(), mock_env(), String::from("THREE"), String::from("ONE"), None).unwrap();
+            assert_eq!(Uint128::from(3u128 * E18), value.rate);
+        }
+    }
+
+    #[test]
+    fn relay_v2_matches_relay() {
+        let mut arrays = mock_dependencies(&[]);
+        let mut entries = mock_dependencies(&[]);
+        for deps in [&mut arrays, &mut entries] {
+            let msg = InstantiateMsg::default();
+            let info = mock_info("creator", &[]);
+            let _res = instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+        }
+
+        let info = mock_info("creator", &[]);
+        let msg = ExecuteMsg::Relay { symbols: vec![String::from("ETH"), String::from("BTC")], rates: vec![3000000000000u64, 50000000000000u64], resolve_times: vec![100u64, 200u64], request_ids: vec![1u64, 2u64] };
+        let _res = execute(arrays.as_mut(), mock_env(), info, msg).unwrap();
+
+        let info = mock_info("creator", &[]);
+        let msg = ExecuteMsg::RelayV2 { entries: vec![
+            RelayEntry { symbol: String::from("ETH"), rate: 3000000000000u64, resolve_time: 100u64, request_id: 1u64 },
+            RelayEntry { symbol: String::from("BTC"), rate: 50000000000000u64, resolve_time: 200u64, request_id: 2u64 },
+        ] };
+        let _res = execute(entries.as_mut(), mock_env(), info, msg).unwrap();
+
+        let expected = query(arrays.as_ref(), mock_env(), QueryMsg::GetAllRefs {}).unwrap();
+        let value = query(entries.as_ref(), mock_env(), QueryMsg::GetAllRefs {}).unwrap();
+        assert_eq!(expected, value);
+        let value: ConfigResponse = from_binary(&value).unwrap();
+        assert_eq!(2, value.refs.len());
+
+        // validation is shared with Relay
+        let info = mock_info("creator", &[]);
+        let msg = ExecuteMsg::RelayV2 { entries: vec![] };
+        let err = execute(entries.as_mut(), mock_env(), info, msg).unwrap_err();
+        let info = mock_info("creator", &[]);
+        let msg = ExecuteMsg::Relay { symbols: vec![], rates: vec![], resolve_times: vec![], request_ids: vec![] };
+        assert_eq!(execute(arrays.as_mut(), mock_env(), info, msg).unwrap_err().to_string(), err.to_string());
+    }
+
+    #[test]
+    fn query_reference_data_scaled() {
+        let mut deps = mock_dependencies(&[]);
+
+        let msg = InstantiateMsg::default();
+        let info = mock_info("creator", &[]);
+        let _res = instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+        let info = mock_info("creator", &[]);
+        let msg = ExecuteMsg::Relay { symbols: vec![String::from("ETH"), String::from("BTC")], rates: vec![3001234567891u64, 45678912345678u64], resolve_times: vec![100u64, 100u64], request_ids: vec![1u64, 1u64] };
+        let _res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+        let scaled = |base: &str, quote: &str, result_decimals: u32| {
+            let msg = QueryMsg::GetReferenceDataScaled { base: String::from(base), quote: String::from(quote), result_decimals };
+            query(deps.as_ref(), mock_env(), msg).map(|res| from_binary::<ReferenceData>(&res).unwrap())
+        };
+        for (base, quote) in [("ETH", "USD"), ("ETH", "BTC"), ("BTC", "ETH"), ("USD", "BTC")] {
+            let full = query_reference_data(deps.as_ref(), mock_env(), String::from(base), String::from(quote), None).unwrap();
+            let value = scaled(base, quote, 6).unwrap();
+            assert_eq!(full.rate.u128() / 1000000000000u128, value.rate.u128());
+            assert_eq!(full.last_updated_base, value.last_updated_base);
+            assert_eq!(full, scaled(base, quote, 18).unwrap());
+        }
+        assert_eq!(Uint128::from(3001u128), scaled("ETH", "USD", 0).unwrap().rate);
+
+        assert!(scaled("ETH", "ETH", 38).is_ok());
+        let err = scaled("ETH", "USD", 39).unwrap_err();
+        assert_eq!(ContractError::InvalidDecimals {}.to_string(), err.to_string());
+    }
+
+    #[test]
+    fn symbol_aliases() {
+        let mut deps = mock_dependencies(&[]);
+
+        let msg = InstantiateMsg::default();
+        let info = mock_info("creator", &[]);
+        let _res = instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+        let info = mock_info("creator", &[]);
+        let msg = ExecuteMsg::Relay { symbols: vec![String::from("ETH"), String::from("BTC")], rates: vec![3000000000000u64, 50000000000000u64], resolve_times: vec![100u64, 100u64], request_ids: vec![1u64, 1u64] };
+        let _res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+        let set_alias = |deps: DepsMut, sender: &str, alias: &str, symbol: &str| {
+            let msg = ExecuteMsg::SetAlias { alias: String::from(alias), symbol: String::from(symbol) };
+            execute(deps, mock_env(), mock_info(sender, &[]), msg)
+        };
+        let err = set_alias(deps.as_mut(), "relayer1", "WETH", "ETH").unwrap_err();
+        assert!(matches!(err, ContractError::Unauthorized {}));
+        let res = set_alias(deps.as_mut(), "creator", "WETH", "ETH").unwrap();
+        assert_eq!(vec![attr("action", "set_alias"), attr("alias", "WETH"), attr("symbol", "ETH")], res.attributes);
+
+        let res = query(deps.as_ref(), mock_env(), QueryMsg::GetAlias { alias: String::from("WETH") }).unwrap();
+        assert_eq!(Some(String::from("ETH")), from_binary::<Option<String>>(&res).unwrap());
+        let res = query(deps.as_ref(), mock_env(), QueryMsg::GetAlias { alias: String::from("ETH") }).unwrap();
+        assert_eq!(None, from_binary::<Option<String>>(&res).unwrap());
+
+        let value = query_reference_data(deps.as_ref(), mock_env(), String::from("WETH"), String::from("USD"), None).unwrap();
+        assert_eq!(ReferenceData { rate: Uint128::from(3000u128 * E18), last_updated_base: Uint128::from(100u64), last_updated_quote: Uint128::from(mock_env().block.time.nanos()) }, value);
+        let value = query_reference_data(deps.as_ref(), mock_env(), String::from("BTC"), String::from("WETH"), None).unwrap();
+        assert_eq!(Uint128::from(E18 * 50 / 3), value.rate);
+
+        // missing targets, stored names and chains in either direction are rejected
+        let err = set_alias(deps.as_mut(), "creator", "WBTC", "BAND").unwrap_err();
+        assert!(matches!(err, ContractError::SymbolNotFound { symbol } if symbol == "BAND"));
+        let err = set_alias(deps.as_mut(), "creator", "BTC", "ETH").unwrap_err();
+        assert!(matches!(err, ContractError::InvalidAlias { alias } if alias == "BTC"));
+        let err = set_alias(deps.as_mut(), "creator", "ETH", "ETH").unwrap_err();
+        assert!(matches!(err, ContractError::InvalidAlias { .. }));
+        let err = set_alias(deps.as_mut(), "creator", "STETH", "WETH").unwrap_err();
+        assert!(matches!(err, ContractError::SymbolNotFound { .. }));
+        let err = set_alias(deps.as_mut(), "creator", "weth", "ETH").unwrap_err();
+        assert!(matches!(err, ContractError::InvalidSymbol { .. }));
+
+        let info = mock_info("creator", &[]);
+        let _res = execute(deps.as_mut(), mock_env(), info, ExecuteMsg::RemoveAlias { alias: String::from("WETH") }).unwrap();
+        let err = query_reference_data(deps.as_ref(), mock_env(), String::from("WETH"), String::from("USD"), None).unwrap_err();
+        assert!(matches!(err, ContractError::SymbolNotFound { symbol } if symbol == "WETH"));
+    }
+
+    #[test]
+    fn aliases_track_their_targets() {
+        let mut deps = mock_dependencies(&[]);
+
+        let msg = InstantiateMsg::default();
+        let info = mock_info("creator", &[]);
+        let _res = instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+        let info = mock_info("creator", &[]);
+        let msg = ExecuteMsg::Relay { symbols: vec![String::from("ETH"), String::from("BTC"), String::from("SOL")], rates: vec![3000000000000u64, 50000000000000u64, 150000000000u64], resolve_times: vec![100u64, 100u64, 100u64], request_ids: vec![1u64, 1u64, 1u64] };
+        let _res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+        for (alias, symbol) in [("WETH", "ETH"), ("WBTC", "BTC"), ("WSOL", "SOL")] {
+            let msg = ExecuteMsg::SetAlias { alias: String::from(alias), symbol: String::from(symbol) };
+            let _res = execute(deps.as_mut(), mock_env(), mock_info("creator", &[]), msg).unwrap();
+        }
+        let alias = |deps: Deps, alias: &str| from_binary::<Option<String>>(&query(deps, mock_env(), QueryMsg::GetAlias { alias: String::from(alias) }).unwrap()).unwrap();
+
+        // relaying under an alias name would hide the new feed behind the alias
+        let info = mock_info("creator", &[]);
+        let msg = ExecuteMsg::Relay { symbols: vec![String::from("BTC"), String::from("WETH")], rates: vec![51000000000000u64, 1u64], resolve_times: vec![200u64, 200u64], request_ids: vec![2u64, 2u64] };
+        let err = execute(deps.as_mut(), mock_env(), info, msg).unwrap_err();
+        assert!(matches!(err, ContractError::SymbolIsAlias { symbol } if symbol == "WETH"));
+        assert!(REFS.may_load(&deps.storage, "WETH").unwrap().is_none());
+        assert_eq!(100u64, REFS.load(&deps.storage, "BTC").unwrap().resolve_time);
+
+        // every removal path drops the aliases of the symbols it removes, and only those
+        let info = mock_info("creator", &[]);
+        let _res = execute(deps.as_mut(), mock_env(), info, ExecuteMsg::RemoveSymbol { symbols: vec![String::from("ETH")] }).unwrap();
+        assert_eq!((None, Some(String::from("BTC"))), (alias(deps.as_ref(), "WETH"), alias(deps.as_ref(), "WBTC")));
+        let err = query_reference_data(deps.as_ref(), mock_env(), String::from("WETH"), String::from("USD"), None).unwrap_err();
+        assert!(matches!(err, ContractError::SymbolNotFound { symbol } if symbol == "WETH"));
+
+        let info = mock_info("creator", &[]);
+        let _res = execute(deps.as_mut(), mock_env(), info, ExecuteMsg::RemoveByPrefix { prefix: String::from("BT") }).unwrap();
+        assert_eq!((None, Some(String::from("SOL"))), (alias(deps.as_ref(), "WBTC"), alias(deps.as_ref(), "WSOL")));
+
+        let info = mock_info("creator", &[]);
+        let _res = execute(deps.as_mut(), mock_env(), info, ExecuteMsg::Clear {}).unwrap();
+        assert_eq!(None, alias(deps.as_ref(), "WSOL"));
+
+        // a freed alias name can be relayed as a symbol of its own
+        let info = mock_info("creator", &[]);
+        let msg = ExecuteMsg::Relay { symbols: vec![String::from("WETH")], rates: vec![3000000000000u64], resolve_times: vec![300u64], request_ids: vec![3u64] };
+        let _res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+    }
+
+    #[test]
+    fn frozen_symbols() {
+        let mut deps = mock_dependencies(&[]);
+
+        let msg = InstantiateMsg::default();
+        let info = mock_info("creator", &[]);
+        let _res = instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+        let relay = |rate: u64, resolve_time: u64| ExecuteMsg::Relay { symbols: vec![String::from("ETH"), String::from("BTC")], rates: vec![rate, 50000000000000u64], resolve_times: vec![resolve_time, resolve_time], request_ids: vec![1u64, 1u64] };
+        let info = mock_info("creator", &[]);
+        let _res = execute(deps.as_mut(), mock_env(), info, relay(3000000000000u64, 100u64)).unwrap();
+
+        let info = mock_info("relayer1", &[]);
+        let err = execute(deps.as_mut(), mock_env(), info, ExecuteMsg::FreezeSymbol { symbol: String::from("ETH") }).unwrap_err();
+        assert!(matches!(err, ContractError::Unauthorized {}));
+        let info = mock_info("creator", &[]);
+        let res = execute(deps.as_mut(), mock_env(), info, ExecuteMsg::FreezeSymbol { symbol: String::from("ETH") }).unwrap();
+        assert_eq!(vec![attr("action", "freeze_symbol"), attr("symbol", "ETH")], res.attributes);
+
+        // the whole batch fails, even for the owner, and the frozen value is still served
+        let info = mock_info("creator", &[]);
+        let err = execute(deps.as_mut(), mock_env(), info, relay(3100000000000u64, 200u64)).unwrap_err();
+        assert!(matches!(err, ContractError::SymbolFrozen { symbol } if symbol == "ETH"));
+        let value = query_ref(deps.as_ref(), mock_env(), String::from("BTC")).unwrap();
+        assert_eq!(100u64, value.resolve_time);
+        let value = query_reference_data(deps.as_ref(), mock_env(), String::from("ETH"), String::from("USD"), None).unwrap();
+        assert_eq!(Uint128::from(3000u128 * E18), value.rate);
+
+        let info = mock_info("creator", &[]);
+        let res = execute(deps.as_mut(), mock_env(), info, ExecuteMsg::UnfreezeSymbol { symbol: String::from("ETH") }).unwrap();
+        assert_eq!(vec![attr("action", "unfreeze_symbol"), attr("symbol", "ETH")], res.attributes);
+        let info = mock_info("creator", &[]);
+        let _res = execute(deps.as_mut(), mock_env(), info, relay(3100000000000u64, 200u64)).unwrap();
+        let value = query_reference_data(deps.as_ref(), mock_env(), String::from("ETH"), String::from("USD"), None).unwrap();
+        assert_eq!(Uint128::from(3100u128 * E18), value.rate);
+    }
+
+    #[test]
+    fn query_reference_data_with_age() {
+        let mut deps = mock_dependencies(&[]);
+
+        let msg = InstantiateMsg::default();
+        let info = mock_info("creator", &[]);
+        let _res = instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+        let now = mock_env().block.time.nanos();
+        let info = mock_info("creator", &[]);
+        let msg = ExecuteMsg::Relay { symbols: vec![String::from("ETH"), String::from("BTC")], rates: vec![3000000000000u64, 45000000000000u64], resolve_times: vec![now - 30 * NANOS_PER_SECOND, now - 5 * NANOS_PER_SECOND - 1], request_ids: vec![1u64, 1u64] };
+        let _res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+        let mut env = mock_env();
+        env.block.time = env.block.time.plus_seconds(10);
+        let with_age = |base: &str, quote: &str| {
+            let msg = QueryMsg::GetReferenceDataWithAge { base: String::from(base), quote: String::from(quote) };
+            from_binary::<ReferenceDataWithAge>(&query(deps.as_ref(), env.clone(), msg).unwrap()).unwrap()
+        };
+        let value = with_age("ETH", "BTC");
+        let data = query_reference_data(deps.as_ref(), env.clone(), String::from("ETH"), String::from("BTC"), None).unwrap();
+        assert_eq!(ReferenceDataWithAge { rate: data.rate, last_updated_base: data.last_updated_base, last_updated_quote: data.last_updated_quote, base_age_secs: 40, quote_age_secs: 15 }, value);
+
+        // partial seconds are truncated and USD is always fresh
+        let value = with_age("USD", "BTC");
+        assert_eq!((0u64, 15u64), (value.base_age_secs, value.quote_age_secs));
+        let value = with_age("ETH", "USD");
+        assert_eq!((40u64, 0u64), (value.base_age_secs, value.quote_age_secs));
+    }
+
+    // `State` exactly as the original release wrote it: a refs-only blob and no cw2 version
+    const INITIAL_STATE: &[u8] = br#"{"refs":[["ETH",{"rate":3000000000000,"resolve_time":2,"request_id":3}]]}"#;
+
+    #[test]
+    fn migrate_introduce_owner() {
+        let mut deps = mock_dependencies(&[]);
+        deps.storage.set(&to_length_prefixed(CONFIG_KEY), INITIAL_STATE);
+
+        let err = super::migrate_introduce_owner(deps.as_mut(), &MigrateMsg::default()).unwrap_err();
+        assert!(matches!(err, ContractError::MissingOwner {}));
+
+        let msg = MigrateMsg { owner: Some(String::from("admin")) };
+        super::migrate_introduce_owner(deps.as_mut(), &msg).unwrap();
+        let legacy = legacy_config(deps.as_mut().storage).load().unwrap();
+        assert_eq!((Addr::unchecked("admin"), None, vec![], false, 1u64), (legacy.owner, legacy.pending_owner, legacy.relayers, legacy.paused, legacy.symbol_count));
+        assert_eq!((DEFAULT_BASE_DECIMALS, DEFAULT_USD_DECIMALS, None), (legacy.base_decimals, legacy.usd_decimals, legacy.max_staleness_secs));
+        assert_eq!((3000000000000u64, 2u64, 3u64), (legacy.refs["ETH"].rate, legacy.refs["ETH"].resolve_time, legacy.refs["ETH"].request_id));
     }
-}
 
-pub fn update_refs(deps: DepsMut, symbols: &[String], new_rates: &[u64], new_resolve_times: &[u64], new_request_ids: &[u64]) -> Result<Response, ContractError> {
-    let len = symbols.len();
-    if new_rates.len() != len || new_request_ids.len() != len || new_resolve_times.len() != len {
-        return Err(ContractError::DifferentArrayLength {});
+    #[test]
+    fn migrate_refs_updated_by() {
+        let mut deps = mock_dependencies(&[]);
+
+        let msg = InstantiateMsg::default();
+        let info = mock_info("creator", &[]);
+        let _res = instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+        // a 0.4.0 entry has std_dev but no updated_by
+        deps.storage.set(&REFS.key("ETH"), br#"{"rate":1,"resolve_time":2,"request_id":3,"std_dev":null}"#);
+        set_contract_version(deps.as_mut().storage, CONTRACT_NAME, "0.4.0").unwrap();
+
+        let _res = migrate(deps.as_mut(), mock_env(), MigrateMsg::default()).unwrap();
+
+        assert_eq!(Addr::unchecked("creator"), REFS.load(&deps.storage, "ETH").unwrap().updated_by);
     }
-    let mut state = config(deps.storage).load()?;
-    for idx in 0..len {
-        state.refs.insert(symbols[idx].clone(), RefData {
-            rate: new_rates[idx],
-            resolve_time: new_resolve_times[idx],
-            request_id: new_request_ids[idx],
-        });
-    };
-    config(deps.storage).save(&state)?;
-    Ok(Response::default())
-}
 
-#[entry_point]
-pub fn query(deps: Deps, env: Env, msg: QueryMsg) -> StdResult<Binary> {
-    match msg {
-        QueryMsg::GetRefs {} => to_binary(&query_refs(deps)?),
-        QueryMsg::GetReferenceData { base, quote } => {
-            let base_ref_data = get_ref_data(deps, env.clone(), base).unwrap();
-            let quote_ref_data = get_ref_data(deps, env.clone(), quote).unwrap();
-            to_binary(&ReferenceData {
-                rate: (base_ref_data.rate * BigUint::from(1e18 as u128)) / quote_ref_data.rate,
-                last_updated_base: BigUint::from(base_ref_data.last_update),
-                last_updated_quote: BigUint::from(quote_ref_data.last_update),
-            })
+    #[test]
+    fn migrate_schema_versions() {
+        let mut deps = mock_dependencies(&[]);
+
+        // pre-0.9.0 deployments map their cw2 version onto a schema version; 0.2.x was the first to store one
+        assert_eq!(vec![2, 2, 3, 4, 5, 6, 6], ["0.1.0", "0.2.0", "0.3.0", "0.4.0", "0.5.0", "0.6.0", "0.8.0"].iter().map(|version| legacy_schema_version(version)).collect::<Vec<_>>());
+
+        // schema 1 runs the whole chain: owner, refs map, std_dev, updated_by and decimals
+        deps.storage.set(&to_length_prefixed(CONFIG_KEY), INITIAL_STATE);
+        let err = migrate(deps.as_mut(), mock_env(), MigrateMsg::default()).unwrap_err();
+        assert!(matches!(err, ContractError::MissingOwner {}));
+        let _res = migrate(deps.as_mut(), mock_env(), MigrateMsg { owner: Some(String::from("admin")) }).unwrap();
+        assert_eq!(SCHEMA_VERSION, schema_version_read(deps.as_ref().storage).load().unwrap());
+        assert_eq!(String::from(CONTRACT_VERSION), get_contract_version(deps.as_ref().storage).unwrap().version);
+        let value = query_ref(deps.as_ref(), mock_env(), String::from("ETH")).unwrap();
+        assert_eq!(RefData{rate: 3000000000000u64, resolve_time: 2u64, request_id: 3u64, std_dev: None, updated_by: Addr::unchecked("admin"), decimals: 9, negative: false, relay_seq: 0}, value);
+        let value = query_reference_data(deps.as_ref(), mock_env(), String::from("ETH"), String::from("USD"), None).unwrap();
+        assert_eq!(Uint128::from(3000u128 * E18), value.rate);
+        let state = config_read(&deps.storage).load().unwrap();
+        assert_eq!((Some(Addr::unchecked("admin")), 1u64), (state.owner, state.symbol_count));
+
+        // the new owner can relay right away
+        let info = mock_info("admin", &[]);
+        let msg = ExecuteMsg::Relay { symbols: vec![String::from("ETH")], rates: vec![3100000000000u64], resolve_times: vec![4u64], request_ids: vec![5u64] };
+        let _res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+        // already current: nothing to do
+        let _res = migrate(deps.as_mut(), mock_env(), MigrateMsg::default()).unwrap();
+        assert_eq!(3100000000000u64, query_ref(deps.as_ref(), mock_env(), String::from("ETH")).unwrap().rate);
+
+        for found in [0u32, SCHEMA_VERSION + 1] {
+            schema_version(deps.as_mut().storage).save(&found).unwrap();
+            let err = migrate(deps.as_mut(), mock_env(), MigrateMsg::default()).unwrap_err();
+            assert!(matches!(err, ContractError::UnknownSchemaVersion { found: value } if value == found));
         }
     }
-}
 
-fn query_refs(deps: Deps) -> StdResult<ConfigResponse> {
-    let state = config_read(deps.storage).load()?;
-    Ok(state)
-}
+    #[test]
+    fn strict_request_id() {
+        let mut deps = mock_dependencies(&[]);
 
-fn get_ref_data(deps: Deps, env: Env, symbol: String) -> Result<RefDataResponse, ContractError> {
-    if symbol == String::from("USD") {
-        return Ok(RefDataResponse {
-            rate: BigUint::from(1e9 as u128),
-            last_update: BigUint::from(env.block.time.nanos()),
-        });
+        let msg = InstantiateMsg::default();
+        let info = mock_info("creator", &[]);
+        let _res = instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+        let info = mock_info("creator", &[]);
+        let msg = ExecuteMsg::Relay { symbols: vec![String::from("ETH"), String::from("BTC")], rates: vec![3000000000000u64, 45000000000000u64], resolve_times: vec![100u64, 100u64], request_ids: vec![0u64, 1u64] };
+        let _res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+        let value = query_reference_data(deps.as_ref(), mock_env(), String::from("ETH"), String::from("USD"), None).unwrap();
+        assert_eq!(Uint128::from(3000u128 * E18), value.rate);
+
+        let info = mock_info("creator", &[]);
+        let msg = ExecuteMsg::UpdateConfig { owner: None, max_staleness_secs: None, max_future_skew_secs: None, max_rate_change_bps: None, base_decimals: None, usd_decimals: None, max_batch_size: None, min_relay_interval_secs: None, allow_signed_rates: None, rounding: None, strict_request_id: Some(true), usd_timestamp_mode: None, twap_samples: None };
+        let _res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+        let err = query_reference_data(deps.as_ref(), mock_env(), String::from("ETH"), String::from("USD"), None).unwrap_err();
+        assert!(matches!(err, ContractError::RefDataNotAvailable {}));
+        // USD has no request id but is always served
+        let value = query_reference_data(deps.as_ref(), mock_env(), String::from("BTC"), String::from("USD"), None).unwrap();
+        assert_eq!(Uint128::from(45000u128 * E18), value.rate);
     }
-    let state = config_read(deps.storage).load()?;
-    let ref_data = state.refs.get(&symbol).unwrap();
-    if ref_data.resolve_time <= 0 {
-        return Err(ContractError::RefDataNotAvailable {});
+
+    #[test]
+    fn query_updated_since() {
+        let mut deps = mock_dependencies(&[]);
+
+        let msg = InstantiateMsg::default();
+        let info = mock_info("creator", &[]);
+        let _res = instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+        let info = mock_info("creator", &[]);
+        let msg = ExecuteMsg::Relay { symbols: vec![String::from("MATIC"), String::from("BAND"), String::from("ETH"), String::from("ATOM")], rates: vec![1u64, 2u64, 3u64, 4u64], resolve_times: vec![100u64, 300u64, 200u64, 400u64], request_ids: vec![1u64, 1u64, 1u64, 1u64] };
+        let _res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+        let updated_since = |resolve_time: u64, start_after: Option<&str>, limit: Option<u32>| -> SymbolsPageResponse {
+            let msg = QueryMsg::GetUpdatedSince { resolve_time, start_after: start_after.map(String::from), limit };
+            from_binary(&query(deps.as_ref(), mock_env(), msg).unwrap()).unwrap()
+        };
+        assert_eq!(SymbolsPageResponse { symbols: vec![String::from("ATOM"), String::from("BAND"), String::from("ETH"), String::from("MATIC")], next_start_after: None }, updated_since(0, None, None));
+        assert_eq!(vec!["ATOM", "BAND", "ETH"], updated_since(200, None, None).symbols);
+        assert_eq!(vec!["ATOM", "BAND"], updated_since(201, None, None).symbols);
+        assert_eq!(SymbolsPageResponse { symbols: vec![String::from("ATOM")], next_start_after: Some(String::from("ATOM")) }, updated_since(200, None, Some(1)));
+        assert_eq!(SymbolsPageResponse { symbols: vec![String::from("BAND"), String::from("ETH")], next_start_after: Some(String::from("ETH")) }, updated_since(200, Some("ATOM"), Some(2)));
+        assert_eq!(SymbolsPageResponse { symbols: vec![], next_start_after: None }, updated_since(200, Some("ETH"), Some(2)));
+        assert!(updated_since(401, None, None).symbols.is_empty());
     }
-    return Ok(RefDataResponse {
-        rate: BigUint::from(ref_data.rate),
-        last_update:BigUint::from(ref_data.resolve_time),
-    });
-}
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use cosmwasm_std::testing::{mock_dependencies, mock_env, mock_info};
-    use cosmwasm_std::{from_binary};
-    use std::collections::HashMap;
+    #[test]
+    fn relay_response_data() {
+        let mut deps = mock_dependencies(&[]);
+
+        let msg = InstantiateMsg::default();
+        let info = mock_info("creator", &[]);
+        let _res = instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+        let info = mock_info("creator", &[]);
+        let msg = ExecuteMsg::Relay { symbols: vec![String::from("ETH"), String::from("BTC")], rates: vec![1u64, 2u64], resolve_times: vec![100u64, 100u64], request_ids: vec![1u64, 1u64] };
+        let res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+        let value: RelayResponse = from_binary(&res.data.unwrap()).unwrap();
+        assert_eq!(RelayResponse { num_updated: 2, relay_seq: 1 }, value);
+
+        // BTC is stale and ETH unchanged, so only the new symbol is counted
+        let info = mock_info("creator", &[]);
+        let msg = ExecuteMsg::Relay { symbols: vec![String::from("ETH"), String::from("BTC"), String::from("BAND")], rates: vec![1u64, 3u64, 4u64], resolve_times: vec![100u64, 50u64, 100u64], request_ids: vec![1u64, 1u64, 1u64] };
+        let res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+        let value: RelayResponse = from_binary(&res.data.unwrap()).unwrap();
+        assert_eq!(RelayResponse { num_updated: 1, relay_seq: 2 }, value);
+    }
 
     #[test]
-    fn proper_initialization() {
+    fn event_namespace() {
         let mut deps = mock_dependencies(&[]);
 
-        let msg = InstantiateMsg {};
+        let msg = InstantiateMsg { event_namespace: Some(String::from("band")), ..InstantiateMsg::default() };
+        let info = mock_info("creator", &[]);
+        let _res = instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+
         let info = mock_info("creator", &[]);
+        let msg = ExecuteMsg::Relay { symbols: vec![String::from("ETH")], rates: vec![1u64], resolve_times: vec![100u64], request_ids: vec![1u64] };
+        let res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+        assert_eq!(vec![attr("action", "band.relay"), attr("band.num_symbols", "1"), attr("band.num_updated", "1"), attr("band.symbol:ETH", "1@100")], res.attributes);
 
-        // we can just call .unwrap() to assert this was a success
-        let res = instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
-        assert_eq!(0, res.messages.len());
+        let info = mock_info("creator", &[]);
+        let res = execute(deps.as_mut(), mock_env(), info, ExecuteMsg::Pause {}).unwrap();
+        assert_eq!(vec![attr("action", "band.pause")], res.attributes);
 
-        // it worked, let's query the state
-        let res = query(deps.as_ref(), mock_env(), QueryMsg::GetRefs{}).unwrap();
-        let value: ConfigResponse = from_binary(&res).unwrap();
-        assert_eq!(HashMap::new(), value.refs);
+        let info = mock_info("creator", &[]);
+        let res = execute(deps.as_mut(), mock_env(), info, ExecuteMsg::AddRelayer { address: String::from("relayer1") }).unwrap();
+        assert_eq!(vec![attr("action", "band.add_relayer"), attr("band.relayer", "relayer1")], res.attributes);
     }
 
     #[test]
-    fn insert_one() {
+    fn query_preview_reference_data() {
         let mut deps = mock_dependencies(&[]);
 
-        let msg = InstantiateMsg {};
+        let msg = InstantiateMsg::default();
         let info = mock_info("creator", &[]);
         let _res = instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
 
         let info = mock_info("creator", &[]);
-        let msg = ExecuteMsg::Relay { symbols: vec![String::from("ETH")], rates: vec![1u64], resolve_times: vec![2u64], request_ids: vec![3u64] };
+        let msg = ExecuteMsg::Relay { symbols: vec![String::from("ETH"), String::from("BTC")], rates: vec![3001234567891u64, 45678912345678u64], resolve_times: vec![100u64, 200u64], request_ids: vec![1u64, 1u64] };
         let _res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
 
-        let res = query(deps.as_ref(), mock_env(), QueryMsg::GetRefs {}).unwrap();
-        let value: ConfigResponse = from_binary(&res).unwrap();
-        let mut mock_map = HashMap::new();
+        let preview = |base_rate: u64, quote_rate: u64, base_resolve_time: u64, quote_resolve_time: u64| {
+            let msg = QueryMsg::PreviewReferenceData { base_rate, quote_rate, base_resolve_time, quote_resolve_time };
+            query(deps.as_ref(), mock_env(), msg).map(|res| from_binary::<ReferenceData>(&res).unwrap())
+        };
+        let expected = query_reference_data(deps.as_ref(), mock_env(), String::from("ETH"), String::from("BTC"), None).unwrap();
+        assert_eq!(expected, preview(3001234567891u64, 45678912345678u64, 100u64, 200u64).unwrap());
+        let expected = query_reference_data(deps.as_ref(), mock_env(), String::from("BTC"), String::from("ETH"), None).unwrap();
+        assert_eq!(expected, preview(45678912345678u64, 3001234567891u64, 200u64, 100u64).unwrap());
 
-        mock_map.insert(String::from("ETH"), RefData{rate: 1u64, resolve_time: 2u64, request_id: 3u64});
+        assert!(preview(1u64, 0u64, 100u64, 100u64).is_err());
+    }
 
-        assert_eq!(mock_map, value.refs);
+    #[test]
+    fn usd_timestamp_mode() {
+        let mut deps = mock_dependencies(&[]);
+
+        let msg = InstantiateMsg::default();
+        let info = mock_info("creator", &[]);
+        let _res = instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+        let info = mock_info("creator", &[]);
+        let msg = ExecuteMsg::Relay { symbols: vec![String::from("ETH")], rates: vec![3000000000000u64], resolve_times: vec![100u64], request_ids: vec![1u64] };
+        let _res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+        let mut later = mock_env();
+        later.block.time = later.block.time.plus_seconds(60);
+        let cases = vec![
+            (UsdTimestampMode::BlockTime, mock_env().block.time.nanos(), later.block.time.nanos()),
+            (UsdTimestampMode::Zero, 0u64, 0u64),
+            (UsdTimestampMode::MaxU64, u64::MAX, u64::MAX),
+        ];
+        for (usd_timestamp_mode, now, then) in cases {
+            let info = mock_info("creator", &[]);
+            let msg = ExecuteMsg::UpdateConfig { owner: None, max_staleness_secs: None, max_future_skew_secs: None, max_rate_change_bps: None, base_decimals: None, usd_decimals: None, max_batch_size: None, min_relay_interval_secs: None, allow_signed_rates: None, rounding: None, strict_request_id: None, usd_timestamp_mode: Some(usd_timestamp_mode), twap_samples: None };
+            let _res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+            let value = query_reference_data(deps.as_ref(), mock_env(), String::from("USD"), String::from("ETH"), None).unwrap();
+            assert_eq!(Uint128::from(now), value.last_updated_base);
+            assert_eq!(Uint128::from(100u64), value.last_updated_quote);
+            let value = query_reference_data(deps.as_ref(), later.clone(), String::from("USD"), String::from("ETH"), None).unwrap();
+            assert_eq!(Uint128::from(then), value.last_updated_base);
+            // the rate itself never depends on the mode
+            assert_eq!(Uint128::from(333333333333333u128), value.rate);
+
+            let msg = QueryMsg::GetReferenceDataWithAge { base: String::from("USD"), quote: String::from("ETH") };
+            let value: ReferenceDataWithAge = from_binary(&query(deps.as_ref(), later.clone(), msg).unwrap()).unwrap();
+            assert_eq!(0u64, value.base_age_secs);
+        }
     }
 
     #[test]
-    fn insert_batch() {
+    fn remove_by_prefix() {
         let mut deps = mock_dependencies(&[]);
 
-        let msg = InstantiateMsg {};
+        let msg = InstantiateMsg { symbol_separator: Some(String::from("_")), ..InstantiateMsg::default() };
         let info = mock_info("creator", &[]);
         let _res = instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
 
         let info = mock_info("creator", &[]);
-        let msg = ExecuteMsg::Relay { symbols: vec![String::from("ETH"), String::from("BAND")], rates: vec![1u64, 100u64], resolve_times: vec![2u64, 200u64], request_ids: vec![3u64, 300u64] };
+        let msg = ExecuteMsg::Relay { symbols: vec![String::from("TEST_A"), String::from("ETH"), String::from("TEST_B"), String::from("TEST"), String::from("TESTX")], rates: vec![1u64; 5], resolve_times: vec![100u64; 5], request_ids: vec![1u64; 5] };
         let _res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
 
-        let res = query(deps.as_ref(), mock_env(), QueryMsg::GetRefs {}).unwrap();
-        let value: ConfigResponse = from_binary(&res).unwrap();
-        let mut mock_map = HashMap::new();
+        let remove = |deps: DepsMut, sender: &str, prefix: &str| execute(deps, mock_env(), mock_info(sender, &[]), ExecuteMsg::RemoveByPrefix { prefix: String::from(prefix) });
+        let err = remove(deps.as_mut(), "relayer1", "TEST_").unwrap_err();
+        assert!(matches!(err, ContractError::Unauthorized {}));
+        let err = remove(deps.as_mut(), "creator", "").unwrap_err();
+        assert!(matches!(err, ContractError::InvalidSymbol { .. }));
 
-        mock_map.insert(String::from("ETH"), RefData{rate: 1u64, resolve_time: 2u64, request_id: 3u64});
-        mock_map.insert(String::from("BAND"), RefData{rate: 100u64, resolve_time: 200u64, request_id: 300u64});
+        let res = remove(deps.as_mut(), "creator", "TEST_").unwrap();
+        assert_eq!(vec![attr("action", "remove_by_prefix"), attr("prefix", "TEST_"), attr("num_removed", "2"), attr("has_more", "false")], res.attributes);
+        let res = query(deps.as_ref(), mock_env(), QueryMsg::GetSymbols { start_after: None, limit: None }).unwrap();
+        assert_eq!(vec!["ETH", "TEST", "TESTX"], from_binary::<Vec<String>>(&res).unwrap());
+        let res = remove(deps.as_mut(), "creator", "TEST").unwrap();
+        assert_eq!(attr("num_removed", "2"), res.attributes[2]);
+        let res = query(deps.as_ref(), mock_env(), QueryMsg::GetSymbols { start_after: None, limit: None }).unwrap();
+        assert_eq!(vec!["ETH"], from_binary::<Vec<String>>(&res).unwrap());
+        assert_eq!(1u64, load_state(deps.as_ref()).unwrap().symbol_count);
 
-        assert_eq!(mock_map, value.refs);
+        // large families are removed over several calls
+        let symbols: Vec<String> = (0..MAX_REMOVE_BY_PREFIX + 5).map(|idx| format!("OLD_{:03}", idx)).collect();
+        for chunk in symbols.chunks(100) {
+            let info = mock_info("creator", &[]);
+            let msg = ExecuteMsg::Relay { symbols: chunk.to_vec(), rates: vec![1u64; chunk.len()], resolve_times: vec![100u64; chunk.len()], request_ids: vec![1u64; chunk.len()] };
+            let _res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+        }
+        let res = remove(deps.as_mut(), "creator", "OLD_").unwrap();
+        assert_eq!(vec![attr("num_removed", "100"), attr("has_more", "true")], res.attributes[2..].to_vec());
+        let res = remove(deps.as_mut(), "creator", "OLD_").unwrap();
+        assert_eq!(vec![attr("num_removed", "5"), attr("has_more", "false")], res.attributes[2..].to_vec());
+        assert_eq!(1u64, load_state(deps.as_ref()).unwrap().symbol_count);
     }
 
     #[test]
-    fn update_rate() {
+    fn query_reference_data_verbose() {
         let mut deps = mock_dependencies(&[]);
 
-        let msg = InstantiateMsg {};
+        let msg = InstantiateMsg::default();
         let info = mock_info("creator", &[]);
         let _res = instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
 
         let info = mock_info("creator", &[]);
-        let msg = ExecuteMsg::Relay { symbols: vec![String::from("MATIC")], rates: vec![12u64], resolve_times: vec![124824u64], request_ids: vec![69u64] };
+        let msg = ExecuteMsg::Relay { symbols: vec![String::from("ETH"), String::from("BTC")], rates: vec![3000000000000u64, 45000000000000u64], resolve_times: vec![100u64, 200u64], request_ids: vec![7u64, 8u64] };
         let _res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
 
-        let res = query(deps.as_ref(), mock_env(), QueryMsg::GetRefs {}).unwrap();
-        let value: ConfigResponse = from_binary(&res).unwrap();
+        let verbose = |base: &str, quote: &str| {
+            let msg = QueryMsg::GetReferenceDataVerbose { base: String::from(base), quote: String::from(quote) };
+            from_binary::<ReferenceDataVerbose>(&query(deps.as_ref(), mock_env(), msg).unwrap()).unwrap()
+        };
+        let value = verbose("ETH", "BTC");
+        assert_eq!(query_reference_data(deps.as_ref(), mock_env(), String::from("ETH"), String::from("BTC"), None).unwrap(), value.data);
+        assert_eq!(RefData{rate: 3000000000000u64, resolve_time: 100u64, request_id: 7u64, std_dev: None, updated_by: Addr::unchecked("creator"), decimals: 9, negative: false, relay_seq: 1}, value.base);
+        assert_eq!(RefData{rate: 45000000000000u64, resolve_time: 200u64, request_id: 8u64, std_dev: None, updated_by: Addr::unchecked("creator"), decimals: 9, negative: false, relay_seq: 1}, value.quote);
 
-        let mut mock_map01 = HashMap::new();
-        mock_map01.insert(String::from("MATIC"), RefData{rate: 12u64, resolve_time: 124824u64, request_id: 69u64});
-        assert_eq!(mock_map01, value.refs);
+        let value = verbose("ETH", "USD");
+        assert_eq!(Uint128::from(3000u128 * E18), value.data.rate);
+        assert_eq!((1000000000u64, 0u64, mock_env().block.time.nanos()), (value.quote.rate, value.quote.request_id, value.quote.resolve_time));
 
-        let info = mock_info("sender", &[]);
-        let msg = ExecuteMsg::Relay { symbols: vec![String::from("MATIC")], rates: vec![24u64], resolve_times: vec![124824u64], request_ids: vec![69u64] };
+        let msg = QueryMsg::GetReferenceDataVerbose { base: String::from("ETH"), quote: String::from("BAND") };
+        assert!(query(deps.as_ref(), mock_env(), msg).is_err());
+    }
+
+    #[test]
+    fn heartbeat() {
+        let mut deps = mock_dependencies(&[]);
+
+        let msg = InstantiateMsg::default();
+        let info = mock_info("creator", &[]);
+        let _res = instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+        let info = mock_info("creator", &[]);
+        let msg = ExecuteMsg::RelayWithStdDev { symbols: vec![String::from("ETH")], rates: vec![3000000000000u64], resolve_times: vec![100u64], request_ids: vec![1u64], std_devs: vec![15000000u64] };
+        let _res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+        let info = mock_info("creator", &[]);
+        let msg = ExecuteMsg::RelayWithDecimals { symbols: vec![String::from("USDC")], rates: vec![999900u64], resolve_times: vec![100u64], request_ids: vec![1u64], decimals: vec![6u32] };
         let _res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
 
-        let res = query(deps.as_ref(), mock_env(), QueryMsg::GetRefs {}).unwrap();
-        let value: ConfigResponse = from_binary(&res).unwrap();
+        let info = mock_info("creator", &[]);
+        let msg = ExecuteMsg::Heartbeat { symbols: vec![String::from("ETH"), String::from("USDC")], resolve_times: vec![200u64, 200u64], request_ids: vec![2u64, 2u64] };
+        let _res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
 
-        let mut mock_map02 = HashMap::new();
-        mock_map02.insert(String::from("MATIC"), RefData{rate: 24u64, resolve_time: 124824u64, request_id: 69u64});
-        assert_eq!(mock_map02, value.refs);
+        let value = query_ref(deps.as_ref(), mock_env(), String::from("ETH")).unwrap();
+        assert_eq!(RefData{rate: 3000000000000u64, resolve_time: 200u64, request_id: 2u64, std_dev: Some(15000000u64), updated_by: Addr::unchecked("creator"), decimals: 9, negative: false, relay_seq: 3}, value);
+        let value = query_ref(deps.as_ref(), mock_env(), String::from("USDC")).unwrap();
+        assert_eq!(RefData{rate: 999900u64, resolve_time: 200u64, request_id: 2u64, std_dev: None, updated_by: Addr::unchecked("creator"), decimals: 6, negative: false, relay_seq: 3}, value);
+
+        // unknown symbols fail the whole heartbeat
+        let info = mock_info("creator", &[]);
+        let msg = ExecuteMsg::Heartbeat { symbols: vec![String::from("ETH"), String::from("BTC")], resolve_times: vec![300u64, 300u64], request_ids: vec![3u64, 3u64] };
+        let err = execute(deps.as_mut(), mock_env(), info, msg).unwrap_err();
+        assert!(matches!(err, ContractError::SymbolNotFound { symbol } if symbol == "BTC"));
+        assert_eq!(200u64, query_ref(deps.as_ref(), mock_env(), String::from("ETH")).unwrap().resolve_time);
+
+        let info = mock_info("relayer1", &[]);
+        let msg = ExecuteMsg::Heartbeat { symbols: vec![String::from("ETH")], resolve_times: vec![300u64], request_ids: vec![3u64] };
+        let err = execute(deps.as_mut(), mock_env(), info, msg).unwrap_err();
+        assert!(matches!(err, ContractError::Unauthorized {}));
     }
 
     #[test]
-    fn query_test_valid() {
+    fn mismatched_decimals_overflow() {
         let mut deps = mock_dependencies(&[]);
 
-        let msg = InstantiateMsg {};
+        let msg = InstantiateMsg::default();
         let info = mock_info("creator", &[]);
         let _res = instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
 
+        // BIG is a whole-dollar price of u64::MAX, ONE a dollar at 18 decimals and DUST a single 1e-18 dollar
         let info = mock_info("creator", &[]);
-        let msg = ExecuteMsg::Relay { symbols: vec![String::from("MATIC")], rates: vec![112u64], resolve_times: vec![1625108298000000000u64], request_ids: vec![124u64] };
+        let msg = ExecuteMsg::RelayWithDecimals { symbols: vec![String::from("BIG"), String::from("ONE"), String::from("DUST")], rates: vec![u64::MAX, 1000000000000000000u64, 1u64], resolve_times: vec![100u64; 3], request_ids: vec![1u64; 3], decimals: vec![0u32, 18u32, 18u32] };
         let _res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
 
-        let _info = mock_info("querier", &[]);
-        let msg = QueryMsg::GetReferenceData { base: String::from("USD"), quote: String::from("MATIC") };
-        let res = query(deps.as_ref(), mock_env(), msg).unwrap();
-        let value: ReferenceData = from_binary(&res).unwrap();
+        // the intermediate u64::MAX * 1e18 * 1e18 is far past 128 bits, but the result still fits
+        let value = query_reference_data(deps.as_ref(), mock_env(), String::from("BIG"), String::from("ONE"), None).unwrap();
+        assert_eq!(Uint128::from(u64::MAX as u128 * E18), value.rate);
+        let value = query_reference_data(deps.as_ref(), mock_env(), String::from("DUST"), String::from("BIG"), None).unwrap();
+        assert_eq!(Uint128::zero(), value.rate);
+
+        // u64::MAX * 1e36 does not
+        let err = query_reference_data(deps.as_ref(), mock_env(), String::from("BIG"), String::from("DUST"), None).unwrap_err();
+        assert!(matches!(err, ContractError::Overflow {}));
+        let value = query_reference_data(deps.as_ref(), mock_env(), String::from("BIG"), String::from("DUST"), Some(0)).unwrap();
+        assert_eq!(Uint128::from(u64::MAX as u128 * E18), value.rate);
+    }
+
+    #[test]
+    fn instantiate_with_owner() {
+        let mut deps = mock_dependencies(&[]);
+
+        let msg = InstantiateMsg::default();
+        let info = mock_info("creator", &[]);
+        let _res = instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+        let res = query(deps.as_ref(), mock_env(), QueryMsg::GetOwnership {}).unwrap();
+        assert_eq!(Some(Addr::unchecked("creator")), from_binary::<OwnershipResponse>(&res).unwrap().owner);
+
+        let mut deps = mock_dependencies(&[]);
+
+        let msg = InstantiateMsg { owner: Some(String::from("dao")), initial_symbols: Some(vec![String::from("ETH")]), initial_rates: Some(vec![1u64]), initial_resolve_times: Some(vec![100u64]), initial_request_ids: Some(vec![1u64]), ..InstantiateMsg::default() };
+        let info = mock_info("factory", &[]);
+        let _res = instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+        let res = query(deps.as_ref(), mock_env(), QueryMsg::GetOwnership {}).unwrap();
+        assert_eq!(Some(Addr::unchecked("dao")), from_binary::<OwnershipResponse>(&res).unwrap().owner);
+        // seeded refs are attributed to the instantiator
+        assert_eq!(Addr::unchecked("factory"), query_ref(deps.as_ref(), mock_env(), String::from("ETH")).unwrap().updated_by);
+
+        let info = mock_info("factory", &[]);
+        let err = execute(deps.as_mut(), mock_env(), info, ExecuteMsg::Pause {}).unwrap_err();
+        assert!(matches!(err, ContractError::Unauthorized {}));
+        let info = mock_info("dao", &[]);
+        let _res = execute(deps.as_mut(), mock_env(), info, ExecuteMsg::Pause {}).unwrap();
+
+        let mut deps = mock_dependencies(&[]);
+        let msg = InstantiateMsg { owner: Some(String::new()), ..InstantiateMsg::default() };
+        let info = mock_info("factory", &[]);
+        assert!(instantiate(deps.as_mut(), mock_env(), info, msg).is_err());
+    }
+
+    #[test]
+    fn query_median_reference_data() {
+        let mut deps = mock_dependencies(&[]);
+
+        let msg = InstantiateMsg::default();
+        let info = mock_info("creator", &[]);
+        let _res = instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+        // ETH against three dollar stablecoins trading at 0.5, 1 and 4
+        let info = mock_info("creator", &[]);
+        let msg = ExecuteMsg::Relay { symbols: vec![String::from("ETH"), String::from("USDC"), String::from("USDT"), String::from("DAI"), String::from("UST")], rates: vec![3000000000000u64, 1000000000u64, 500000000u64, 4000000000u64, 0u64], resolve_times: vec![100u64, 100u64, 100u64, 100u64, 0u64], request_ids: vec![1u64; 5] };
+        let _res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+        let median = |quotes: &[&str], skip_missing: bool| {
+            let msg = QueryMsg::GetMedianReferenceData { base: String::from("ETH"), quotes: quotes.iter().map(|quote| String::from(*quote)).collect(), skip_missing };
+            query(deps.as_ref(), mock_env(), msg).map(|res| from_binary::<MedianReferenceData>(&res).unwrap())
+        };
+        let value = median(&["USDT", "DAI", "USDC"], false).unwrap();
+        assert_eq!(MedianReferenceData { rate: Uint128::from(3000u128 * E18), symbols: vec![String::from("USDT"), String::from("DAI"), String::from("USDC")] }, value);
+        // (750 + 3000) / 2
+        let value = median(&["DAI", "USDC"], false).unwrap();
+        assert_eq!(Uint128::from(1875u128 * E18), value.rate);
+
+        assert!(median(&["USDC", "BAND", "UST"], false).is_err());
+        let value = median(&["USDC", "BAND", "UST", "USDT"], true).unwrap();
+        assert_eq!(MedianReferenceData { rate: Uint128::from(4500u128 * E18), symbols: vec![String::from("USDC"), String::from("USDT")] }, value);
+        let err = median(&["BAND"], true).unwrap_err();
+        assert_eq!(ContractError::NoReferenceData {}.to_string(), err.to_string());
+        assert!(median(&[], false).is_err());
+    }
+
+    #[test]
+    fn query_twap() {
+        let mut deps = mock_dependencies(&[]);
+
+        let msg = InstantiateMsg::default();
+        let info = mock_info("creator", &[]);
+        let _res = instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+        let now = mock_env().block.time.nanos();
+        let secs_ago = |secs: u64| now - secs * NANOS_PER_SECOND;
+        let relay = |deps: DepsMut, rate: u64, resolve_time: u64| {
+            let msg = ExecuteMsg::Relay { symbols: vec![String::from("ETH")], rates: vec![rate], resolve_times: vec![resolve_time], request_ids: vec![1u64] };
+            execute(deps, mock_env(), mock_info("creator", &[]), msg).unwrap();
+        };
+        let twap = |deps: Deps, symbol: &str, window_secs: u64| {
+            let msg = QueryMsg::GetTwap { symbol: String::from(symbol), window_secs };
+            query(deps, mock_env(), msg).map(|res| from_binary::<TwapResponse>(&res).unwrap())
+        };
+        // 1000 for 60s, 2000 for 30s and 4000 for the last 10s
+        relay(deps.as_mut(), 1000u64, secs_ago(100));
+        relay(deps.as_mut(), 2000u64, secs_ago(40));
+        relay(deps.as_mut(), 4000u64, secs_ago(10));
+
+        assert_eq!(TwapResponse { rate: Uint128::from(4000u128), decimals: 9, num_samples: 1 }, twap(deps.as_ref(), "ETH", 10).unwrap());
+        // (2000 * 10 + 4000 * 10) / 20
+        assert_eq!(TwapResponse { rate: Uint128::from(3000u128), decimals: 9, num_samples: 2 }, twap(deps.as_ref(), "ETH", 20).unwrap());
+        // (1000 * 60 + 2000 * 30 + 4000 * 10) / 100, and the same once the window reaches past the first sample
+        assert_eq!(TwapResponse { rate: Uint128::from(1600u128), decimals: 9, num_samples: 3 }, twap(deps.as_ref(), "ETH", 100).unwrap());
+        assert_eq!(Uint128::from(1600u128), twap(deps.as_ref(), "ETH", 1000).unwrap().rate);
+        assert_eq!(Uint128::from(4000u128), twap(deps.as_ref(), "ETH", 0).unwrap().rate);
+        assert_eq!(Uint128::from(1000000000u128), twap(deps.as_ref(), "USD", 100).unwrap().rate);
+        assert!(twap(deps.as_ref(), "BTC", 100).is_err());
+
+        // only the configured number of samples is kept
+        let info = mock_info("creator", &[]);
+        let msg = ExecuteMsg::UpdateConfig { owner: None, max_staleness_secs: None, max_future_skew_secs: None, max_rate_change_bps: None, base_decimals: None, usd_decimals: None, max_batch_size: None, min_relay_interval_secs: None, allow_signed_rates: None, rounding: None, strict_request_id: None, usd_timestamp_mode: None, twap_samples: Some(2) };
+        let _res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+        relay(deps.as_mut(), 4000u64, secs_ago(5));
+        assert_eq!(2, SAMPLES.load(deps.as_ref().storage, "ETH").unwrap().len());
+        // the older samples are gone, so the average only reaches back 10s
+        assert_eq!(TwapResponse { rate: Uint128::from(4000u128), decimals: 9, num_samples: 2 }, twap(deps.as_ref(), "ETH", 100).unwrap());
+
+        for twap_samples in [0u32, MAX_TWAP_SAMPLES + 1] {
+            let info = mock_info("creator", &[]);
+            let msg = ExecuteMsg::UpdateConfig { owner: None, max_staleness_secs: None, max_future_skew_secs: None, max_rate_change_bps: None, base_decimals: None, usd_decimals: None, max_batch_size: None, min_relay_interval_secs: None, allow_signed_rates: None, rounding: None, strict_request_id: None, usd_timestamp_mode: None, twap_samples: Some(twap_samples) };
+            let err = execute(deps.as_mut(), mock_env(), info, msg).unwrap_err();
+            assert!(matches!(err, ContractError::InvalidTwapSamples { max: MAX_TWAP_SAMPLES }));
+        }
+
+        // a change of precision starts over
+        let info = mock_info("creator", &[]);
+        let msg = ExecuteMsg::RelayWithDecimals { symbols: vec![String::from("ETH")], rates: vec![4u64], resolve_times: vec![secs_ago(1)], request_ids: vec![1u64], decimals: vec![0u32] };
+        let _res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+        assert_eq!(TwapResponse { rate: Uint128::from(4u128), decimals: 0, num_samples: 1 }, twap(deps.as_ref(), "ETH", 100).unwrap());
+
+        let info = mock_info("creator", &[]);
+        let _res = execute(deps.as_mut(), mock_env(), info, ExecuteMsg::RemoveSymbol { symbols: vec![String::from("ETH")] }).unwrap();
+        assert_eq!(None, SAMPLES.may_load(deps.as_ref().storage, "ETH").unwrap());
+    }
+
+    #[test]
+    fn query_labeled_reference_data() {
+        let mut deps = mock_dependencies(&[]);
+
+        let msg = InstantiateMsg::default();
+        let info = mock_info("creator", &[]);
+        let _res = instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+        let info = mock_info("creator", &[]);
+        let msg = ExecuteMsg::Relay { symbols: vec![String::from("ETH"), String::from("BTC")], rates: vec![3000000000000u64, 45000000000000u64], resolve_times: vec![100u64, 200u64], request_ids: vec![1u64, 1u64] };
+        let _res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+        for (base, quote) in [("ETH", "BTC"), ("BTC", "USD"), ("USD", "ETH")] {
+            let msg = QueryMsg::GetLabeledReferenceData { base: String::from(base), quote: String::from(quote) };
+            let value: LabeledReferenceData = from_binary(&query(deps.as_ref(), mock_env(), msg).unwrap()).unwrap();
+            let data = query_reference_data(deps.as_ref(), mock_env(), String::from(base), String::from(quote), None).unwrap();
+            assert_eq!(LabeledReferenceData { base: String::from(base), quote: String::from(quote), rate: data.rate, last_updated_base: data.last_updated_base, last_updated_quote: data.last_updated_quote }, value);
+        }
+    }
+
+    #[test]
+    fn instantiate_twice() {
+        let mut deps = mock_dependencies(&[]);
+
+        let msg = InstantiateMsg::default();
+        let info = mock_info("creator", &[]);
+        let _res = instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+        let info = mock_info("creator", &[]);
+        let msg = ExecuteMsg::Relay { symbols: vec![String::from("ETH")], rates: vec![1u64], resolve_times: vec![100u64], request_ids: vec![1u64] };
+        let _res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+        let msg = InstantiateMsg { owner: Some(String::from("intruder")), ..InstantiateMsg::default() };
+        let info = mock_info("intruder", &[]);
+        let err = instantiate(deps.as_mut(), mock_env(), info, msg).unwrap_err();
+        assert!(matches!(err, ContractError::AlreadyInitialized {}));
+        assert_eq!(Some(Addr::unchecked("creator")), load_state(deps.as_ref()).unwrap().owner);
+        assert_eq!(1u64, query_ref(deps.as_ref(), mock_env(), String::from("ETH")).unwrap().rate);
+
+        // a pre-0.3.0 config blob counts as well
+        let mut deps = mock_dependencies(&[]);
+        legacy_config(deps.as_mut().storage).save(&legacy_state(HashMap::new())).unwrap();
+        let info = mock_info("creator", &[]);
+        let err = instantiate(deps.as_mut(), mock_env(), info, InstantiateMsg::default()).unwrap_err();
+        assert!(matches!(err, ContractError::AlreadyInitialized {}));
+    }
+
+    #[test]
+    fn query_basket_value() {
+        let mut deps = mock_dependencies(&[]);
+
+        let msg = InstantiateMsg::default();
+        let info = mock_info("creator", &[]);
+        let _res = instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+        let info = mock_info("creator", &[]);
+        let msg = ExecuteMsg::Relay { symbols: vec![String::from("ETH"), String::from("BTC")], rates: vec![3000000000000u64, 45000000000000u64], resolve_times: vec![100u64, 100u64], request_ids: vec![1u64, 1u64] };
+        let _res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+        let basket = |base: &str, holdings: &[(&str, u128)], skip_missing: bool| {
+            let holdings = holdings.iter().map(|(symbol, amount)| (String::from(*symbol), Uint128::from(*amount))).collect();
+            let msg = QueryMsg::GetBasketValue { base: String::from(base), holdings, skip_missing };
+            query(deps.as_ref(), mock_env(), msg).map(|res| from_binary::<BasketValue>(&res).unwrap())
+        };
+        // 2.5 ETH and 0.1 BTC
+        let value = basket("USD", &[("ETH", 25 * E18 / 10), ("BTC", E18 / 10)], false).unwrap();
+        assert_eq!(BasketValue { value: Uint128::from(12000u128 * E18), symbols: vec![String::from("ETH"), String::from("BTC")] }, value);
+        let value = basket("ETH", &[("ETH", 25 * E18 / 10), ("BTC", E18 / 10)], false).unwrap();
+        assert_eq!(Uint128::from(4u128 * E18), value.value);
+
+        assert!(basket("USD", &[("ETH", E18), ("BAND", E18)], false).is_err());
+        let value = basket("USD", &[("ETH", E18), ("BAND", E18)], true).unwrap();
+        assert_eq!(BasketValue { value: Uint128::from(3000u128 * E18), symbols: vec![String::from("ETH")] }, value);
+        assert_eq!(Uint128::zero(), basket("USD", &[], false).unwrap().value);
+
+        let err = basket("USD", &[("BTC", u128::MAX / 2), ("BTC", u128::MAX / 2)], false).unwrap_err();
+        assert_eq!(ContractError::Overflow {}.to_string(), err.to_string());
+    }
+
+    #[test]
+    fn query_reference_data_bulk_partial() {
+        let mut deps = mock_dependencies(&[]);
+
+        let msg = InstantiateMsg::default();
+        let info = mock_info("creator", &[]);
+        let _res = instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+        let info = mock_info("creator", &[]);
+        let msg = ExecuteMsg::Relay { symbols: vec![String::from("ETH"), String::from("BTC")], rates: vec![3000000000000u64, 45000000000000u64], resolve_times: vec![100u64, 100u64], request_ids: vec![1u64, 1u64] };
+        let _res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+        let pairs = vec![(String::from("ETH"), String::from("USD")), (String::from("ATOM"), String::from("USD")), (String::from("BTC"), String::from("ETH"))];
+        let res = query(deps.as_ref(), mock_env(), QueryMsg::GetReferenceDataBulkPartial { pairs }).unwrap();
+        let value: PartialBulkResponse = from_binary(&res).unwrap();
+        let expected = |base: &str, quote: &str| Some(query_reference_data(deps.as_ref(), mock_env(), String::from(base), String::from(quote), None).unwrap());
+        assert_eq!(PartialBulkResponse { results: vec![expected("ETH", "USD"), None, expected("BTC", "ETH")], failed_indices: vec![1] }, value);
+    }
+
+    #[test]
+    fn transfer_ownership_to_contract() {
+        let mut deps = mock_dependencies(&[]);
+        let info = mock_info("creator", &[]);
+        let _res = instantiate(deps.as_mut(), mock_env(), info, InstantiateMsg::default()).unwrap();
+
+        let msg = ExecuteMsg::TransferOwnershipToContract { contract_addr: String::from("dao") };
+        let err = execute(deps.as_mut(), mock_env(), mock_info("intruder", &[]), msg.clone()).unwrap_err();
+        assert!(matches!(err, ContractError::Unauthorized {}));
+
+        let res = execute(deps.as_mut(), mock_env(), mock_info("creator", &[]), msg.clone()).unwrap();
+        assert_eq!(1, res.submessages.len());
+        assert_eq!(TRANSFER_OWNERSHIP_REPLY_ID, res.submessages[0].id);
+        assert_eq!(ReplyOn::Always, res.submessages[0].reply_on);
+        let callback = to_binary(&OwnershipCallbackMsg::AcknowledgeOwnership {}).unwrap();
+        assert_eq!(CosmosMsg::Wasm(WasmMsg::Execute { contract_addr: String::from("dao"), msg: callback, send: vec![] }), res.submessages[0].msg);
+
+        // a failed acknowledgement leaves the owner in place
+        let failed = Reply { id: TRANSFER_OWNERSHIP_REPLY_ID, result: ContractResult::Err(String::from("not supported")) };
+        let _res = reply(deps.as_mut(), mock_env(), failed).unwrap();
+        let state = config_read(&deps.storage).load().unwrap();
+        assert_eq!(Some(Addr::unchecked("creator")), state.owner);
+        assert!(PENDING_CONTRACT_OWNER.may_load(&deps.storage).unwrap().is_none());
+
+        let _res = execute(deps.as_mut(), mock_env(), mock_info("creator", &[]), msg).unwrap();
+        let acknowledged = Reply { id: TRANSFER_OWNERSHIP_REPLY_ID, result: ContractResult::Ok(SubcallResponse { events: vec![], data: None }) };
+        let res = reply(deps.as_mut(), mock_env(), acknowledged).unwrap();
+        assert_eq!(attr("owner", "dao"), res.attributes[1]);
+        let state = config_read(&deps.storage).load().unwrap();
+        assert_eq!(Some(Addr::unchecked("dao")), state.owner);
+        assert!(PENDING_CONTRACT_OWNER.may_load(&deps.storage).unwrap().is_none());
+
+        let unknown = Reply { id: 7, result: ContractResult::Err(String::new()) };
+        let err = reply(deps.as_mut(), mock_env(), unknown).unwrap_err();
+        assert!(matches!(err, ContractError::UnknownReplyId { id: 7 }));
+    }
+
+    #[test]
+    fn query_reference_data_by_denom() {
+        let mut deps = mock_dependencies(&[]);
+        let info = mock_info("creator", &[]);
+        let _res = instantiate(deps.as_mut(), mock_env(), info, InstantiateMsg::default()).unwrap();
+
+        let info = mock_info("creator", &[]);
+        let msg = ExecuteMsg::Relay { symbols: vec![String::from("ATOM")], rates: vec![10000000000u64], resolve_times: vec![100u64], request_ids: vec![1u64] };
+        let _res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+        let msg = ExecuteMsg::SetDenom { denom: String::from("uatom"), symbol: String::from("ATOM") };
+        let err = execute(deps.as_mut(), mock_env(), mock_info("intruder", &[]), msg.clone()).unwrap_err();
+        assert!(matches!(err, ContractError::Unauthorized {}));
+        let res = execute(deps.as_mut(), mock_env(), mock_info("creator", &[]), msg).unwrap();
+        assert_eq!(vec![attr("action", "set_denom"), attr("denom", "uatom"), attr("symbol", "ATOM")], res.attributes);
+        let msg = ExecuteMsg::SetDenom { denom: String::from("uusd"), symbol: String::from("USD") };
+        let _res = execute(deps.as_mut(), mock_env(), mock_info("creator", &[]), msg).unwrap();
+
+        let by_denom = QueryMsg::GetReferenceDataByDenom { base_denom: String::from("uatom"), quote_denom: String::from("uusd") };
+        let by_symbol = QueryMsg::GetReferenceData { base: String::from("ATOM"), quote: String::from("USD"), decimals: None };
+        let value: ReferenceData = from_binary(&query(deps.as_ref(), mock_env(), by_denom).unwrap()).unwrap();
+        assert_eq!(Uint128::from(10u128 * E18), value.rate);
+        assert_eq!(query(deps.as_ref(), mock_env(), by_symbol).unwrap(), to_binary(&value).unwrap());
 
-        assert_eq!(ReferenceData{rate: BigUint::from(8928571428571428571428571u128), last_updated_base: BigUint::from(1571797419879305533u128), last_updated_quote: BigUint::from(1625108298000000000u128)}, value);
+        let msg = QueryMsg::GetReferenceDataByDenom { base_denom: String::from("uatom"), quote_denom: String::from("ibc/27394FB0") };
+        let err = query(deps.as_ref(), mock_env(), msg).unwrap_err();
+        assert!(matches!(err, ContractError::UnknownDenom { denom } if denom == "ibc/27394FB0"));
     }
 }
@@ -9,11 +9,113 @@ pub enum ContractError {
     #[error("Unauthorized")]
     Unauthorized {},
 
-    #[error("Invalid array length")]
-    DifferentArrayLength {},
+    #[error("Invalid array length: symbols {symbols_len}, rates {rates_len}, resolve_times {resolve_times_len}, request_ids {request_ids_len}")]
+    DifferentArrayLength { symbols_len: usize, rates_len: usize, resolve_times_len: usize, request_ids_len: usize, std_devs_len: Option<usize>, decimals_len: Option<usize> },
+
+    #[error("Batch of {size} symbols exceeds the maximum of {max}")]
+    BatchTooLarge { size: usize, max: u32 },
+
+    #[error("Relay must contain at least one symbol")]
+    EmptyRelay {},
+
+    #[error("Duplicate symbol in relay: {symbol}")]
+    DuplicateSymbol { symbol: String },
 
     #[error("Ref data is not available")]
     RefDataNotAvailable {},
+
+    #[error("Symbol not found: {symbol}")]
+    SymbolNotFound { symbol: String },
+
+    #[error("No quote has reference data")]
+    NoReferenceData {},
+
+    #[error("Cannot divide by zero quote rate")]
+    DivideByZero {},
+
+    #[error("Rate does not fit in 128 bits")]
+    Overflow {},
+
+    #[error("Stale data for symbol: {symbol}")]
+    StaleData { symbol: String },
+
+    #[error("Ref data for {symbol} is too old: {age} seconds")]
+    RefDataTooOld { symbol: String, age: u64 },
+
+    #[error("Resolve time for {symbol} is in the future")]
+    ResolveTimeInFuture { symbol: String },
+
+    #[error("Rate change for {symbol} exceeds the allowed threshold")]
+    RateJumpTooLarge { symbol: String },
+
+    #[error("{symbol} was updated too recently")]
+    RelayTooSoon { symbol: String },
+
+    #[error("Signed rates are not enabled")]
+    SignedRatesDisabled {},
+
+    #[error("Rate for {symbol} is negative")]
+    NegativeRate { symbol: String },
+
+    #[error("Invalid signature")]
+    InvalidSignature {},
+
+    #[error("Signed payload is for another contract or chain")]
+    WrongSignatureDomain {},
+
+    #[error("Nonce {nonce} is not above the last used nonce {last}")]
+    NonceAlreadyUsed { nonce: u64, last: u64 },
+
+    #[error("Contract is not initialized")]
+    NotInitialized {},
+
+    #[error("Contract is already initialized")]
+    AlreadyInitialized {},
+
+    #[error("Contract is paused")]
+    ContractPaused {},
+
+    #[error("TWAP samples must be between 1 and {max}")]
+    InvalidTwapSamples { max: u32 },
+
+    #[error("Invalid decimals")]
+    InvalidDecimals {},
+
+    #[error("Invalid symbol: {symbol}")]
+    InvalidSymbol { symbol: String },
+
+    #[error("Symbol is frozen: {symbol}")]
+    SymbolFrozen { symbol: String },
+
+    #[error("Invalid alias: {alias}")]
+    InvalidAlias { alias: String },
+
+    #[error("Symbol is an alias: {symbol}")]
+    SymbolIsAlias { symbol: String },
+
+    #[error("Unknown denom: {denom}")]
+    UnknownDenom { denom: String },
+
+    #[error("Invalid symbol separator")]
+    InvalidSeparator {},
+
+    #[error("USD rate must be nonzero")]
+    InvalidUsdRate {},
+
+    #[error("Cannot change usd_decimals while usd_rate is set")]
+    UsdRatePinned {},
+
+    #[error("Cannot migrate from a different contract or a newer version")]
+    CannotMigrate {},
+
+    #[error("Unknown storage schema version: {found}")]
+    UnknownSchemaVersion { found: u32 },
+
+    #[error("Migrating the original release needs an owner")]
+    MissingOwner {},
+
+    #[error("Unknown reply id: {id}")]
+    UnknownReplyId { id: u64 },
     // Add any other custom errors you like here.
     // Look at https://docs.rs/thiserror/1.0.21/thiserror/ for details.
 }
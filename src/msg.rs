@@ -1,36 +1,473 @@
 use schemars::JsonSchema;
-use serde::{Deserialize, Serialize};
-use crate::state::State;
-use num::BigUint;
+use serde::{Deserialize, Deserializer, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+use cosmwasm_std::{Addr, Binary, Uint128};
+use crate::state::{RefData, RoundingMode, UsdTimestampMode};
+use vectorize;
 
-#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema, Default)]
 pub struct InstantiateMsg {
+    /// Owner of the contract, defaults to the sender, e.g. a DAO when deployed by a factory.
+    pub owner: Option<String>,
+    /// Decimals of the `GetReferenceData` rate, defaults to 18.
+    pub base_decimals: Option<u32>,
+    /// Decimals of the synthetic USD rate, defaults to 9.
+    pub usd_decimals: Option<u32>,
+    /// Exact synthetic USD rate at `usd_decimals`, overriding `10^usd_decimals`; must be nonzero, and keeps
+    /// `usd_decimals` from being changed afterwards.
+    pub usd_rate: Option<u64>,
+    /// Extra character allowed in symbols besides `A-Z` and `0-9`, e.g. `"_"`.
+    pub symbol_separator: Option<String>,
+    /// Refs to seed at instantiation, validated like a `Relay`.
+    pub initial_symbols: Option<Vec<String>>,
+    pub initial_rates: Option<Vec<u64>>,
+    pub initial_resolve_times: Option<Vec<u64>>,
+    pub initial_request_ids: Option<Vec<u64>>,
+    /// Prefix for execute event types and attribute keys, e.g. `"band"` emits `action=band.relay` and
+    /// `band.num_symbols`; unprefixed by default.
+    pub event_namespace: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema, Default)]
+pub struct MigrateMsg {
+    /// Owner for a deployment of the original release, which stored no owner; ignored otherwise.
+    pub owner: Option<String>,
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 #[serde(rename_all = "snake_case")]
 pub enum ExecuteMsg {
+    /// Relayers must send a nonzero resolve time; entries with `resolve_time == 0` are treated as unavailable.
     Relay { symbols: Vec<String>, rates: Vec<u64>, resolve_times: Vec<u64>, request_ids: Vec<u64> },
+    /// Same as `Relay`, with each symbol's fields grouped into one entry.
+    RelayV2 { entries: Vec<RelayEntry> },
+    /// Same as `Relay`, but rejects the whole batch if any symbol is older than its stored value.
+    RelayStrict { symbols: Vec<String>, rates: Vec<u64>, resolve_times: Vec<u64>, request_ids: Vec<u64> },
+    /// Same as `Relay`, with a standard deviation (confidence) reported for every symbol.
+    RelayWithStdDev { symbols: Vec<String>, rates: Vec<u64>, resolve_times: Vec<u64>, request_ids: Vec<u64>, std_devs: Vec<u64> },
+    /// Same as `Relay`, with each rate's own precision instead of the configured `usd_decimals`.
+    RelayWithDecimals { symbols: Vec<String>, rates: Vec<u64>, resolve_times: Vec<u64>, request_ids: Vec<u64>, decimals: Vec<u32> },
+    /// Same as `Relay` for feeds that can go negative; requires `allow_signed_rates`.
+    RelaySignedRates { symbols: Vec<String>, rates: Vec<i64>, resolve_times: Vec<u64>, request_ids: Vec<u64> },
+    /// Re-attests stored symbols: only `resolve_time` and `request_id` change, everything else is kept.
+    /// Every symbol must already exist; otherwise validated and skipped like `Relay`.
+    Heartbeat { symbols: Vec<String>, resolve_times: Vec<u64>, request_ids: Vec<u64> },
+    /// Owner-only relay that always overwrites, even with an older resolve time.
+    ForceRelay { symbols: Vec<String>, rates: Vec<u64>, resolve_times: Vec<u64>, request_ids: Vec<u64> },
+    /// Relays a JSON `SignedRelayPayload` signed by a registered relayer key; the signature is over the SHA-256 of
+    /// `payload`. Anyone may submit it, and entries are skipped like `Relay` when they are not newer than the stored value.
+    RelaySigned { payload: Binary, signature: Binary, pubkey: Binary },
+    RemoveSymbol { symbols: Vec<String> },
+    /// Owner-only: removes up to 100 symbols starting with `prefix` per call; the `has_more` attribute
+    /// tells whether another call is needed.
+    RemoveByPrefix { prefix: String },
+    /// Owner-only: removes every ref and resets the symbol count and last relay; config is kept.
+    Clear {},
+    /// Relays and removes symbols in one message; nothing is written unless both parts succeed.
+    BatchUpdate { relays: RelayPayload, remove: Vec<String> },
+    AddRelayer { address: String },
+    RemoveRelayer { address: String },
+    /// Adds a relayer that may only relay `delay_secs` after this message executes.
+    AddRelayerDelayed { address: String, delay_secs: u64 },
+    /// Compressed or uncompressed secp256k1 key accepted by `RelaySigned`.
+    AddRelayerPubkey { pubkey: Binary },
+    RemoveRelayerPubkey { pubkey: Binary },
+    TransferOwnership { new_owner: String },
+    AcceptOwnership {},
+    /// Owner-only: hands ownership to a contract in one step; it is sent `AcknowledgeOwnership` and only
+    /// becomes owner if that call succeeds.
+    TransferOwnershipToContract { contract_addr: String },
+    /// Owner-only: removes the owner for good; relayers keep relaying but nothing can be reconfigured.
+    RenounceOwnership {},
+    Pause {},
+    Unpause {},
+    /// Owner-only: every relay touching `symbol` fails with `SymbolFrozen` until it is unfrozen.
+    FreezeSymbol { symbol: String },
+    UnfreezeSymbol { symbol: String },
+    SetSymbolRelayers { symbol: String, relayers: Vec<String> },
+    /// Owner-only: makes reference data queries for `alias` read `symbol`, which must be stored and not an alias itself.
+    /// The alias name cannot be relayed while it is set, and removing `symbol` removes the alias with it.
+    SetAlias { alias: String, symbol: String },
+    RemoveAlias { alias: String },
+    /// Owner-only: maps a bank denom to a symbol for `GetReferenceDataByDenom`, replacing any previous mapping.
+    SetDenom { denom: String, symbol: String },
+    /// Owner-only. Omitted fields are left unchanged; `null` clears the nullable checks, disabling them.
+    UpdateConfig {
+        owner: Option<String>,
+        #[serde(default, skip_serializing_if = "Option::is_none", deserialize_with = "nullable")]
+        max_staleness_secs: Option<Option<u64>>,
+        #[serde(default, skip_serializing_if = "Option::is_none", deserialize_with = "nullable")]
+        max_future_skew_secs: Option<Option<u64>>,
+        #[serde(default, skip_serializing_if = "Option::is_none", deserialize_with = "nullable")]
+        max_rate_change_bps: Option<Option<u16>>,
+        base_decimals: Option<u32>,
+        usd_decimals: Option<u32>,
+        max_batch_size: Option<u32>,
+        /// Minimum time between two writes of the same symbol; `ForceRelay` is exempt.
+        #[serde(default, skip_serializing_if = "Option::is_none", deserialize_with = "nullable")]
+        min_relay_interval_secs: Option<Option<u64>>,
+        allow_signed_rates: Option<bool>,
+        rounding: Option<RoundingMode>,
+        /// Reject refs relayed with `request_id == 0` as unavailable.
+        strict_request_id: Option<bool>,
+        /// A fixed `USD` timestamp keeps its `last_updated_*` the same across blocks.
+        usd_timestamp_mode: Option<UsdTimestampMode>,
+        /// Samples kept per symbol for `GetTwap`; lowering it drops the oldest on the next relay.
+        twap_samples: Option<u32>,
+    },
+}
+
+/// Sent by `TransferOwnershipToContract` to the proposed owner, which must handle it without error.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum OwnershipCallbackMsg {
+    AcknowledgeOwnership {},
+}
+
+/// Tells an explicit `null` (`Some(None)`) apart from an omitted field (`None`, via `#[serde(default)]`).
+fn nullable<'de, T, D>(deserializer: D) -> Result<Option<Option<T>>, D::Error>
+where
+    T: Deserialize<'de>,
+    D: Deserializer<'de>,
+{
+    Option::<T>::deserialize(deserializer).map(Some)
+}
+
+/// JSON `data` of every relay response, for callers reading it from a submessage reply.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct RelayResponse {
+    pub num_updated: u32,
+    pub relay_seq: u64,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct RelayEntry {
+    pub symbol: String,
+    pub rate: u64,
+    pub resolve_time: u64,
+    pub request_id: u64,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema, Default)]
+pub struct RelayPayload {
+    pub symbols: Vec<String>,
+    pub rates: Vec<u64>,
+    pub resolve_times: Vec<u64>,
+    pub request_ids: Vec<u64>,
+}
+
+/// What a `RelaySigned` key signs. The contract address and chain id pin it to one deployment, and `nonce` must
+/// be above the key's last accepted nonce, so a payload is only ever applied once.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct SignedRelayPayload {
+    pub contract_addr: String,
+    pub chain_id: String,
+    pub nonce: u64,
+    pub relays: RelayPayload,
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 #[serde(rename_all = "snake_case")]
 pub enum QueryMsg {
-    GetRefs {},
-    GetReferenceData { base: String, quote: String },
+    /// Every stored ref, sorted by symbol.
+    GetAllRefs {},
+    /// The refs for `symbols` in the same order, `null` for symbols that were never relayed.
+    GetRefs { symbols: Vec<String> },
+    GetRefsPaginated { start_after: Option<String>, limit: Option<u32> },
+    /// Sorted symbol names only, paginated like `GetRefsPaginated`.
+    GetSymbols { start_after: Option<String>, limit: Option<u32> },
+    /// Sorted symbols whose stored `request_id` matches among one page of `limit` refs after `start_after`;
+    /// continue from `next_start_after` until it is `null`.
+    GetSymbolsByRequestId { request_id: u64, start_after: Option<String>, limit: Option<u32> },
+    /// Sorted symbols whose stored `resolve_time` is at or after `resolve_time` (nanoseconds), paged like
+    /// `GetSymbolsByRequestId`.
+    GetUpdatedSince { resolve_time: u64, start_after: Option<String>, limit: Option<u32> },
+    GetRef { symbol: String },
+    /// `symbol`'s USD rate exactly as `GetReferenceData` reads it: aliases resolved, and unresolved
+    /// (`resolve_time == 0`) or negative refs rejected.
+    GetRefData { symbol: String },
+    /// Time-weighted average of `symbol`'s rate over the last `window_secs`, from its recent samples.
+    GetTwap { symbol: String, window_secs: u64 },
+    GetSymbolCount {},
+    /// The symbol with the smallest resolve time and that time, `null` when nothing is stored.
+    GetOldestResolveTime {},
+    /// Whether `symbol` has been relayed; `USD` is always present.
+    HasSymbol { symbol: String },
+    GetContractVersion {},
+    /// `decimals` scales the returned rate to `10^decimals` instead of the configured precision.
+    GetReferenceData { base: String, quote: String, decimals: Option<u32> },
+    GetReferenceDataFull { base: String, quote: String },
+    GetReferenceDataWithAge { base: String, quote: String },
+    /// `GetReferenceData` with the requested `base` and `quote` echoed back.
+    GetLabeledReferenceData { base: String, quote: String },
+    /// `GetReferenceData` together with both legs' stored refs (or the synthetic `USD` ref), for debugging.
+    GetReferenceDataVerbose { base: String, quote: String },
+    /// `GetReferenceData` over caller-supplied rates at the configured `usd_decimals`, nothing is read from storage
+    /// except the config; a zero `quote_rate` fails with a division by zero.
+    PreviewReferenceData { base_rate: u64, quote_rate: u64, base_resolve_time: u64, quote_resolve_time: u64 },
+    /// `GetReferenceData` scaled to `10^result_decimals`, e.g. 6 for a USDC-priced quote; the extra digits are
+    /// dropped by the configured rounding, truncating by default. `result_decimals` must be at most 38.
+    GetReferenceDataScaled { base: String, quote: String, result_decimals: u32 },
+    /// Like `GetReferenceData`, but `null` instead of an error when either leg is missing or unresolved.
+    TryGetReferenceData { base: String, quote: String },
+    /// `GetReferenceData` split into whole units and the remainder at the configured precision.
+    GetReferenceDataSplit { base: String, quote: String },
+    /// `quote/base`, the same as `GetReferenceData` with the arguments swapped; a zero `base` rate fails with a division by zero.
+    GetInverseReferenceData { base: String, quote: String },
+    /// `GetReferenceData` for signed feeds: the configured rounding applies to the magnitude and `negative` carries the sign.
+    GetReferenceDataSigned { base: String, quote: String },
+    /// Like `GetReferenceData`, but missing legs and a zero quote come back as a `status` instead of an error.
+    GetReferenceDataStatus { base: String, quote: String },
+    /// Both legs' stored rates without dividing them.
+    GetRawReferenceData { base: String, quote: String },
+    /// `base/quote` derived from the `base/USD` and `quote/USD` rates rather than dividing the stored rates directly.
+    GetCrossRate { base: String, quote: String },
+    GetReferenceDataBulk { pairs: Vec<(String, String)> },
+    /// Like `GetReferenceDataBulk`, but a failing pair reports its error instead of failing the whole query.
+    GetReferenceDataTryBulk { pairs: Vec<(String, String)> },
+    /// Median of `base/quote` over `quotes`, the mean of the middle two for an even count. With `skip_missing`,
+    /// quotes that are missing or unresolved are left out instead of failing the query.
+    GetMedianReferenceData { base: String, quotes: Vec<String>, skip_missing: bool },
+    /// Value of `holdings` in `base`: the sum of `amount * symbol/base`, each term truncated, with amounts and
+    /// the value at the same precision (e.g. 1e18 units). `skip_missing` works as in `GetMedianReferenceData`.
+    GetBasketValue { base: String, holdings: Vec<(String, Uint128)>, skip_missing: bool },
+    /// Like `GetReferenceDataTryBulk`, but failures are `null` results whose positions are listed in `failed_indices`.
+    GetReferenceDataBulkPartial { pairs: Vec<(String, String)> },
+    /// One base against many quotes, results aligned with `quotes`.
+    GetReferenceDataMulti { base: String, quotes: Vec<String> },
+    GetRelayers {},
+    GetOwnership {},
+    /// Every config field in one response; refs are queried separately.
+    GetConfig {},
+    GetLastRelay {},
+    IsPaused {},
+    /// Liveness summary for monitoring in a single query.
+    Health {},
+    GetSymbolRelayers { symbol: String },
+    /// Last nonce accepted from `pubkey` through `RelaySigned`, `null` before its first signed relay.
+    GetSignerNonce { pubkey: Binary },
+    /// The symbol `alias` resolves to, `null` when it is not an alias.
+    GetAlias { alias: String },
+    /// `GetReferenceData` with each side given as a denom mapped by `SetDenom`.
+    GetReferenceDataByDenom { base_denom: String, quote_denom: String },
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct ConfigResponse {
+    /// Serialized as `[symbol, ref]` pairs sorted by symbol.
+    #[serde(with="vectorize")]
+    pub refs: BTreeMap<String, RefData>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct RefsPageResponse {
+    pub refs: Vec<(String, RefData)>,
+    pub symbols: Vec<String>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct SymbolsPageResponse {
+    pub symbols: Vec<String>,
+    /// Last symbol scanned when the page was full, `None` once every ref has been scanned.
+    pub next_start_after: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct RelayersResponse {
+    pub relayers: Vec<Addr>,
+    /// Block time (nanoseconds) each relayer becomes active, zero if already active.
+    pub active_after: Vec<u64>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct OwnershipResponse {
+    pub owner: Option<Addr>,
+    pub pending_owner: Option<Addr>,
 }
 
-pub type ConfigResponse = State;
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct FullConfigResponse {
+    pub owner: Option<Addr>,
+    pub pending_owner: Option<Addr>,
+    pub relayers: Vec<Addr>,
+    pub paused: bool,
+    #[serde(with="vectorize")]
+    pub symbol_relayers: BTreeMap<String, Vec<Addr>>,
+    pub symbol_count: u64,
+    pub max_staleness_secs: Option<u64>,
+    pub base_decimals: u32,
+    pub usd_decimals: u32,
+    pub usd_rate: Option<u64>,
+    pub symbol_separator: Option<String>,
+    pub max_future_skew_secs: Option<u64>,
+    pub max_rate_change_bps: Option<u16>,
+    pub max_batch_size: u32,
+    #[serde(with="vectorize")]
+    pub relayer_active_after: BTreeMap<Addr, u64>,
+    pub last_relay_time: u64,
+    pub last_relay_block: u64,
+    pub relayer_pubkeys: Vec<Binary>,
+    pub min_relay_interval_secs: Option<u64>,
+    pub allow_signed_rates: bool,
+    pub relay_seq: u64,
+    pub rounding: RoundingMode,
+    pub frozen: BTreeSet<String>,
+    pub strict_request_id: bool,
+    pub event_namespace: String,
+    pub usd_timestamp_mode: UsdTimestampMode,
+    pub twap_samples: u32,
+}
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct HealthResponse {
+    pub paused: bool,
+    pub symbol_count: u64,
+    /// Block time in nanoseconds, zero before the first relay.
+    pub last_relay_time: u64,
+    /// Smallest resolve time across every stored ref, `None` when nothing is stored.
+    pub oldest_resolve_time: Option<u64>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct LastRelayResponse {
+    /// Block time in nanoseconds.
+    pub last_relay_time: u64,
+    pub last_relay_block: u64,
+    pub relay_seq: u64,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 pub struct RefDataResponse {
-    pub rate: BigUint,
-    pub last_update: BigUint,
+    pub rate: Uint128,
+    pub last_update: Uint128,
+    pub request_id: u64,
+    pub std_dev: Option<u64>,
+    pub decimals: u32,
+    pub negative: bool,
 }
 
-#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 pub struct ReferenceData {
-    pub rate: BigUint,
-    pub last_updated_base: BigUint,
-    pub last_updated_quote: BigUint,
+    pub rate: Uint128,
+    pub last_updated_base: Uint128,
+    pub last_updated_quote: Uint128,
+}
+
+/// `rate` is at the symbol's own `decimals`, like `RefData`, and truncated. When the oldest sample is
+/// younger than the window, the average only covers the time since that sample.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct TwapResponse {
+    pub rate: Uint128,
+    pub decimals: u32,
+    /// Samples that contributed, including the one in effect at the start of the window.
+    pub num_samples: u32,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct LabeledReferenceData {
+    pub base: String,
+    pub quote: String,
+    pub rate: Uint128,
+    pub last_updated_base: Uint128,
+    pub last_updated_quote: Uint128,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct ReferenceDataVerbose {
+    pub data: ReferenceData,
+    pub base: RefData,
+    pub quote: RefData,
+}
+
+/// `ReferenceData` with each leg's age at the query's block time, in whole seconds; `USD` is always 0.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct ReferenceDataWithAge {
+    pub rate: Uint128,
+    pub last_updated_base: Uint128,
+    pub last_updated_quote: Uint128,
+    pub base_age_secs: u64,
+    pub quote_age_secs: u64,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct SignedReferenceData {
+    pub rate: Uint128,
+    pub negative: bool,
+    pub last_updated_base: Uint128,
+    pub last_updated_quote: Uint128,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct MedianReferenceData {
+    pub rate: Uint128,
+    /// Quotes that contributed to the median, in the order given.
+    pub symbols: Vec<String>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct BasketValue {
+    pub value: Uint128,
+    /// Holdings that were valued, in the order given.
+    pub symbols: Vec<String>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct PartialBulkResponse {
+    /// Aligned with the requested pairs.
+    pub results: Vec<Option<ReferenceData>>,
+    /// Ascending.
+    pub failed_indices: Vec<u32>,
+}
+
+/// Exactly one of `data` and `error` is set.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct ReferenceDataResult {
+    pub data: Option<ReferenceData>,
+    pub error: Option<String>,
+}
+
+/// `integer * 10^decimals + fraction` is the `GetReferenceData` rate.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct ReferenceDataSplit {
+    pub integer: Uint128,
+    pub fraction: Uint128,
+    pub decimals: u32,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum RefStatus {
+    Ok,
+    BaseMissing,
+    QuoteMissing,
+    QuoteZero,
+}
+
+/// `data` is only set when `status` is `ok`.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct ReferenceDataStatus {
+    pub status: RefStatus,
+    pub data: Option<ReferenceData>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct ReferenceDataFull {
+    pub rate: Uint128,
+    pub last_updated_base: Uint128,
+    pub last_updated_quote: Uint128,
+    /// Zero for the synthetic USD leg.
+    pub base_request_id: u64,
+    pub quote_request_id: u64,
+    pub base_std_dev: Option<u64>,
+    pub quote_std_dev: Option<u64>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct RawReferenceData {
+    pub base_rate: u64,
+    pub quote_rate: u64,
+    pub last_updated_base: u64,
+    pub last_updated_quote: u64,
+    pub base_request_id: u64,
+    pub quote_request_id: u64,
 }
@@ -0,0 +1,128 @@
+use cosmwasm_std::{Addr, Uint128};
+use num::{BigUint, ToPrimitive};
+
+use crate::error::ContractError;
+use crate::msg::ReferenceData;
+use crate::state::{RefData, RoundingMode};
+
+/// `10^decimals`, `None` past 38 decimals.
+pub const fn scale(decimals: u32) -> Option<u128> {
+    10u128.checked_pow(decimals)
+}
+
+/// The synthetic `USD` ref: `usd_rate`, or exactly one at `usd_decimals`, always resolved at `time`.
+pub fn usd_ref_data(usd_rate: Option<u64>, usd_decimals: u32, time: u64, updated_by: Addr) -> RefData {
+    RefData {
+        rate: usd_rate.unwrap_or_else(|| 10u64.pow(usd_decimals)),
+        resolve_time: time,
+        request_id: 0,
+        std_dev: None,
+        updated_by,
+        decimals: usd_decimals,
+        negative: false,
+        relay_seq: 0,
+    }
+}
+
+/// `base/quote` at `10^base_decimals`, from the legs' magnitudes; checking signs, staleness and
+/// availability is left to the caller. A zero quote rate fails with `DivideByZero`.
+pub fn compute_reference_data(base: &RefData, quote: &RefData, base_decimals: u32, rounding: &RoundingMode) -> Result<ReferenceData, ContractError> {
+    if quote.rate == 0 {
+        return Err(ContractError::DivideByZero {});
+    }
+    let scale = scale(base_decimals).ok_or(ContractError::InvalidDecimals {})?;
+    Ok(ReferenceData {
+        rate: ratio(base.rate.into(), base.decimals, quote.rate.into(), quote.decimals, scale, rounding)?,
+        last_updated_base: Uint128::from(base.resolve_time),
+        last_updated_quote: Uint128::from(quote.resolve_time),
+    })
+}
+
+/// `value * scale / divisor`, truncated. The product of a u64 rate and 10^decimals can exceed a u128
+/// once decimals passes 19, so it is taken in BigUint and only the quotient must fit in a `Uint128`.
+pub fn mul_div(value: Uint128, scale: u128, divisor: Uint128) -> Result<Uint128, ContractError> {
+    let result = BigUint::from(value.u128()) * BigUint::from(scale) / BigUint::from(divisor.u128());
+    result.to_u128().map(Uint128::from).ok_or(ContractError::Overflow {})
+}
+
+/// `base/quote * scale`, with each rate first brought back from its own `10^decimals` precision.
+/// `quote_rate` must be nonzero.
+pub fn ratio(base_rate: u128, base_decimals: u32, quote_rate: u128, quote_decimals: u32, scale: u128, rounding: &RoundingMode) -> Result<Uint128, ContractError> {
+    let ten = BigUint::from(10u32);
+    let numerator = BigUint::from(base_rate) * BigUint::from(scale) * ten.pow(quote_decimals);
+    let denominator = BigUint::from(quote_rate) * ten.pow(base_decimals);
+    let quotient = match rounding {
+        RoundingMode::Trunc => numerator / denominator,
+        RoundingMode::Nearest => (numerator + &denominator / 2u32) / denominator,
+        RoundingMode::Ceil => (numerator + &denominator - 1u32) / denominator,
+    };
+    quotient.to_u128().map(Uint128::from).ok_or(ContractError::Overflow {})
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::contract::{DEFAULT_BASE_DECIMALS, DEFAULT_USD_DECIMALS};
+
+    const E18: u128 = 1_000_000_000_000_000_000;
+
+    fn ref_data(rate: u64, resolve_time: u64, decimals: u32) -> RefData {
+        RefData { rate, resolve_time, request_id: 1, std_dev: None, updated_by: Addr::unchecked("relayer"), decimals, negative: false, relay_seq: 1 }
+    }
+
+    #[test]
+    fn compute_cross_rate() {
+        let eth = ref_data(3000000000000, 100, 9);
+        let btc = ref_data(45000000000000, 200, 9);
+        let value = compute_reference_data(&eth, &btc, 18, &RoundingMode::Trunc).unwrap();
+        assert_eq!(ReferenceData { rate: Uint128::from(66666666666666666u128), last_updated_base: Uint128::from(100u64), last_updated_quote: Uint128::from(200u64) }, value);
+        let value = compute_reference_data(&eth, &btc, 18, &RoundingMode::Ceil).unwrap();
+        assert_eq!(Uint128::from(66666666666666667u128), value.rate);
+
+        // legs at different precisions are normalized first
+        let usdc = ref_data(1000000, 300, 6);
+        let value = compute_reference_data(&eth, &usdc, 18, &RoundingMode::Trunc).unwrap();
+        assert_eq!(Uint128::from(3000u128 * E18), value.rate);
+    }
+
+    #[test]
+    fn compute_with_usd() {
+        let eth = ref_data(3000000000000, 100, 9);
+        let usd = usd_ref_data(None, 9, 500, Addr::unchecked("contract"));
+        let value = compute_reference_data(&eth, &usd, 18, &RoundingMode::Trunc).unwrap();
+        assert_eq!(ReferenceData { rate: Uint128::from(3000u128 * E18), last_updated_base: Uint128::from(100u64), last_updated_quote: Uint128::from(500u64) }, value);
+        let value = compute_reference_data(&usd, &eth, 9, &RoundingMode::Trunc).unwrap();
+        assert_eq!(Uint128::from(333333u128), value.rate);
+
+        // a custom USD rate of 0.5 doubles every USD price
+        let usd = usd_ref_data(Some(500000000), 9, 500, Addr::unchecked("contract"));
+        let value = compute_reference_data(&eth, &usd, 18, &RoundingMode::Trunc).unwrap();
+        assert_eq!(Uint128::from(6000u128 * E18), value.rate);
+    }
+
+    #[test]
+    fn compute_errors() {
+        let eth = ref_data(3000000000000, 100, 9);
+        let err = compute_reference_data(&eth, &ref_data(0, 100, 9), 18, &RoundingMode::Trunc).unwrap_err();
+        assert!(matches!(err, ContractError::DivideByZero {}));
+        let err = compute_reference_data(&eth, &eth, 39, &RoundingMode::Trunc).unwrap_err();
+        assert!(matches!(err, ContractError::InvalidDecimals {}));
+        let err = compute_reference_data(&ref_data(u64::MAX, 100, 0), &ref_data(1, 100, 9), 38, &RoundingMode::Trunc).unwrap_err();
+        assert!(matches!(err, ContractError::Overflow {}));
+    }
+
+    #[test]
+    fn scale_limits() {
+        assert_eq!(Some(1), scale(0));
+        assert_eq!(Some(E18), scale(18));
+        assert_eq!(Some(10u128.pow(38)), scale(38));
+        assert_eq!(None, scale(39));
+    }
+
+    #[test]
+    fn default_scales() {
+        assert_eq!(Some(10u128.pow(18)), scale(DEFAULT_BASE_DECIMALS));
+        assert_eq!(1_000_000_000, 10u64.pow(DEFAULT_USD_DECIMALS));
+        assert_eq!(1_000_000_000, usd_ref_data(None, DEFAULT_USD_DECIMALS, 0, Addr::unchecked("contract")).rate);
+    }
+}
@@ -1,29 +1,245 @@
 use schemars::JsonSchema;
-use std::collections::HashMap;
-use cosmwasm_std::Storage;
+use std::collections::{BTreeSet, HashMap};
+use cosmwasm_std::{Addr, Binary, Storage};
 use cosmwasm_storage::{singleton, singleton_read, ReadonlySingleton, Singleton};
+use cw_storage_plus::{Item, Map, U64Key};
 use serde::{Deserialize, Serialize};
 use vectorize;
 
 pub static CONFIG_KEY: &[u8] = b"config";
+/// Stored from 0.9.0 on; older deployments derive it from their cw2 version.
+pub static SCHEMA_VERSION_KEY: &[u8] = b"schema_version";
+
+pub const REFS: Map<&str, RefData> = Map::new("refs");
+
+/// `(resolve_time, symbol) -> symbol` for every entry of `REFS`, so the oldest ref is the first key.
+pub const RESOLVE_TIMES: Map<(U64Key, &str), String> = Map::new("resolve_times");
+
+/// Block time in nanoseconds of each symbol's last write, for `State::min_relay_interval_secs`.
+pub const LAST_WRITES: Map<&str, u64> = Map::new("last_writes");
+
+/// Owner-managed `alias -> symbol` names resolved by reference data queries; always a single hop to a stored symbol.
+pub const ALIASES: Map<&str, String> = Map::new("aliases");
+
+/// Owner-managed `denom -> symbol` mapping, e.g. `uatom -> ATOM`, for querying by Cosmos SDK denom.
+pub const DENOMS: Map<&str, String> = Map::new("denoms");
+
+/// Most recent `(rate, resolve_time)` samples of each symbol, oldest first, for `GetTwap`. Only samples at the
+/// symbol's current decimals and sign are kept; a change of either starts a new history.
+pub const SAMPLES: Map<&str, Vec<RateSample>> = Map::new("samples");
+
+/// Last nonce accepted from each `RelaySigned` key, by raw key bytes; a payload must carry a larger one.
+pub const SIGNER_NONCES: Map<&[u8], u64> = Map::new("signer_nonces");
+
+/// Contract proposed by `TransferOwnershipToContract`, made owner by `reply` once its acknowledgement succeeds.
+pub const PENDING_CONTRACT_OWNER: Item<Addr> = Item::new("pending_contract_owner");
+
+pub const NANOS_PER_SECOND: u64 = 1_000_000_000;
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 pub struct RefData {
     pub rate: u64,
+    /// Unix time in nanoseconds, the same unit as `env.block.time`.
     pub resolve_time: u64,
     pub request_id: u64,
+    #[serde(default)]
+    pub std_dev: Option<u64>,
+    /// Sender of the relay that last wrote this entry, or the base64 signing key for `RelaySigned`.
+    #[serde(default = "unknown_updater")]
+    pub updated_by: Addr,
+    /// Precision of `rate`, i.e. the rate is the USD price times `10^decimals`.
+    #[serde(default = "legacy_decimals")]
+    pub decimals: u32,
+    /// Sign of `rate`, only ever set when `State::allow_signed_rates` is on; zero is never negative.
+    #[serde(default)]
+    pub negative: bool,
+    /// `State::relay_seq` of the relay that wrote this entry, zero for entries written before 0.8.0.
+    #[serde(default)]
+    pub relay_seq: u64,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct RateSample {
+    pub rate: u64,
+    pub resolve_time: u64,
+}
+
+impl RefData {
+    pub fn resolve_time_nanos(&self) -> u64 {
+        self.resolve_time
+    }
+
+    /// Whole seconds, truncated.
+    pub fn resolve_time_secs(&self) -> u64 {
+        self.resolve_time / NANOS_PER_SECOND
+    }
+
+    pub fn signed_rate(&self) -> i128 {
+        if self.negative {
+            -(self.rate as i128)
+        } else {
+            self.rate as i128
+        }
+    }
+}
+
+/// Placeholder for entries stored before `updated_by` existed, replaced on migration.
+fn unknown_updater() -> Addr {
+    Addr::unchecked("")
+}
+
+/// Entries stored before `decimals` existed use the configured `usd_decimals`, which the migration writes in.
+fn legacy_decimals() -> u32 {
+    9
+}
+
+/// Resolve time reported for the synthetic `USD` leg.
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum UsdTimestampMode {
+    /// The query's block time.
+    #[default]
+    BlockTime,
+    Zero,
+    MaxU64,
+}
+
+/// How `GetReferenceData` rounds the last digit of a rate.
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum RoundingMode {
+    /// Toward zero.
+    #[default]
+    Trunc,
+    /// Half up.
+    Nearest,
+    /// Up, away from zero.
+    Ceil,
+}
+
+pub const DEFAULT_MAX_BATCH_SIZE: u32 = 256;
+
+fn default_max_batch_size() -> u32 {
+    DEFAULT_MAX_BATCH_SIZE
+}
+
+pub const DEFAULT_TWAP_SAMPLES: u32 = 8;
+/// Bounds the samples read and rewritten on every relay.
+pub const MAX_TWAP_SAMPLES: u32 = 32;
+
+fn default_twap_samples() -> u32 {
+    DEFAULT_TWAP_SAMPLES
 }
 
 #[derive(Serialize, Deserialize, Debug)]
 pub struct State {
+    /// `None` once ownership has been renounced, which permanently disables every owner-only message.
+    pub owner: Option<Addr>,
+    pub pending_owner: Option<Addr>,
+    pub relayers: Vec<Addr>,
+    pub paused: bool,
+    #[serde(with="vectorize")]
+    pub symbol_relayers: HashMap<String, Vec<Addr>>,
+    pub symbol_count: u64,
+    pub max_staleness_secs: Option<u64>,
+    pub base_decimals: u32,
+    pub usd_decimals: u32,
+    /// Synthetic USD rate when it is not a power of ten; `None` uses `10^usd_decimals`.
+    #[serde(default)]
+    pub usd_rate: Option<u64>,
+    #[serde(default)]
+    pub symbol_separator: Option<String>,
+    /// How far past the block time a relayed resolve time may be.
+    #[serde(default)]
+    pub max_future_skew_secs: Option<u64>,
+    /// Largest accepted move from the stored rate, in basis points; `ForceRelay` is exempt.
+    #[serde(default)]
+    pub max_rate_change_bps: Option<u16>,
+    /// Most symbols a single relay may carry.
+    #[serde(default = "default_max_batch_size")]
+    pub max_batch_size: u32,
+    /// Block time (nanoseconds) before which a delayed relayer in `relayers` is not yet active.
+    #[serde(default, with="vectorize")]
+    pub relayer_active_after: HashMap<Addr, u64>,
+    /// Block time (nanoseconds) and height of the last successful relay, zero if none yet.
+    #[serde(default)]
+    pub last_relay_time: u64,
+    #[serde(default)]
+    pub last_relay_block: u64,
+    /// secp256k1 keys whose signed payloads anyone may submit through `RelaySigned`.
+    #[serde(default)]
+    pub relayer_pubkeys: Vec<Binary>,
+    #[serde(default)]
+    pub min_relay_interval_secs: Option<u64>,
+    /// Accept `RelaySignedRates`; unsigned queries still reject negative legs.
+    #[serde(default)]
+    pub allow_signed_rates: bool,
+    /// Number of successful relays so far; never reset, not even by `Clear`.
+    #[serde(default)]
+    pub relay_seq: u64,
+    #[serde(default)]
+    pub rounding: RoundingMode,
+    /// Symbols no relay may update; their last value stays queryable.
+    #[serde(default)]
+    pub frozen: BTreeSet<String>,
+    /// Treat refs with `request_id == 0` as never fetched from the oracle.
+    #[serde(default)]
+    pub strict_request_id: bool,
+    /// Prefix of every execute response's `action` value and other attribute keys, e.g. `band` gives
+    /// `action=band.relay`; empty leaves them unprefixed.
+    #[serde(default)]
+    pub event_namespace: String,
+    #[serde(default)]
+    pub usd_timestamp_mode: UsdTimestampMode,
+    /// Samples kept per symbol for `GetTwap`, 1 to `MAX_TWAP_SAMPLES`.
+    #[serde(default = "default_twap_samples")]
+    pub twap_samples: u32,
+}
+
+/// Layout of `State` in the original release: only the refs, with no owner and no cw2 version.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct InitialState {
+    #[serde(with="vectorize")]
+    pub refs: HashMap<String, RefData>,
+}
+
+/// Layout of `State` in 0.2.x, when every ref was still stored inside the config blob next to the owner.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct LegacyState {
+    pub owner: Addr,
+    pub pending_owner: Option<Addr>,
+    pub relayers: Vec<Addr>,
+    pub paused: bool,
+    #[serde(with="vectorize")]
+    pub symbol_relayers: HashMap<String, Vec<Addr>>,
+    pub symbol_count: u64,
+    pub max_staleness_secs: Option<u64>,
+    pub base_decimals: u32,
+    pub usd_decimals: u32,
     #[serde(with="vectorize")]
     pub refs: HashMap<String, RefData>,
 }
 
-pub fn config(storage: &mut dyn Storage) -> Singleton<State> {
+pub fn config(storage: &mut dyn Storage) -> Singleton<'_, State> {
+    singleton(storage, CONFIG_KEY)
+}
+
+pub fn config_read(storage: &dyn Storage) -> ReadonlySingleton<'_, State> {
+    singleton_read(storage, CONFIG_KEY)
+}
+
+pub fn schema_version(storage: &mut dyn Storage) -> Singleton<'_, u32> {
+    singleton(storage, SCHEMA_VERSION_KEY)
+}
+
+pub fn schema_version_read(storage: &dyn Storage) -> ReadonlySingleton<'_, u32> {
+    singleton_read(storage, SCHEMA_VERSION_KEY)
+}
+
+pub fn legacy_config(storage: &mut dyn Storage) -> Singleton<'_, LegacyState> {
     singleton(storage, CONFIG_KEY)
 }
 
-pub fn config_read(storage: &dyn Storage) -> ReadonlySingleton<State> {
+pub fn initial_config_read(storage: &dyn Storage) -> ReadonlySingleton<'_, InitialState> {
     singleton_read(storage, CONFIG_KEY)
 }